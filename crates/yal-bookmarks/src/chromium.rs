@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Deserialize;
+
+use crate::{Browser, Entry, EntryKind};
+
+#[derive(Deserialize)]
+struct BookmarksFile {
+    roots: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Node {
+    Url {
+        name: String,
+        url: String,
+    },
+    Folder {
+        name: String,
+        #[serde(default)]
+        children: Vec<Node>,
+    },
+}
+
+/// Parses the `Bookmarks` JSON file written by Chromium based browsers.
+pub fn parse_bookmarks(json: &str) -> Result<Vec<Entry>> {
+    let file: BookmarksFile = serde_json::from_str(json)?;
+    let mut entries = Vec::new();
+    // `roots` also holds bookkeeping keys (e.g. `sync_transaction_version`), so
+    // only keep values that parse as folders.
+    for root in file.roots.into_values() {
+        if let Ok(node) = serde_json::from_value::<Node>(root) {
+            collect(node, None, &mut entries);
+        }
+    }
+    Ok(entries)
+}
+
+fn collect(node: Node, folder: Option<&str>, out: &mut Vec<Entry>) {
+    match node {
+        Node::Url { name, url } => out.push(Entry {
+            title: name,
+            url,
+            folder: folder.map(str::to_string),
+            browser: Browser::Chromium,
+            kind: EntryKind::Bookmark,
+        }),
+        Node::Folder { name, children } => {
+            for child in children {
+                collect(child, Some(&name), out);
+            }
+        }
+    }
+}
+
+/// Reads the most visited pages from a Chromium `History` database.
+pub fn read_history(db: &Path, limit: usize) -> Result<Vec<Entry>> {
+    let snapshot = crate::open_snapshot(db)?;
+    query_history(&snapshot.conn, limit)
+}
+
+fn query_history(conn: &Connection, limit: usize) -> Result<Vec<Entry>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, title FROM urls
+         WHERE hidden = 0
+         ORDER BY visit_count DESC, last_visit_time DESC
+         LIMIT ?1",
+    )?;
+    let rows = stmt.query_map([limit as i64], |row| {
+        Ok(Entry {
+            url: row.get(0)?,
+            title: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            folder: None,
+            browser: Browser::Chromium,
+            kind: EntryKind::History,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
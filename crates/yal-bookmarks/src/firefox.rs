@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::{Browser, Entry, EntryKind};
+
+/// Reads bookmarks and, if `history_limit > 0`, the most frecent history
+/// entries from a Firefox `places.sqlite` database.
+pub fn read_places(db: &Path, history_limit: usize) -> Result<Vec<Entry>> {
+    let snapshot = crate::open_snapshot(db)?;
+    let mut entries = query_bookmarks(&snapshot.conn)?;
+    if history_limit > 0 {
+        entries.extend(query_history(&snapshot.conn, history_limit)?);
+    }
+    Ok(entries)
+}
+
+fn query_bookmarks(conn: &Connection) -> Result<Vec<Entry>> {
+    // type 1 = bookmark, 2 = folder, 3 = separator
+    let mut stmt = conn.prepare(
+        "SELECT b.title, p.url, parent.title
+         FROM moz_bookmarks b
+         JOIN moz_places p ON p.id = b.fk
+         LEFT JOIN moz_bookmarks parent ON parent.id = b.parent
+         WHERE b.type = 1 AND p.url NOT LIKE 'place:%'
+         ORDER BY b.parent, b.position",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Entry {
+            title: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+            url: row.get(1)?,
+            folder: row.get::<_, Option<String>>(2)?.filter(|f| !f.is_empty()),
+            browser: Browser::Firefox,
+            kind: EntryKind::Bookmark,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn query_history(conn: &Connection, limit: usize) -> Result<Vec<Entry>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, title FROM moz_places
+         WHERE visit_count > 0 AND hidden = 0
+         ORDER BY frecency DESC
+         LIMIT ?1",
+    )?;
+    let rows = stmt.query_map([limit as i64], |row| {
+        Ok(Entry {
+            url: row.get(0)?,
+            title: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            folder: None,
+            browser: Browser::Firefox,
+            kind: EntryKind::History,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
@@ -0,0 +1,373 @@
+//! Browser bookmark and history indexing.
+//!
+//! Reads Chromium `Bookmarks`/`History` and Firefox `places.sqlite` from a set
+//! of profile directories. [`Index`] keeps the parsed entries and only re-reads
+//! a file when its modification time changes.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+pub mod chromium;
+pub mod firefox;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+    /// Chrome, Chromium, Brave, Edge, Arc, ...
+    Chromium,
+    Firefox,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    Bookmark,
+    History,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub title: String,
+    pub url: String,
+    pub folder: Option<String>,
+    pub browser: Browser,
+    pub kind: EntryKind,
+}
+
+/// A browser profile directory, e.g. `~/Library/Application Support/Google/Chrome/Default`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Profile {
+    pub browser: Browser,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    ChromiumBookmarks,
+    ChromiumHistory,
+    FirefoxPlaces,
+}
+
+struct Source {
+    file: PathBuf,
+    format: Format,
+    modified: Option<SystemTime>,
+    entries: Vec<Entry>,
+}
+
+impl Source {
+    fn new(file: PathBuf, format: Format) -> Self {
+        Self {
+            file,
+            format,
+            modified: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Latest mtime of the file and, for sqlite, its write-ahead log.
+    fn mtime(&self) -> Option<SystemTime> {
+        let mtime = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+        let wal = match self.format {
+            Format::ChromiumBookmarks => None,
+            _ => mtime(&wal_path(&self.file)),
+        };
+        mtime(&self.file).max(wal)
+    }
+
+    fn load(&self, history_limit: usize) -> Result<Vec<Entry>> {
+        match self.format {
+            Format::ChromiumBookmarks => {
+                chromium::parse_bookmarks(&fs::read_to_string(&self.file)?)
+            }
+            Format::ChromiumHistory => chromium::read_history(&self.file, history_limit),
+            Format::FirefoxPlaces => firefox::read_places(&self.file, history_limit),
+        }
+    }
+}
+
+pub struct Index {
+    profiles: Vec<Profile>,
+    history_limit: usize,
+    sources: Vec<Source>,
+}
+
+impl Index {
+    /// `history_limit` caps the history entries read per profile; 0 disables
+    /// history and only indexes bookmarks.
+    pub fn new(profiles: Vec<Profile>, history_limit: usize) -> Self {
+        let sources = profiles
+            .iter()
+            .flat_map(|p| match p.browser {
+                Browser::Chromium => {
+                    let mut s = vec![Source::new(
+                        p.path.join("Bookmarks"),
+                        Format::ChromiumBookmarks,
+                    )];
+                    if history_limit > 0 {
+                        s.push(Source::new(p.path.join("History"), Format::ChromiumHistory));
+                    }
+                    s
+                }
+                Browser::Firefox => {
+                    vec![Source::new(
+                        p.path.join("places.sqlite"),
+                        Format::FirefoxPlaces,
+                    )]
+                }
+            })
+            .collect();
+        Self {
+            profiles,
+            history_limit,
+            sources,
+        }
+    }
+
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    pub fn history_limit(&self) -> usize {
+        self.history_limit
+    }
+
+    /// Re-reads every file whose modification time changed since the last
+    /// refresh. Returns whether anything was reloaded.
+    pub fn refresh(&mut self) -> bool {
+        let mut changed = false;
+        for source in &mut self.sources {
+            let mtime = source.mtime();
+            if mtime == source.modified {
+                continue;
+            }
+            source.modified = mtime;
+            source.entries = match mtime {
+                None => Vec::new(),
+                Some(_) => source.load(self.history_limit).unwrap_or_else(|e| {
+                    log::warn!("Failed to read {}: {}", source.file.display(), e);
+                    Vec::new()
+                }),
+            };
+            changed = true;
+        }
+        changed
+    }
+
+    /// Bookmarks first, then history, each URL listed once.
+    pub fn entries(&self) -> Vec<&Entry> {
+        let mut seen = HashSet::new();
+        let all = self.sources.iter().flat_map(|s| &s.entries);
+        let bookmarks = all.clone().filter(|e| e.kind == EntryKind::Bookmark);
+        let history = all.filter(|e| e.kind == EntryKind::History);
+        bookmarks
+            .chain(history)
+            .filter(|e| seen.insert(e.url.as_str()))
+            .collect()
+    }
+}
+
+/// Profiles of the common macOS browsers that exist on this machine.
+pub fn default_profiles() -> Vec<Profile> {
+    let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+        return Vec::new();
+    };
+    let support = home.join("Library/Application Support");
+
+    let chromium = [
+        "Google/Chrome/Default",
+        "Chromium/Default",
+        "BraveSoftware/Brave-Browser/Default",
+        "Microsoft Edge/Default",
+        "Arc/User Data/Default",
+        "Vivaldi/Default",
+    ]
+    .into_iter()
+    .map(|dir| support.join(dir))
+    .filter(|p| p.join("Bookmarks").exists() || p.join("History").exists())
+    .map(|path| Profile {
+        browser: Browser::Chromium,
+        path,
+    });
+
+    let firefox = fs::read_dir(support.join("Firefox/Profiles"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.join("places.sqlite").exists())
+        .map(|path| Profile {
+            browser: Browser::Firefox,
+            path,
+        });
+
+    chromium.chain(firefox).collect()
+}
+
+fn wal_path(db: &Path) -> PathBuf {
+    let mut name = db.as_os_str().to_owned();
+    name.push("-wal");
+    PathBuf::from(name)
+}
+
+/// A connection to a copy of a browser database. Browsers keep their databases
+/// locked while running, so we never open the original.
+struct Snapshot {
+    conn: Connection,
+    _dir: tempfile::TempDir,
+}
+
+fn open_snapshot(db: &Path) -> Result<Snapshot> {
+    let dir = tempfile::tempdir()?;
+    let copy = dir.path().join("snapshot.sqlite");
+    fs::copy(db, &copy)?;
+    let wal = wal_path(db);
+    if wal.exists() {
+        fs::copy(&wal, wal_path(&copy))?;
+    }
+    let conn = Connection::open(&copy)?;
+    Ok(Snapshot { conn, _dir: dir })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(path)
+    }
+
+    fn firefox_profile(dir: &Path) -> Profile {
+        let sql = fs::read_to_string(fixture("firefox/places.sql")).unwrap();
+        Connection::open(dir.join("places.sqlite"))
+            .unwrap()
+            .execute_batch(&sql)
+            .unwrap();
+        Profile {
+            browser: Browser::Firefox,
+            path: dir.to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn parses_chromium_bookmarks() {
+        let json = fs::read_to_string(fixture("chromium/Bookmarks")).unwrap();
+        let entries = chromium::parse_bookmarks(&json).unwrap();
+        let urls = entries.iter().map(|e| e.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                "https://github.com/",
+                "https://docs.rs/",
+                "https://tauri.app/",
+                "https://news.ycombinator.com/",
+            ]
+        );
+        assert_eq!(entries[1].folder.as_deref(), Some("Rust"));
+        assert!(entries.iter().all(|e| e.kind == EntryKind::Bookmark));
+    }
+
+    #[test]
+    fn reads_firefox_places() {
+        let dir = tempfile::tempdir().unwrap();
+        let profile = firefox_profile(dir.path());
+        let entries = firefox::read_places(&profile.path.join("places.sqlite"), 10).unwrap();
+
+        let bookmarks = entries
+            .iter()
+            .filter(|e| e.kind == EntryKind::Bookmark)
+            .map(|e| (e.title.as_str(), e.folder.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bookmarks,
+            vec![("MDN", Some("toolbar")), ("Rust", Some("Languages"))]
+        );
+
+        let history = entries
+            .iter()
+            .filter(|e| e.kind == EntryKind::History)
+            .map(|e| e.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            vec![
+                "https://www.rust-lang.org/",
+                "https://developer.mozilla.org/",
+                "https://example.com/visited"
+            ]
+        );
+    }
+
+    #[test]
+    fn index_dedups_and_refreshes_on_change() {
+        let chrome = tempfile::tempdir().unwrap();
+        let bookmarks = chrome.path().join("Bookmarks");
+        fs::copy(fixture("chromium/Bookmarks"), &bookmarks).unwrap();
+        let firefox = tempfile::tempdir().unwrap();
+
+        let mut index = Index::new(
+            vec![
+                Profile {
+                    browser: Browser::Chromium,
+                    path: chrome.path().to_path_buf(),
+                },
+                firefox_profile(firefox.path()),
+            ],
+            10,
+        );
+        assert!(index.refresh());
+        assert!(!index.refresh());
+
+        let entries = index.entries();
+        assert_eq!(entries.len(), 7);
+        let rust = entries
+            .iter()
+            .filter(|e| e.url == "https://www.rust-lang.org/")
+            .collect::<Vec<_>>();
+        assert_eq!(rust.len(), 1);
+        assert_eq!(rust[0].kind, EntryKind::Bookmark);
+
+        let json = r#"{"roots": {"other": {"type": "folder", "name": "Other", "children": [
+            {"type": "url", "name": "Only", "url": "https://only.example/"}
+        ]}}}"#;
+        fs::write(&bookmarks, json).unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&bookmarks)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        assert!(index.refresh());
+        let urls = index
+            .entries()
+            .iter()
+            .map(|e| e.url.clone())
+            .collect::<Vec<_>>();
+        assert!(urls.contains(&"https://only.example/".to_string()));
+        assert!(!urls.contains(&"https://github.com/".to_string()));
+    }
+
+    #[test]
+    fn missing_files_are_empty() {
+        let mut index = Index::new(
+            vec![Profile {
+                browser: Browser::Chromium,
+                path: PathBuf::from("/does/not/exist"),
+            }],
+            10,
+        );
+        assert!(!index.refresh());
+        assert!(index.entries().is_empty());
+    }
+}
@@ -0,0 +1,36 @@
+-- Subset of the Firefox places schema used by the reader.
+CREATE TABLE moz_places (
+    id INTEGER PRIMARY KEY,
+    url LONGVARCHAR,
+    title LONGVARCHAR,
+    visit_count INTEGER DEFAULT 0,
+    hidden INTEGER DEFAULT 0 NOT NULL,
+    frecency INTEGER DEFAULT -1 NOT NULL
+);
+
+CREATE TABLE moz_bookmarks (
+    id INTEGER PRIMARY KEY,
+    type INTEGER,
+    fk INTEGER DEFAULT NULL,
+    parent INTEGER,
+    position INTEGER,
+    title LONGVARCHAR
+);
+
+INSERT INTO moz_places (id, url, title, visit_count, hidden, frecency) VALUES
+    (1, 'https://www.rust-lang.org/', 'Rust Programming Language', 12, 0, 200),
+    (2, 'https://developer.mozilla.org/', 'MDN Web Docs', 4, 0, 150),
+    (3, 'https://example.com/visited', 'Example', 1, 0, 50),
+    (4, 'https://example.com/never', 'Never visited', 0, 0, 10),
+    (5, 'https://example.com/redirect', 'Hidden redirect', 3, 1, 100),
+    (6, 'place:sort=8&maxResults=10', 'Most Visited', 0, 0, 0);
+
+INSERT INTO moz_bookmarks (id, type, fk, parent, position, title) VALUES
+    (1, 2, NULL, 0, 0, ''),
+    (2, 2, NULL, 1, 0, 'menu'),
+    (3, 2, NULL, 1, 1, 'toolbar'),
+    (5, 2, NULL, 1, 3, 'unfiled'),
+    (6, 2, NULL, 2, 0, 'Languages'),
+    (10, 1, 2, 3, 0, 'MDN'),
+    (11, 1, 1, 6, 0, 'Rust'),
+    (12, 1, 6, 3, 1, 'Most Visited');
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use serde::de::DeserializeOwned;
+use yal_core::{AppConfig, Snippet, Theme};
+use yal_plugin::manager::{
+    self,
+    config::{PluginConfig, PluginKind},
+};
+use yal_template::Template;
+
+use crate::themes;
+
+pub fn check() -> Result<()> {
+    let mut problems = 0;
+
+    let config = load::<AppConfig>(&yal_config::config_path(), &mut problems);
+    let themes = load::<Vec<Theme>>(&yal_config::themes_path(), &mut problems);
+    let snippets = load::<Vec<Snippet>>(&yal_config::snippets_path(), &mut problems);
+    let plugins = load::<PluginConfig>(&manager::plugins_config_path(), &mut problems);
+
+    let mut report = |message: String| {
+        println!("  {}", message);
+        problems += 1;
+    };
+    if let Some(themes) = &themes {
+        themes::check_themes(themes)
+            .into_iter()
+            .for_each(&mut report);
+    }
+    if let (Some(config), Some(themes)) = (&config, &themes)
+        && let Some(name) = &config.theme
+        && !themes.iter().any(|t| t.name.as_ref() == Some(name))
+        && yal_theme::by_name(name).is_none()
+    {
+        report(format!("config.lua: unknown theme '{}'", name));
+    }
+    for snippet in snippets.iter().flatten() {
+        if let Err(e) = Template::parse(&snippet.text) {
+            report(format!("snippet '{}': {}", snippet.name, e));
+        }
+    }
+    let plugins = plugins.unwrap_or_default();
+    for (i, entry) in plugins.iter().enumerate() {
+        if plugins[..i].iter().any(|p| p.name == entry.name) {
+            report(format!(
+                "plugin '{}' is configured more than once",
+                entry.name
+            ));
+        }
+        if entry.kind == Some(PluginKind::Process) && entry.exec.is_none() {
+            report(format!(
+                "plugin '{}' is a process plugin without `exec`",
+                entry.name
+            ));
+        }
+    }
+
+    if problems > 0 {
+        bail!("found {} problems", problems);
+    }
+    println!("Config ok");
+    Ok(())
+}
+
+/// Loads one file, printing its status. `None` if it exists but is invalid.
+fn load<T: DeserializeOwned + Default>(path: &Path, problems: &mut usize) -> Option<T> {
+    if !path.exists() {
+        println!("-  {} (not present, using defaults)", path.display());
+        return Some(T::default());
+    }
+    match yal_config::try_load_config::<T>(path) {
+        Ok(value) => {
+            println!("ok {}", path.display());
+            Some(value)
+        }
+        Err(e) => {
+            println!("!! {}: {:#}", path.display(), e);
+            *problems += 1;
+            None
+        }
+    }
+}
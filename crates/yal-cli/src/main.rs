@@ -0,0 +1,194 @@
+//! Command-line companion to YAL. Works on the files under `~/.config/yal` and
+//! the plugin checkouts directly, so the app does not need to be running.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+use yal_plugin::manager::config::HttpMode;
+
+mod config;
+mod plugins;
+mod themes;
+
+#[derive(Parser)]
+#[command(
+    name = "yal-cli",
+    version,
+    about = "Manage YAL plugins, themes and config"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Install, update and inspect plugins from plugins.lua
+    #[command(subcommand)]
+    Plugins(PluginsCommand),
+    /// Inspect and validate themes
+    #[command(subcommand)]
+    Themes(ThemesCommand),
+    /// Inspect config files
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand)]
+enum PluginsCommand {
+    /// List configured plugins and whether they are installed
+    List,
+    /// Clone every configured plugin that is not installed yet
+    Install,
+    /// Fast-forward installed plugins to their upstream branch
+    Update {
+        /// Only update this plugin
+        name: Option<String>,
+    },
+    /// Delete a plugin checkout
+    Remove { name: String },
+    /// Accept local changes to a plugin checkout
+    Trust { name: String },
+    /// Delete what plugins no longer in plugins.lua left behind
+    Prune {
+        /// Do not ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Load every plugin and report the ones that fail
+    Doctor,
+    /// Run a plugin's *_spec.lua files against a mock UI
+    Test {
+        /// Plugin directory (defaults to the current directory)
+        dir: Option<PathBuf>,
+        /// Config passed to `init`, as JSON (defaults to the plugin's entry in plugins.lua)
+        #[arg(long)]
+        config: Option<String>,
+        /// Where yal.http requests go; cassettes live in cassettes/<spec>.json
+        #[arg(long, value_enum, default_value_t = Http::Replay)]
+        http: Http,
+    },
+    /// Scaffold a new plugin
+    New {
+        name: String,
+        /// Parent directory (defaults to the current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Http {
+    /// Use the network
+    Live,
+    /// Use the network and rewrite each spec's cassette
+    Record,
+    /// Answer from cassettes; unrecorded requests fail
+    Replay,
+}
+
+#[derive(Subcommand)]
+enum ThemesCommand {
+    /// List built-in and user themes
+    List,
+    /// Print the colors of a theme
+    Show { name: String },
+    /// Check a themes file (defaults to themes.lua)
+    Validate { file: Option<PathBuf> },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Parse every config file and report errors
+    Check,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Plugins(cmd) => match cmd {
+            PluginsCommand::List => plugins::list().await,
+            PluginsCommand::Install => plugins::install().await,
+            PluginsCommand::Update { name } => plugins::update(name.as_deref()).await,
+            PluginsCommand::Remove { name } => plugins::remove(&name).await,
+            PluginsCommand::Trust { name } => plugins::trust(&name).await,
+            PluginsCommand::Prune { yes } => plugins::prune(yes).await,
+            PluginsCommand::Doctor => plugins::doctor().await,
+            PluginsCommand::Test { dir, config, http } => {
+                let http = match http {
+                    Http::Live => HttpMode::Live,
+                    Http::Record => HttpMode::Record,
+                    Http::Replay => HttpMode::Replay,
+                };
+                plugins::test(dir, config.as_deref(), http).await
+            }
+            PluginsCommand::New { name, path } => plugins::new(&name, path),
+        },
+        Command::Themes(cmd) => match cmd {
+            ThemesCommand::List => themes::list(),
+            ThemesCommand::Show { name } => themes::show(&name),
+            ThemesCommand::Validate { file } => themes::validate(file),
+        },
+        Command::Config(ConfigCommand::Check) => config::check(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use yal_core::Theme;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn scaffolds_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = dir.path().join("hello-world");
+        plugins::scaffold("hello-world", &plugin).unwrap();
+
+        let init = std::fs::read_to_string(plugin.join("init.lua")).unwrap();
+        assert!(init.contains("name = \"hello-world\""));
+        assert!(init.contains("function M.execute(req)"));
+        assert!(init.contains(&format!(
+            "api_version = \"{}\"",
+            yal_plugin::api::current_requirement()
+        )));
+        assert!(plugin.join("vendor").is_dir());
+
+        // Never overwrites an existing plugin.
+        assert!(plugins::scaffold("hello-world", &plugin).is_err());
+        assert!(plugins::scaffold("../escape", &dir.path().join("x")).is_err());
+    }
+
+    #[test]
+    fn reports_theme_problems() {
+        let theme = |name: Option<&str>, bg: &str| Theme {
+            name: name.map(str::to_string),
+            bg_color: Some(bg.to_string()),
+            fg_color: Some("rgb(10, 20, 30)".to_string()),
+            bg_font_color: Some("white".to_string()),
+            fg_font_color: None,
+        };
+        assert!(themes::check_themes(&[theme(Some("ok"), "#1e1e2e")]).is_empty());
+
+        let problems = themes::check_themes(&[
+            theme(Some("dup"), "#fff"),
+            theme(Some("dup"), "#12345"),
+            theme(None, "#000"),
+        ]);
+        assert_eq!(
+            problems,
+            vec![
+                "theme 'dup' is defined more than once",
+                "theme 'dup': bg_color '#12345' is not a color",
+                "theme #3 has no name",
+            ]
+        );
+    }
+}
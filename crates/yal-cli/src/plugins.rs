@@ -0,0 +1,385 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use yal_core::PromptResponse;
+use yal_plugin::{
+    PluginManager, api,
+    backend::{Backend, RequestId},
+    manager::{
+        self,
+        config::HttpMode,
+        health::{PluginStatus, UpdateOutcome},
+        prune,
+    },
+};
+
+const INIT_TEMPLATE: &str = include_str!("../templates/init.lua");
+
+/// Backend for plugins loaded outside the app. Only `init` runs here, which
+/// must not prompt.
+#[derive(Clone)]
+struct NoopBackend;
+
+impl Backend for NoopBackend {
+    async fn prompt(&self, _prompt: yal_core::Prompt) -> Result<RequestId> {
+        bail!("prompts are not available from the command line")
+    }
+
+    async fn prompt_state(&self, _id: RequestId) -> Result<PromptResponse> {
+        bail!("prompts are not available from the command line")
+    }
+
+    async fn prompt_submission(&self, _id: RequestId) -> Result<PromptResponse> {
+        bail!("prompts are not available from the command line")
+    }
+
+    async fn prompt_cancel(&self, _id: RequestId) -> Result<()> {
+        Ok(())
+    }
+
+    async fn set_visibility(&self, _visible: bool) -> Result<()> {
+        Ok(())
+    }
+}
+
+async fn manager() -> Result<PluginManager<NoopBackend>> {
+    let mut manager = PluginManager::new(NoopBackend);
+    manager.init().await?;
+    manager.load_config().await?;
+    Ok(manager)
+}
+
+pub async fn list() -> Result<()> {
+    let manager = manager().await?;
+    if manager.config.is_empty() {
+        println!(
+            "No plugins configured in {}",
+            manager::plugins_config_path().display()
+        );
+        return Ok(());
+    }
+    let width = manager
+        .config
+        .iter()
+        .map(|p| p.name.len())
+        .max()
+        .unwrap_or(0);
+    for entry in &manager.config {
+        let state = if manager.plugin_dir(entry.source()).exists() {
+            "installed"
+        } else {
+            "not installed"
+        };
+        match &entry.source {
+            Some(source) if *source != entry.name => println!(
+                "{:width$}  {:13}  {} (instance of {})",
+                entry.name, state, entry.git, source
+            ),
+            _ => println!("{:width$}  {:13}  {}", entry.name, state, entry.git),
+        }
+    }
+    Ok(())
+}
+
+pub async fn install() -> Result<()> {
+    let mut manager = manager().await?;
+    let installed = manager.install().await?;
+    if installed.is_empty() {
+        println!("All configured plugins and their dependencies are installed");
+        return Ok(());
+    }
+    for name in installed {
+        println!("installed {}", name);
+    }
+    notify_app().await;
+    Ok(())
+}
+
+pub async fn update(name: Option<&str>) -> Result<()> {
+    let manager = manager().await?;
+    let names = match name {
+        Some(name) => vec![name.to_string()],
+        None => {
+            let mut sources: Vec<String> = Vec::new();
+            for entry in &manager.config {
+                let source = entry.source();
+                if manager.plugin_dir(source).exists() && !sources.iter().any(|s| s == source) {
+                    sources.push(source.to_string());
+                }
+            }
+            sources
+        }
+    };
+
+    let mut failed = 0;
+    let mut updated = false;
+    for name in &names {
+        match manager.update(name).await {
+            Ok(UpdateOutcome::UpToDate) => println!("{}: up to date", name),
+            Ok(UpdateOutcome::Updated { from, to }) => {
+                println!("{}: {} -> {}", name, from, to);
+                updated = true;
+            }
+            Err(e) => {
+                eprintln!("{}: {:#}", name, e);
+                failed += 1;
+            }
+        }
+    }
+    if updated {
+        notify_app().await;
+    }
+    if failed > 0 {
+        bail!("{} of {} plugins failed to update", failed, names.len());
+    }
+    Ok(())
+}
+
+pub async fn remove(name: &str) -> Result<()> {
+    let mut manager = manager().await?;
+    manager.remove(name).await?;
+    println!("removed {}", name);
+    if manager.config.iter().any(|p| p.source() == name) {
+        println!(
+            "'{}' is still listed in {} and will be reinstalled on the next install",
+            name,
+            manager::plugins_config_path().display()
+        );
+    }
+    notify_app().await;
+    Ok(())
+}
+
+pub async fn trust(name: &str) -> Result<()> {
+    let manager = manager().await?;
+    manager.trust(name)?;
+    println!("trusted {} as it is now", name);
+    notify_app().await;
+    Ok(())
+}
+
+pub async fn prune(yes: bool) -> Result<()> {
+    let manager = manager().await?;
+    let orphans = manager.orphans()?;
+    if orphans.is_empty() {
+        println!("Removed plugins left nothing behind");
+        return Ok(());
+    }
+    let width = orphans.iter().map(|o| o.name.len()).max().unwrap_or(0);
+    for orphan in &orphans {
+        let kinds: Vec<String> = orphan.items.iter().map(|(k, _)| k.to_string()).collect();
+        println!(
+            "{:width$}  {:>9}  {}",
+            orphan.name,
+            prune::human_size(orphan.bytes),
+            kinds.join(", ")
+        );
+    }
+    let total: u64 = orphans.iter().map(|o| o.bytes).sum();
+    println!("{:width$}  {:>9}", "total", prune::human_size(total));
+
+    if !yes {
+        print!("Delete these? [y/N] ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Kept everything");
+            return Ok(());
+        }
+    }
+    let freed = manager.prune(&orphans)?;
+    println!("freed {}", prune::human_size(freed));
+    Ok(())
+}
+
+pub async fn doctor() -> Result<()> {
+    let mut manager = manager().await?;
+    let mut seen = Vec::new();
+    for entry in &manager.config {
+        if seen.contains(&&entry.name) {
+            bail!("plugin '{}' is configured more than once", entry.name);
+        }
+        seen.push(&entry.name);
+        if let Some(other) = manager
+            .config
+            .iter()
+            .find(|e| e.source() == entry.source() && e.git != entry.git)
+        {
+            bail!(
+                "plugins '{}' and '{}' share the source '{}' but not the git URL",
+                entry.name,
+                other.name,
+                entry.source()
+            );
+        }
+    }
+
+    manager.load_plugins().await;
+    let report = manager.health_report();
+    let mut problems = 0;
+    for health in &report {
+        match &health.status {
+            PluginStatus::Loaded { version, commands } => {
+                println!(
+                    "ok       {} {} ({} commands)",
+                    health.name, version, commands
+                )
+            }
+            PluginStatus::Disabled => println!("off      {} (disabled)", health.name),
+            PluginStatus::NotInstalled => {
+                println!("missing  {} (run `yal-cli plugins install`)", health.name)
+            }
+            PluginStatus::Failed { error } => println!("failed   {}: {}", health.name, error),
+            PluginStatus::Incompatible { required, host } => println!(
+                "refused  {}: needs plugin API {}, this yal provides {}",
+                health.name, required, host
+            ),
+            PluginStatus::InvalidConfig { errors } => {
+                println!("invalid  {}: config does not match its schema", health.name);
+                for error in errors {
+                    println!("           {}", error);
+                }
+            }
+            PluginStatus::Untrusted { reason } => {
+                println!("untrusted {}: {}", health.name, reason)
+            }
+        }
+        for task in &health.tasks {
+            match (&task.error, task.running) {
+                (None, _) => println!("           task {}: running", task.name),
+                (Some(error), true) => {
+                    println!(
+                        "           task {}: running, last run failed: {}",
+                        task.name, error
+                    )
+                }
+                (Some(error), false) => {
+                    println!("           task {}: failed: {}", task.name, error)
+                }
+            }
+        }
+        if !health.is_ok() {
+            problems += 1;
+        }
+    }
+    if problems > 0 {
+        bail!("{} of {} plugins have problems", problems, report.len());
+    }
+    Ok(())
+}
+
+pub async fn test(dir: Option<PathBuf>, config: Option<&str>, http: HttpMode) -> Result<()> {
+    let dir = dir.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let config = match config {
+        Some(json) => Some(serde_json::from_str(json).context("--config is not valid JSON")?),
+        None => configured(&dir).await?,
+    };
+
+    let reports = yal_plugin_test::run_specs(&dir, config, http).await?;
+    if reports.is_empty() {
+        bail!("no *_spec.lua files in {}", dir.display());
+    }
+    let (mut passed, mut failed) = (0, 0);
+    for report in &reports {
+        println!("{}", report.file.display());
+        for case in &report.cases {
+            match &case.error {
+                None => {
+                    println!("  ok    {}", case.name);
+                    passed += 1;
+                }
+                Some(error) => {
+                    println!("  FAIL  {}", case.name);
+                    for line in error.lines() {
+                        println!("        {}", line);
+                    }
+                    failed += 1;
+                }
+            }
+        }
+    }
+    println!();
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        bail!("{} of {} cases failed", failed, passed + failed);
+    }
+    Ok(())
+}
+
+/// The config of the first plugins.lua entry running from a checkout named
+/// like `dir`, if any.
+async fn configured(dir: &Path) -> Result<Option<serde_json::Value>> {
+    let Some(name) = dir.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+        return Ok(None);
+    };
+    let manager = manager().await?;
+    Ok(manager
+        .config
+        .into_iter()
+        .find(|p| p.source() == name)
+        .and_then(|p| p.config))
+}
+
+pub fn new(name: &str, parent: Option<PathBuf>) -> Result<()> {
+    let dir = parent.unwrap_or_else(|| PathBuf::from(".")).join(name);
+    scaffold(name, &dir)?;
+    let dir = dir.canonicalize()?;
+    println!("Created {}", dir.display());
+    println!();
+    println!("To try it, link it into the plugins directory and add it to plugins.lua:");
+    println!(
+        "  ln -s {} {}",
+        dir.display(),
+        manager::plugin_dir(name).display()
+    );
+    println!(
+        "  {{ name = \"{}\", git = \"file://{}\" }}",
+        name,
+        dir.display()
+    );
+    Ok(())
+}
+
+pub(crate) fn scaffold(name: &str, dir: &Path) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("plugin names may only contain letters, digits, '-' and '_'");
+    }
+    if dir.exists() && dir.read_dir()?.next().is_some() {
+        bail!("{} already exists and is not empty", dir.display());
+    }
+
+    std::fs::create_dir_all(dir.join("vendor"))
+        .with_context(|| format!("Failed creating {}", dir.display()))?;
+    std::fs::write(dir.join("vendor/.gitkeep"), "")?;
+    std::fs::write(
+        dir.join("init.lua"),
+        INIT_TEMPLATE
+            .replace("{{name}}", name)
+            .replace("{{api_version}}", &api::current_requirement()),
+    )?;
+    Ok(())
+}
+
+/// Asks a running app to reload its plugins. Silent when it is not running.
+async fn notify_app() {
+    let path = yal_ipc::socket_path();
+    if !path.exists() {
+        return;
+    }
+    let result = async {
+        let mut client = yal_ipc::Client::connect(&path).await?;
+        client.call(yal_ipc::Method::ReloadPlugins).await
+    }
+    .await;
+    if let Err(e) = result {
+        eprintln!("Could not ask YAL to reload plugins: {:#}", e);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, bail};
+use yal_core::{AppConfig, Theme};
+
+/// User themes from `path` followed by the built-in ones, like the app loads them.
+pub(crate) fn load_themes(path: &Path) -> Result<Vec<(Theme, bool)>> {
+    let user = yal_config::try_load_config::<Vec<Theme>>(path)?;
+    Ok(user
+        .into_iter()
+        .map(|t| (t, true))
+        .chain(yal_theme::list_owned().into_iter().map(|t| (t, false)))
+        .collect())
+}
+
+fn current_theme() -> Option<String> {
+    yal_config::load_config::<AppConfig>(&yal_config::config_path()).theme
+}
+
+pub fn list() -> Result<()> {
+    let current = current_theme();
+    for (theme, user) in load_themes(&yal_config::themes_path())? {
+        let Some(name) = theme.name else { continue };
+        let marker = if current.as_deref() == Some(name.as_str()) {
+            "*"
+        } else {
+            " "
+        };
+        let origin = if user { "user" } else { "built-in" };
+        println!("{} {:24} {}", marker, name, origin);
+    }
+    Ok(())
+}
+
+pub fn show(name: &str) -> Result<()> {
+    let themes = load_themes(&yal_config::themes_path())?;
+    let theme = themes
+        .into_iter()
+        .map(|(t, _)| t)
+        .find(|t| t.name.as_deref() == Some(name))
+        .or_else(|| yal_theme::by_name(name).map(Theme::from));
+    let Some(theme) = theme else {
+        bail!("no theme named '{}'", name);
+    };
+
+    let swatches = std::io::stdout().is_terminal();
+    println!("{}", theme.name.as_deref().unwrap_or(name));
+    for (key, value) in colors(&theme) {
+        let value = value.unwrap_or("(unset)");
+        match hex_rgb(value).filter(|_| swatches) {
+            Some((r, g, b)) => println!(
+                "  {:14} \x1b[48;2;{};{};{}m    \x1b[0m {}",
+                key, r, g, b, value
+            ),
+            None => println!("  {:14} {}", key, value),
+        }
+    }
+    Ok(())
+}
+
+pub fn validate(file: Option<PathBuf>) -> Result<()> {
+    let path = file.unwrap_or_else(yal_config::themes_path);
+    if !path.exists() {
+        bail!("{} does not exist", path.display());
+    }
+    let themes = yal_config::try_load_config::<Vec<Theme>>(&path)?;
+    let problems = check_themes(&themes);
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if !problems.is_empty() {
+        bail!("{} has {} problems", path.display(), problems.len());
+    }
+    println!("{}: {} themes ok", path.display(), themes.len());
+    Ok(())
+}
+
+/// Problems with user themes, one message each.
+pub(crate) fn check_themes(themes: &[Theme]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut names: Vec<&str> = Vec::new();
+    for (i, theme) in themes.iter().enumerate() {
+        let Some(name) = theme.name.as_deref().filter(|n| !n.trim().is_empty()) else {
+            problems.push(format!("theme #{} has no name", i + 1));
+            continue;
+        };
+        if names.contains(&name) {
+            problems.push(format!("theme '{}' is defined more than once", name));
+        }
+        names.push(name);
+        for (key, value) in colors(theme) {
+            if let Some(value) = value
+                && !is_css_color(value)
+            {
+                problems.push(format!(
+                    "theme '{}': {} '{}' is not a color",
+                    name, key, value
+                ));
+            }
+        }
+    }
+    problems
+}
+
+fn colors(theme: &Theme) -> [(&'static str, Option<&str>); 4] {
+    [
+        ("bg_color", theme.bg_color.as_deref()),
+        ("fg_color", theme.fg_color.as_deref()),
+        ("bg_font_color", theme.bg_font_color.as_deref()),
+        ("fg_font_color", theme.fg_font_color.as_deref()),
+    ]
+}
+
+/// Colors are handed to CSS as-is: accept hex, functional notation and names.
+fn is_css_color(value: &str) -> bool {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    if let Some((func, rest)) = value.split_once('(') {
+        return matches!(
+            func.to_ascii_lowercase().as_str(),
+            "rgb" | "rgba" | "hsl" | "hsla" | "hwb" | "lab" | "lch" | "oklab" | "oklch" | "color"
+        ) && rest.ends_with(')');
+    }
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn hex_rgb(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = value.strip_prefix('#')?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 | 8 => Some((channel(0)?, channel(2)?, channel(4)?)),
+        _ => None,
+    }
+}
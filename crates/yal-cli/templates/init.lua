@@ -0,0 +1,32 @@
+-- {{name}}: a YAL plugin.
+-- The `yal.*` modules are documented in docs/yal-std.md of the yal repository.
+-- Pure-Lua libraries placed in ./vendor can be loaded with `require`.
+local log = require("yal.log")
+
+local M = {}
+
+-- Called once when the plugin is loaded. `config` is the `config` value of this
+-- plugin's entry in plugins.lua, or nil.
+function M.init(config)
+  return {
+    name = "{{name}}",
+    description = "TODO: describe {{name}}",
+    version = "0.1.0",
+    -- Plugin API versions this plugin works with; see `yal.version`.
+    api_version = "{{api_version}}",
+    commands = {
+      { name = "hello", description = "Say hello" },
+    },
+  }
+end
+
+-- req: { command, context, args }
+function M.execute(req)
+  if req.command == "hello" then
+    log.info("Hello from {{name}}!")
+    return { hide = true }
+  end
+  return { hide = false }
+end
+
+return M
@@ -1,25 +1,55 @@
 use std::fs;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use mlua::{Lua, LuaSerdeExt};
 use mlua::{Table, Value};
 use serde::de::DeserializeOwned;
-use std::path::Path;
 
+/// `$XDG_CONFIG_HOME/yal`, falling back to `~/.config/yal`.
+pub fn config_base_path() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .map(|h| h.join(".config"))
+        })
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("yal")
+}
+
+pub fn config_path() -> PathBuf {
+    config_base_path().join("config.lua")
+}
+
+pub fn themes_path() -> PathBuf {
+    config_base_path().join("themes.lua")
+}
+
+pub fn snippets_path() -> PathBuf {
+    config_base_path().join("snippets.lua")
+}
+
+/// Loads a Lua config file, falling back to the default when it is missing
+/// or invalid.
 pub fn load_config<ConfigType: DeserializeOwned + Default>(path: &Path) -> ConfigType {
-    let lua = Lua::new();
-    if path.exists()
-        && let Ok(table) = eval_lua_file(path, &lua)
-    {
-        return lua
-            .from_value::<ConfigType>(Value::Table(table))
-            .unwrap_or_else(|e| {
-                eprintln!("Failed to parse config.lua: {}", e);
-                ConfigType::default()
-            });
-    }
+    try_load_config(path).unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {:#}", path.display(), e);
+        ConfigType::default()
+    })
+}
 
-    ConfigType::default()
+/// Like [`load_config`], but reports why an existing file could not be used.
+/// A missing file still yields the default.
+pub fn try_load_config<ConfigType: DeserializeOwned + Default>(path: &Path) -> Result<ConfigType> {
+    if !path.exists() {
+        return Ok(ConfigType::default());
+    }
+    let lua = Lua::new();
+    let table = eval_lua_file(path, &lua)?;
+    lua.from_value::<ConfigType>(Value::Table(table))
+        .with_context(|| format!("Invalid config in {}", path.display()))
 }
 
 fn eval_lua_file(path: &Path, lua: &Lua) -> Result<Table> {
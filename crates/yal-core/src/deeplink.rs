@@ -0,0 +1,290 @@
+//! `yal://` URLs.
+//!
+//! ```text
+//! yal://show?q=term&mode=switch                 open the palette
+//! yal://theme/<name>                             apply a theme
+//! yal://run/plugin/<plugin>/<command>?args=<json> run a plugin command
+//! ```
+//!
+//! Path segments and query values are percent-decoded. Anything not listed
+//! above, including unknown query parameters, is rejected.
+
+use std::fmt;
+
+use crate::{Command, CommandKind, PaletteRequest};
+
+pub const SCHEME: &str = "yal";
+
+/// URLs longer than this are rejected outright.
+const MAX_LEN: usize = 8 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeepLink {
+    Show(PaletteRequest),
+    Run(Command),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeepLinkError(String);
+
+impl fmt::Display for DeepLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid yal:// link: {}", self.0)
+    }
+}
+
+impl std::error::Error for DeepLinkError {}
+
+fn error<T>(message: impl Into<String>) -> Result<T, DeepLinkError> {
+    Err(DeepLinkError(message.into()))
+}
+
+/// Whether `s` uses the `yal://` scheme (e.g. to pick links out of argv).
+pub fn is_deep_link(s: &str) -> bool {
+    strip_scheme(s).is_some()
+}
+
+fn strip_scheme(s: &str) -> Option<&str> {
+    let (scheme, rest) = s.split_once("://")?;
+    scheme.eq_ignore_ascii_case(SCHEME).then_some(rest)
+}
+
+impl DeepLink {
+    pub fn parse(url: &str) -> Result<Self, DeepLinkError> {
+        if url.len() > MAX_LEN {
+            return error(format!("longer than {} bytes", MAX_LEN));
+        }
+        let Some(rest) = strip_scheme(url) else {
+            return error(format!("expected a {}:// URL", SCHEME));
+        };
+        if rest.contains('#') {
+            return error("fragments are not supported");
+        }
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        // Some launchers append a slash to bare hosts (`yal://show/`).
+        let path = path.strip_suffix('/').unwrap_or(path);
+
+        let segments = path
+            .split('/')
+            .map(decode_segment)
+            .collect::<Result<Vec<_>, _>>()?;
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut query = Query::parse(query)?;
+
+        let link = match segments.as_slice() {
+            ["show"] => DeepLink::Show(PaletteRequest {
+                query: query.take("q"),
+                mode: query.take("mode").map(|m| parse_mode(&m)).transpose()?,
+            }),
+            ["theme", name] => DeepLink::Run(Command::Theme(name.to_string())),
+            ["run", "plugin", plugin, command] => {
+                let args = query
+                    .take("args")
+                    .map(|a| {
+                        serde_json::from_str(&a)
+                            .or_else(|e| error(format!("args is not valid JSON: {}", e)))
+                    })
+                    .transpose()?;
+                DeepLink::Run(Command::Plugin {
+                    plugin_name: plugin.to_string(),
+                    command_name: command.to_string(),
+                    args,
+                })
+            }
+            _ => return error(format!("unknown action '{}'", path)),
+        };
+        query.finish()?;
+        Ok(link)
+    }
+
+    /// Plugin commands run third-party code, so links to them may need the
+    /// user's approval first.
+    pub fn runs_plugin(&self) -> bool {
+        matches!(self, DeepLink::Run(Command::Plugin { .. }))
+    }
+}
+
+impl std::str::FromStr for DeepLink {
+    type Err = DeepLinkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn parse_mode(mode: &str) -> Result<CommandKind, DeepLinkError> {
+    serde_json::from_value(serde_json::Value::String(mode.to_string()))
+        .or_else(|_| error(format!("unknown mode '{}'", mode)))
+}
+
+struct Query(Vec<(String, String)>);
+
+impl Query {
+    fn parse(query: &str) -> Result<Self, DeepLinkError> {
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let key = decode(key, true)?;
+            if pairs.iter().any(|(k, _)| *k == key) {
+                return error(format!("duplicate parameter '{}'", key));
+            }
+            pairs.push((key, decode(value, true)?));
+        }
+        Ok(Self(pairs))
+    }
+
+    fn take(&mut self, key: &str) -> Option<String> {
+        let index = self.0.iter().position(|(k, _)| k == key)?;
+        Some(self.0.remove(index).1)
+    }
+
+    /// Fails on parameters the action did not consume.
+    fn finish(self) -> Result<(), DeepLinkError> {
+        match self.0.first() {
+            Some((key, _)) => error(format!("unexpected parameter '{}'", key)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn decode_segment(s: &str) -> Result<String, DeepLinkError> {
+    let decoded = decode(s, false)?;
+    if decoded.is_empty() {
+        return error("empty path segment");
+    }
+    if decoded.contains('/') {
+        return error(format!("'{}' must not contain '/'", decoded));
+    }
+    Ok(decoded)
+}
+
+/// Percent-decodes `s`. Control characters are rejected so names cannot
+/// smuggle line breaks into prompts or logs.
+fn decode(s: &str, plus_as_space: bool) -> Result<String, DeepLinkError> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'%' => {
+                let hex = [iter.next(), iter.next()];
+                let value = match hex {
+                    [Some(h), Some(l)] => std::str::from_utf8(&[h, l])
+                        .ok()
+                        .and_then(|h| u8::from_str_radix(h, 16).ok()),
+                    _ => None,
+                };
+                match value {
+                    Some(v) => bytes.push(v),
+                    None => return error(format!("bad percent-encoding in '{}'", s)),
+                }
+            }
+            b'+' if plus_as_space => bytes.push(b' '),
+            b => bytes.push(b),
+        }
+    }
+    let decoded =
+        String::from_utf8(bytes).or_else(|_| error(format!("'{}' is not valid UTF-8", s)))?;
+    if decoded.chars().any(char::is_control) {
+        return error("control characters are not allowed");
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(url: &str) -> Command {
+        match DeepLink::parse(url).unwrap() {
+            DeepLink::Run(cmd) => cmd,
+            other => panic!("expected a command for {}, got {:?}", url, other),
+        }
+    }
+
+    #[test]
+    fn parses_show() {
+        assert_eq!(
+            DeepLink::parse("yal://show?mode=switch&q=term").unwrap(),
+            DeepLink::Show(PaletteRequest {
+                query: Some("term".into()),
+                mode: Some(CommandKind::Switch),
+            })
+        );
+        assert_eq!(
+            DeepLink::parse("YAL://show/").unwrap(),
+            DeepLink::Show(PaletteRequest::default())
+        );
+        assert_eq!(
+            DeepLink::parse("yal://show?q=git+log%20-p%20%F0%9F%93%9C").unwrap(),
+            DeepLink::Show(PaletteRequest {
+                query: Some("git log -p 📜".into()),
+                mode: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            run("yal://theme/tokyo-night"),
+            Command::Theme("tokyo-night".into())
+        );
+        assert_eq!(
+            run("yal://run/plugin/spotify/play%20pause?args=%7B%22volume%22%3A5%7D"),
+            Command::Plugin {
+                plugin_name: "spotify".into(),
+                command_name: "play pause".into(),
+                args: Some(json!({ "volume": 5 })),
+            }
+        );
+        assert_eq!(
+            run("yal://run/plugin/spotify/next"),
+            Command::Plugin {
+                plugin_name: "spotify".into(),
+                command_name: "next".into(),
+                args: None,
+            }
+        );
+        assert!(DeepLink::parse("yal://run/plugin/a/b")
+            .unwrap()
+            .runs_plugin());
+        assert!(!DeepLink::parse("yal://theme/nord").unwrap().runs_plugin());
+    }
+
+    #[test]
+    fn rejects_invalid_links() {
+        for url in [
+            "https://show",
+            "yal:show",
+            "yal://",
+            "yal://explode",
+            "yal://show?q=a&q=b",
+            "yal://show?mode=everything",
+            "yal://show?q=a&extra=1",
+            "yal://show#frag",
+            "yal://theme",
+            "yal://theme/",
+            "yal://theme/a/b",
+            "yal://theme/nord?x=1",
+            "yal://theme/a%2Fb",
+            "yal://theme/%0Aevil",
+            "yal://theme/%zz",
+            "yal://theme/%FF",
+            "yal://run/plugin/a",
+            "yal://run/plugin//b",
+            "yal://run/plugin/a/b?args={nope",
+        ] {
+            assert!(DeepLink::parse(url).is_err(), "{} should be rejected", url);
+        }
+        let long = format!("yal://show?q={}", "a".repeat(MAX_LEN));
+        assert!(DeepLink::parse(&long).is_err());
+    }
+
+    #[test]
+    fn detects_links_in_argv() {
+        let argv = ["/Applications/yal.app/Contents/MacOS/yal", "yal://show"];
+        let links: Vec<_> = argv.iter().filter(|a| is_deep_link(a)).collect();
+        assert_eq!(links, vec![&"yal://show"]);
+    }
+}
@@ -1,8 +1,11 @@
 use serde::de::{self, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Display;
 
+pub mod deeplink;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AlignH {
@@ -25,6 +28,63 @@ pub struct AppConfig {
     pub theme: Option<String>,
     pub font: Option<FontConfig>,
     pub keys: Option<KeysConfig>,
+    pub commands: Option<Vec<CustomCommand>>,
+    pub shell: Option<ShellConfig>,
+    pub emoji: Option<EmojiConfig>,
+    pub bookmarks: Option<BookmarksConfig>,
+    pub deeplinks: Option<DeepLinksConfig>,
+    pub schedules: Option<Vec<ScheduledCommand>>,
+}
+
+/// A command `config.lua` runs on a timer or at login instead of from the
+/// palette. Exactly one of `every`, `cron` and `at_login` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledCommand {
+    pub name: String, // unique; keys the saved time of the last run
+    pub command: Command,
+    pub every: Option<u64>,   // seconds between runs
+    pub cron: Option<String>, // "minute hour day month weekday", local time
+    #[serde(default)]
+    pub at_login: bool, // once each time YAL starts
+    pub catch_up: Option<bool>, // run once at start if runs were missed (default true)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DeepLinksConfig {
+    pub enabled: Option<bool>, // handle yal:// links (default true)
+    pub confirm_plugin_commands: Option<bool>, // ask before running plugin commands (default true)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BookmarksConfig {
+    pub profiles: Option<Vec<BrowserProfile>>, // default: detected browser profiles
+    pub history_limit: Option<usize>, // history entries per profile (default 500, 0 disables)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserProfile {
+    pub browser: BrowserKind,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserKind {
+    Chromium,
+    Firefox,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EmojiConfig {
+    pub skin_tone: Option<String>, // "light" | "medium-light" | "medium" | "medium-dark" | "dark"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ShellConfig {
+    pub program: Option<String>, // shell used for `>` commands (default "sh")
+    pub cwd: Option<String>,     // working directory (default "~")
+    pub timeout_ms: Option<u64>, // no timeout by default
+    pub history_size: Option<usize>, // number of remembered commands (default 100)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -51,6 +111,57 @@ pub struct WindowConfig {
     pub w_radius: Option<f64>,    // window corner radius in px (default ~0)
 }
 
+/// An entry in `snippets.lua`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub name: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub text: String, // template, see the `yal-template` crate
+    #[serde(default)]
+    pub paste: bool, // paste into the focused app instead of only copying
+}
+
+/// A palette command declared inline in `config.lua`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomCommand {
+    pub name: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub action: CustomAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CustomAction {
+    Shell(ShellAction),
+    Open(OpenAction),
+    Url(UrlAction),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellAction {
+    pub command: String,
+    pub timeout_ms: Option<u64>,              // default 30s
+    pub cwd: Option<String>,                  // "~" is expanded
+    pub env: Option<HashMap<String, String>>, // merged over the inherited environment
+    #[serde(default)]
+    pub show_output: bool, // show stdout/stderr in a prompt instead of hiding
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAction {
+    pub path: String,
+    pub app: Option<String>, // open `path` with this application
+    #[serde(default)]
+    pub args: Vec<String>, // passed to the application via `--args`
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlAction {
+    pub url: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Theme {
     pub name: Option<String>,
@@ -84,6 +195,34 @@ pub enum Command {
         command_name: String,
         args: Option<serde_json::Value>,
     },
+    // opens the settings form of a plugin with a config schema
+    PluginSettings {
+        plugin_name: String,
+    },
+    // enables or disables a plugin until toggled back
+    PluginToggle {
+        plugin_name: String,
+        enable: bool,
+    },
+    // deletes what plugins removed from plugins.lua left behind
+    PrunePlugins,
+    Custom {
+        name: String,
+        keywords: Vec<String>,
+    },
+    Shell(String),
+    Emoji {
+        symbol: String,
+        name: String,
+    },
+    Bookmark {
+        title: String,
+        url: String,
+    },
+    Snippet {
+        name: String,
+        keywords: Vec<String>,
+    },
 }
 
 impl Display for Command {
@@ -109,6 +248,27 @@ impl Command {
                 command_name,
                 ..
             } => format!("{} - {}", plugin_name, command_name),
+            Command::PluginSettings { plugin_name } => format!("Configure {}", plugin_name),
+            Command::PluginToggle {
+                plugin_name,
+                enable: true,
+            } => format!("Enable {}", plugin_name),
+            Command::PluginToggle { plugin_name, .. } => format!("Disable {}", plugin_name),
+            Command::PrunePlugins => "Clean up removed plugins".to_string(),
+            Command::Custom { name, .. } => name.clone(),
+            Command::Shell(line) => line.clone(),
+            Command::Emoji { symbol, name } => format!("{} {}", symbol, name),
+            Command::Bookmark { title, url } if title.is_empty() => url.clone(),
+            Command::Bookmark { title, .. } => title.clone(),
+            Command::Snippet { name, .. } => name.clone(),
+        }
+    }
+
+    pub fn keywords(&self) -> &[String] {
+        match self {
+            Command::Custom { keywords, .. } | Command::Snippet { keywords, .. } => keywords,
+            Command::Bookmark { url, .. } => std::slice::from_ref(url),
+            _ => &[],
         }
     }
 
@@ -117,17 +277,31 @@ impl Command {
             Command::App(_) => "app",
             Command::Switch(_) => "switch",
             Command::Theme(_) => "theme",
-            Command::Plugin { .. } => "plugin",
+            Command::Plugin { .. }
+            | Command::PluginSettings { .. }
+            | Command::PluginToggle { .. }
+            | Command::PrunePlugins => "plugin",
+            Command::Custom { .. } => "cmd",
+            Command::Shell(_) => "shell",
+            Command::Emoji { .. } => "emoji",
+            Command::Bookmark { .. } => "bookmark",
+            Command::Snippet { .. } => "snippet",
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum CommandKind {
     App,
     Switch,
     Theme,
     Plugin,
+    Custom,
+    Shell,
+    Emoji,
+    Bookmark,
+    Snippet,
 }
 
 impl CommandKind {
@@ -138,10 +312,25 @@ impl CommandKind {
                 | (CommandKind::Switch, Command::Switch(_))
                 | (CommandKind::Theme, Command::Theme(_))
                 | (CommandKind::Plugin, Command::Plugin { .. })
+                | (CommandKind::Plugin, Command::PluginSettings { .. })
+                | (CommandKind::Plugin, Command::PluginToggle { .. })
+                | (CommandKind::Plugin, Command::PrunePlugins)
+                | (CommandKind::Custom, Command::Custom { .. })
+                | (CommandKind::Shell, Command::Shell(_))
+                | (CommandKind::Emoji, Command::Emoji { .. })
+                | (CommandKind::Bookmark, Command::Bookmark { .. })
+                | (CommandKind::Snippet, Command::Snippet { .. })
         )
     }
 }
 
+/// Opens the palette, optionally pre-filled with a query and switched to a mode.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct PaletteRequest {
+    pub query: Option<String>,
+    pub mode: Option<CommandKind>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Prompt {
     pub title: Option<String>,
@@ -0,0 +1,268 @@
+//! Emoji and Unicode symbol dataset with fuzzy search.
+//!
+//! `data/emoji.tsv` is generated from Unicode CLDR names and gemoji aliases for
+//! emoji, plus `unicodedata` names for common symbol blocks (arrows, math,
+//! currency, Greek, ...). One entry per line:
+//!
+//! `symbol<TAB>name<TAB>group<TAB>aliases<TAB>skin tone variants`
+//!
+//! where aliases and variants are space separated, and variants (if any) are
+//! listed light → dark.
+
+use std::sync::OnceLock;
+
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use serde::{Deserialize, Serialize};
+
+const DATA: &str = include_str!("../data/emoji.tsv");
+
+/// Score added to recently used entries, scaled so the most recent ranks first.
+const RECENT_BOOST: i64 = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Group {
+    Smileys,
+    People,
+    Animals,
+    Food,
+    Travel,
+    Activities,
+    Objects,
+    Symbols,
+    Flags,
+    Unicode,
+}
+
+impl Group {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "smileys" => Group::Smileys,
+            "people" => Group::People,
+            "animals" => Group::Animals,
+            "food" => Group::Food,
+            "travel" => Group::Travel,
+            "activities" => Group::Activities,
+            "objects" => Group::Objects,
+            "symbols" => Group::Symbols,
+            "flags" => Group::Flags,
+            "unicode" => Group::Unicode,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkinTone {
+    #[default]
+    Default,
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark,
+}
+
+impl SkinTone {
+    pub const ALL: [SkinTone; 6] = [
+        SkinTone::Default,
+        SkinTone::Light,
+        SkinTone::MediumLight,
+        SkinTone::Medium,
+        SkinTone::MediumDark,
+        SkinTone::Dark,
+    ];
+
+    /// Cycles to the next tone, wrapping back to `Default`.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl std::str::FromStr for SkinTone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "default" | "none" | "" => Ok(SkinTone::Default),
+            "light" => Ok(SkinTone::Light),
+            "medium-light" => Ok(SkinTone::MediumLight),
+            "medium" => Ok(SkinTone::Medium),
+            "medium-dark" => Ok(SkinTone::MediumDark),
+            "dark" => Ok(SkinTone::Dark),
+            other => Err(format!("unknown skin tone '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Emoji {
+    pub symbol: &'static str,
+    pub name: &'static str,
+    pub group: Group,
+    pub aliases: Vec<&'static str>,
+    skin_tones: Vec<&'static str>,
+}
+
+impl Emoji {
+    pub fn supports_skin_tones(&self) -> bool {
+        !self.skin_tones.is_empty()
+    }
+
+    /// The symbol in the requested tone, or the plain symbol if the emoji has
+    /// no skin tone variants.
+    pub fn with_skin_tone(&self, tone: SkinTone) -> &'static str {
+        match tone {
+            SkinTone::Default => self.symbol,
+            tone => {
+                let i = SkinTone::ALL.iter().position(|t| *t == tone).unwrap_or(0);
+                self.skin_tones.get(i - 1).copied().unwrap_or(self.symbol)
+            }
+        }
+    }
+
+    fn is_variant(&self, symbol: &str) -> bool {
+        self.symbol == symbol || self.skin_tones.contains(&symbol)
+    }
+}
+
+fn parse_line(line: &'static str) -> Option<Emoji> {
+    let mut cols = line.split('\t');
+    let symbol = cols.next().filter(|s| !s.is_empty())?;
+    let name = cols.next().filter(|s| !s.is_empty())?;
+    let group = Group::parse(cols.next()?)?;
+    let aliases = cols.next().unwrap_or_default().split_whitespace().collect();
+    let skin_tones = cols
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    Some(Emoji {
+        symbol,
+        name,
+        group,
+        aliases,
+        skin_tones,
+    })
+}
+
+/// Every entry in the embedded dataset, emoji first, in dataset order.
+pub fn all() -> &'static [Emoji] {
+    static ALL: OnceLock<Vec<Emoji>> = OnceLock::new();
+    ALL.get_or_init(|| DATA.lines().filter_map(parse_line).collect())
+}
+
+/// Looks up an entry by its symbol or any of its skin tone variants.
+pub fn find(symbol: &str) -> Option<&'static Emoji> {
+    all().iter().find(|e| e.is_variant(symbol))
+}
+
+/// Fuzzy searches names and aliases. `recent` symbols (most recent first) are
+/// ranked higher, and are returned on their own for an empty query.
+pub fn search(query: &str, recent: &[String], limit: usize) -> Vec<&'static Emoji> {
+    let recent = recent
+        .iter()
+        .filter_map(|s| find(s))
+        .fold(Vec::<&Emoji>::new(), |mut acc, e| {
+            if !acc.iter().any(|r| r.symbol == e.symbol) {
+                acc.push(e);
+            }
+            acc
+        });
+
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return recent.into_iter().take(limit).collect();
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let mut scored = all()
+        .iter()
+        .filter_map(|e| {
+            let name_score = matcher.fuzzy_match(e.name, &query);
+            let alias_score = e
+                .aliases
+                .iter()
+                .filter_map(|a| {
+                    let alias = a.replace('_', " ");
+                    let exact = if alias == query { 100 } else { 0 };
+                    matcher.fuzzy_match(&alias, &query).map(|s| s + exact)
+                })
+                .max();
+            let score = name_score.max(alias_score)?;
+            let boost = recent
+                .iter()
+                .position(|r| r.symbol == e.symbol)
+                .map(|i| RECENT_BOOST * (recent.len() - i) as i64)
+                .unwrap_or_default();
+            Some((e, score + boost))
+        })
+        .collect::<Vec<_>>();
+
+    // Stable sort keeps dataset order (emoji before plain symbols) on ties.
+    scored.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    scored.into_iter().take(limit).map(|(e, _)| e).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dataset_parses_every_line() {
+        assert_eq!(all().len(), DATA.lines().count());
+        assert!(all().iter().any(|e| e.group == Group::Unicode));
+        assert!(all().iter().any(|e| e.group == Group::Flags));
+    }
+
+    #[test]
+    fn searches_names_and_aliases() {
+        assert_eq!(search("thumbs up", &[], 1)[0].symbol, "👍");
+        assert_eq!(search("+1", &[], 1)[0].symbol, "👍");
+        assert_eq!(search("tada", &[], 1)[0].symbol, "🎉");
+        assert_eq!(search("rightwards arrow", &[], 1)[0].symbol, "→");
+        assert_eq!(search("cmd", &[], 1)[0].symbol, "⌘");
+    }
+
+    #[test]
+    fn skin_tones() {
+        let wave = find("👋").unwrap();
+        assert!(wave.supports_skin_tones());
+        assert_eq!(wave.with_skin_tone(SkinTone::Default), "👋");
+        assert_eq!(wave.with_skin_tone(SkinTone::Medium), "👋🏽");
+        assert_eq!(wave.with_skin_tone(SkinTone::Dark), "👋🏿");
+
+        let rocket = find("🚀").unwrap();
+        assert!(!rocket.supports_skin_tones());
+        assert_eq!(rocket.with_skin_tone(SkinTone::Dark), "🚀");
+    }
+
+    #[test]
+    fn finds_base_from_variant() {
+        assert_eq!(find("👋🏽").unwrap().symbol, "👋");
+    }
+
+    #[test]
+    fn parses_skin_tone_names() {
+        assert_eq!("medium_dark".parse::<SkinTone>(), Ok(SkinTone::MediumDark));
+        assert_eq!("Light".parse::<SkinTone>(), Ok(SkinTone::Light));
+        assert!("purple".parse::<SkinTone>().is_err());
+        assert_eq!(SkinTone::Dark.next(), SkinTone::Default);
+    }
+
+    #[test]
+    fn recent_items_rank_first() {
+        let recent = vec!["😹".to_string(), "👋🏽".to_string()];
+        let empty = search("", &recent, 10);
+        assert_eq!(
+            empty.iter().map(|e| e.symbol).collect::<Vec<_>>(),
+            vec!["😹", "👋"]
+        );
+
+        let without = search("cat", &[], 1)[0].symbol;
+        assert_ne!(without, "😹");
+        assert_eq!(search("cat", &recent, 1)[0].symbol, "😹");
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+
+use crate::{Hello, Method, PROTOCOL_VERSION, RawRequest, Response};
+
+pub struct Client {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    write: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connects and checks that the server speaks the same protocol version.
+    pub async fn connect(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path)
+            .await
+            .with_context(|| format!("yal is not running (no socket at {})", path.display()))?;
+        let (read, write) = stream.into_split();
+        let mut client = Self {
+            lines: BufReader::new(read).lines(),
+            write,
+            next_id: 1,
+        };
+
+        let hello: Hello = client.call_as(Method::Hello).await?;
+        if hello.protocol != PROTOCOL_VERSION {
+            bail!(
+                "protocol mismatch: client speaks v{}, {} speaks v{}",
+                PROTOCOL_VERSION,
+                hello.server,
+                hello.protocol
+            );
+        }
+        Ok(client)
+    }
+
+    pub async fn call(&mut self, method: Method) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut line = serde_json::to_vec(&RawRequest::new(id, &method))?;
+        line.push(b'\n');
+        self.write.write_all(&line).await?;
+
+        let reply = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| anyhow!("connection closed by yal"))?;
+        let response: Response = serde_json::from_str(&reply)?;
+        if response.id != id {
+            bail!("response id {} does not match request {}", response.id, id);
+        }
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(error.into()),
+            (result, None) => Ok(result.unwrap_or(Value::Null)),
+        }
+    }
+
+    pub async fn call_as<T: DeserializeOwned>(&mut self, method: Method) -> Result<T> {
+        let name = method.name();
+        let value = self.call(method).await?;
+        serde_json::from_value(value).with_context(|| format!("unexpected result for {}", name))
+    }
+}
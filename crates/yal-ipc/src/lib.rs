@@ -0,0 +1,382 @@
+//! Control API for the running launcher.
+//!
+//! The backend listens on a per-user Unix domain socket. Each message is one
+//! line of JSON-RPC 2.0:
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"show","params":{"query":"git","mode":"shell"}}
+//! ← {"jsonrpc":"2.0","id":1,"result":null}
+//! ```
+//!
+//! Clients should call `hello` first and check [`PROTOCOL_VERSION`]; [`Client`]
+//! does this on connect.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use yal_core::{Command, CommandKind};
+
+mod client;
+mod server;
+
+pub use client::Client;
+pub use server::{Handler, Server};
+
+/// Bumped on breaking changes to [`Method`] or its results.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const JSONRPC: &str = "2.0";
+
+/// Socket path: `$YAL_SOCKET`, else `$XDG_RUNTIME_DIR/yal/yal.sock`, else
+/// `yal-<user>.sock` in the (per-user on macOS) temp dir.
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("YAL_SOCKET").filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|p| !p.is_empty()) {
+        return PathBuf::from(dir).join("yal/yal.sock");
+    }
+    let user = std::env::var("USER").unwrap_or_else(|_| "default".to_string());
+    std::env::temp_dir().join(format!("yal-{}.sock", user))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Method {
+    /// Handshake, answered by the server itself with [`Hello`].
+    Hello,
+    /// Shows the palette, optionally with a query and a mode (`"emoji"`, ...).
+    Show {
+        #[serde(default)]
+        query: Option<String>,
+        #[serde(default)]
+        mode: Option<CommandKind>,
+    },
+    Hide,
+    /// Returns every command currently offered by the palette.
+    ListCommands,
+    RunCommand {
+        command: Command,
+    },
+    ReloadConfig,
+    ReloadPlugins,
+    ApplyTheme {
+        theme: String,
+    },
+}
+
+impl Method {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Method::Hello => "hello",
+            Method::Show { .. } => "show",
+            Method::Hide => "hide",
+            Method::ListCommands => "list_commands",
+            Method::RunCommand { .. } => "run_command",
+            Method::ReloadConfig => "reload_config",
+            Method::ReloadPlugins => "reload_plugins",
+            Method::ApplyTheme { .. } => "apply_theme",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Hello {
+    pub protocol: u32,
+    pub server: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    pub const PARSE_ERROR: i32 = -32700;
+    pub const INVALID_REQUEST: i32 = -32600;
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    pub const INVALID_PARAMS: i32 = -32602;
+    pub const INTERNAL_ERROR: i32 = -32603;
+
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(Self::INTERNAL_ERROR, message)
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// A request as it appears on the wire, before the method is resolved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RawRequest {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+impl RawRequest {
+    fn new(id: u64, method: &Method) -> Self {
+        let mut value = serde_json::to_value(method).expect("methods always serialize");
+        let params = value.get_mut("params").map(Value::take);
+        Self {
+            jsonrpc: JSONRPC.to_string(),
+            id: id.into(),
+            method: method.name().to_string(),
+            params,
+        }
+    }
+
+    fn method(&self) -> Result<Method, RpcError> {
+        let parse = |params: Option<&Value>| {
+            let mut value = serde_json::json!({ "method": self.method });
+            if let Some(params) = params {
+                value["params"] = params.clone();
+            }
+            serde_json::from_value::<Method>(value)
+        };
+        // Omitted params mean "all defaults" for methods that take any.
+        let parsed = match &self.params {
+            Some(params) => parse(Some(params)),
+            None => parse(None).or_else(|_| parse(Some(&serde_json::json!({})))),
+        };
+        parsed.map_err(|e| {
+            if KNOWN_METHODS.contains(&self.method.as_str()) {
+                RpcError::new(RpcError::INVALID_PARAMS, e.to_string())
+            } else {
+                RpcError::new(
+                    RpcError::METHOD_NOT_FOUND,
+                    format!("unknown method '{}'", self.method),
+                )
+            }
+        })
+    }
+}
+
+const KNOWN_METHODS: &[&str] = &[
+    "hello",
+    "show",
+    "hide",
+    "list_commands",
+    "run_command",
+    "reload_config",
+    "reload_plugins",
+    "apply_theme",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Response {
+    jsonrpc: String,
+    id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(v) => (Some(v), None),
+            Err(e) => (None, Some(e)),
+        };
+        Self {
+            jsonrpc: JSONRPC.to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        calls: Arc<Mutex<Vec<Method>>>,
+    }
+
+    impl Handler for Recorder {
+        async fn handle(&self, method: Method) -> Result<Value, RpcError> {
+            self.calls.lock().unwrap().push(method.clone());
+            match method {
+                Method::ListCommands => {
+                    Ok(serde_json::to_value(vec![Command::Theme("nord".into())]).unwrap())
+                }
+                Method::ApplyTheme { theme } if theme == "missing" => {
+                    Err(RpcError::internal("no such theme"))
+                }
+                _ => Ok(Value::Null),
+            }
+        }
+    }
+
+    async fn start() -> (tempfile::TempDir, PathBuf, Recorder) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("yal.sock");
+        let recorder = Recorder::default();
+        let server = Server::bind(&path).unwrap();
+        tokio::spawn(server.serve(recorder.clone()));
+        (dir, path, recorder)
+    }
+
+    #[test]
+    fn method_wire_format() {
+        let req = RawRequest::new(
+            7,
+            &Method::Show {
+                query: Some("git".into()),
+                mode: Some(CommandKind::Shell),
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(&req).unwrap(),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 7,
+                "method": "show",
+                "params": { "query": "git", "mode": "shell" }
+            })
+        );
+
+        let hide = RawRequest::new(1, &Method::Hide);
+        assert_eq!(hide.params, None);
+        assert_eq!(hide.method().unwrap(), Method::Hide);
+
+        // Params are optional where every field is.
+        let bare =
+            serde_json::from_str::<RawRequest>(r#"{"jsonrpc":"2.0","id":1,"method":"show"}"#)
+                .unwrap();
+        assert_eq!(
+            bare.method().unwrap(),
+            Method::Show {
+                query: None,
+                mode: None
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn end_to_end_over_socket() {
+        let (_dir, path, recorder) = start().await;
+        let mut client = Client::connect(&path).await.unwrap();
+
+        client
+            .call(Method::Show {
+                query: Some("😀".into()),
+                mode: Some(CommandKind::Emoji),
+            })
+            .await
+            .unwrap();
+        let cmds: Vec<Command> = client.call_as(Method::ListCommands).await.unwrap();
+        assert_eq!(cmds, vec![Command::Theme("nord".into())]);
+
+        let err = client
+            .call(Method::ApplyTheme {
+                theme: "missing".into(),
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no such theme"), "{}", err);
+
+        // `hello` is answered by the server and never reaches the handler.
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec![
+                Method::Show {
+                    query: Some("😀".into()),
+                    mode: Some(CommandKind::Emoji)
+                },
+                Method::ListCommands,
+                Method::ApplyTheme {
+                    theme: "missing".into()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_protocol_errors() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (_dir, path, _) = start().await;
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+
+        let mut roundtrip = async |line: &str| -> Response {
+            write
+                .write_all(format!("{}\n", line).as_bytes())
+                .await
+                .unwrap();
+            let reply = lines.next_line().await.unwrap().unwrap();
+            serde_json::from_str(&reply).unwrap()
+        };
+
+        let code = |r: Response| r.error.map(|e| e.code);
+        assert_eq!(
+            code(roundtrip("not json").await),
+            Some(RpcError::PARSE_ERROR)
+        );
+        assert_eq!(
+            code(roundtrip(r#"{"jsonrpc":"1.0","id":1,"method":"hide"}"#).await),
+            Some(RpcError::INVALID_REQUEST)
+        );
+        assert_eq!(
+            code(roundtrip(r#"{"jsonrpc":"2.0","id":2,"method":"explode"}"#).await),
+            Some(RpcError::METHOD_NOT_FOUND)
+        );
+        assert_eq!(
+            code(roundtrip(r#"{"jsonrpc":"2.0","id":3,"method":"apply_theme","params":{}}"#).await),
+            Some(RpcError::INVALID_PARAMS)
+        );
+
+        let hello = roundtrip(r#"{"jsonrpc":"2.0","id":4,"method":"hello"}"#).await;
+        assert_eq!(hello.id, Value::from(4));
+        let hello: Hello = serde_json::from_value(hello.result.unwrap()).unwrap();
+        assert_eq!(hello.protocol, PROTOCOL_VERSION);
+    }
+
+    #[tokio::test]
+    async fn replaces_stale_socket_but_not_live_one() {
+        let (_dir, path, _) = start().await;
+        assert!(Server::bind(&path).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let stale = dir.path().join("stale.sock");
+        drop(std::os::unix::net::UnixListener::bind(&stale).unwrap());
+        assert!(stale.exists());
+        assert!(Server::bind(&stale).is_ok());
+    }
+
+    #[tokio::test]
+    async fn socket_is_private_to_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (dir, path, _) = start().await;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // Nothing is left behind from binding it.
+        let entries: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+}
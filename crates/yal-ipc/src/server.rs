@@ -0,0 +1,150 @@
+use std::{
+    io,
+    os::unix::fs::{DirBuilderExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use crate::{Hello, JSONRPC, Method, PROTOCOL_VERSION, RawRequest, Response, RpcError};
+
+/// Executes control API calls. `Method::Hello` is answered by the server.
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, method: Method) -> impl Future<Output = Result<Value, RpcError>> + Send;
+}
+
+pub struct Server {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Server {
+    /// Binds the socket, usable only by the current user, replacing a stale
+    /// socket file left by a previous run.
+    /// Fails with `AddrInUse` if another process is still listening on it.
+    /// Must be called from within a tokio runtime.
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is already in use", path.display()),
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        std::fs::create_dir_all(dir)?;
+
+        // Bound inside a directory only we can enter and moved into place once
+        // it is private, so other users never get a chance to connect.
+        let staging = dir.join(format!(".yal-ipc-{}", std::process::id()));
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+        std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+        let staged = staging.join("yal.sock");
+        let listener = UnixListener::bind(&staged).and_then(|listener| {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+        let _ = std::fs::remove_dir_all(&staging);
+        Ok(Self {
+            listener: listener?,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Accepts connections until the listener fails. Each connection is served
+    /// on its own task; requests on one connection are handled in order.
+    pub async fn serve<H: Handler>(self, handler: H) {
+        let handler = Arc::new(handler);
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve_connection(stream, handler.clone()));
+                }
+                Err(e) => {
+                    log::error!("IPC listener on {} failed: {}", self.path.display(), e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn serve_connection<H: Handler>(stream: UnixStream, handler: Arc<H>) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                log::warn!("IPC read failed: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = handle_line(&line, handler.as_ref()).await;
+        let mut out = serde_json::to_vec(&response).expect("responses always serialize");
+        out.push(b'\n');
+        if let Err(e) = write.write_all(&out).await {
+            log::warn!("IPC write failed: {}", e);
+            break;
+        }
+    }
+}
+
+async fn handle_line<H: Handler>(line: &str, handler: &H) -> Response {
+    let request = match serde_json::from_str::<RawRequest>(line) {
+        Ok(request) => request,
+        Err(e) => {
+            let code = if serde_json::from_str::<Value>(line).is_ok() {
+                RpcError::INVALID_REQUEST
+            } else {
+                RpcError::PARSE_ERROR
+            };
+            return Response::new(Value::Null, Err(RpcError::new(code, e.to_string())));
+        }
+    };
+    if request.jsonrpc != JSONRPC {
+        return Response::new(
+            request.id,
+            Err(RpcError::new(
+                RpcError::INVALID_REQUEST,
+                format!("unsupported jsonrpc version '{}'", request.jsonrpc),
+            )),
+        );
+    }
+
+    let result = match request.method() {
+        Ok(Method::Hello) => Ok(serde_json::to_value(Hello {
+            protocol: PROTOCOL_VERSION,
+            server: "yal".to_string(),
+        })
+        .expect("hello always serializes")),
+        Ok(method) => {
+            log::info!("IPC: {}", method.name());
+            handler.handle(method).await
+        }
+        Err(e) => Err(e),
+    };
+    Response::new(request.id, result)
+}
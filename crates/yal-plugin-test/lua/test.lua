@@ -0,0 +1,153 @@
+-- yal.test: helpers available to *_spec.lua files.
+--
+--   local t = require("yal.test")
+--   t.describe("greet", function()
+--     t.it("asks for a name", function()
+--       t.ui.submit({ name = "Ada" })
+--       local res = t.execute("greet")
+--       t.eq(res.hide, true)
+--       t.eq(#t.ui.prompts(), 1)
+--     end)
+--   end)
+
+local host = ...
+
+local M = {
+  cases = {},
+  ui = {},
+  plugin = host.plugin,
+  context = host.context,
+}
+
+local scope = {}
+
+function M.describe(name, fn)
+  table.insert(scope, name)
+  fn()
+  table.remove(scope)
+end
+
+function M.it(name, fn)
+  local parts = { table.unpack(scope) }
+  table.insert(parts, name)
+  table.insert(M.cases, { name = table.concat(parts, " "), fn = fn })
+end
+
+-- Calls the plugin's `init` with its configured config.
+function M.init()
+  return host.plugin.init(host.config)
+end
+
+-- Runs a command like the palette would. `context` defaults to `t.context`,
+-- a synthetic snapshot of displays and windows that each case may modify.
+function M.execute(command, args, context)
+  return host.plugin.execute({
+    command = command,
+    args = args,
+    context = context or M.context,
+  })
+end
+
+-- Scripted answers for `yal.ui.prompt`, consumed in order.
+function M.ui.submit(values)
+  host.push("submit", values or {})
+end
+
+function M.ui.state(values)
+  host.push("state", values or {})
+end
+
+function M.ui.cancel()
+  host.push("cancel")
+end
+
+function M.ui.prompts()
+  return host.prompts()
+end
+
+function M.ui.cancelled()
+  return host.cancelled()
+end
+
+-- Booleans passed to `visibility:show()` / `visibility:hide()`, oldest first.
+function M.visibility()
+  return host.visibility()
+end
+
+local function show(v, seen)
+  if type(v) == "string" then
+    return string.format("%q", v)
+  end
+  if type(v) ~= "table" then
+    return tostring(v)
+  end
+  seen = seen or {}
+  if seen[v] then
+    return "<cycle>"
+  end
+  seen[v] = true
+  local keys = {}
+  for k in pairs(v) do
+    table.insert(keys, k)
+  end
+  table.sort(keys, function(a, b)
+    return tostring(a) < tostring(b)
+  end)
+  local parts = {}
+  for _, k in ipairs(keys) do
+    table.insert(parts, tostring(k) .. " = " .. show(v[k], seen))
+  end
+  seen[v] = nil
+  return "{ " .. table.concat(parts, ", ") .. " }"
+end
+
+local function equal(a, b)
+  if a == b then
+    return true
+  end
+  if type(a) ~= "table" or type(b) ~= "table" then
+    return false
+  end
+  for k, v in pairs(a) do
+    if not equal(v, b[k]) then
+      return false
+    end
+  end
+  for k in pairs(b) do
+    if a[k] == nil then
+      return false
+    end
+  end
+  return true
+end
+
+local function fail(msg, detail)
+  error((msg and (msg .. ": ") or "") .. detail, 3)
+end
+
+-- Deep equality for tables, `==` for everything else.
+function M.eq(actual, expected, msg)
+  if not equal(actual, expected) then
+    fail(msg, "expected " .. show(expected) .. ", got " .. show(actual))
+  end
+end
+
+function M.ok(value, msg)
+  if not value then
+    fail(msg, "expected a truthy value, got " .. show(value))
+  end
+end
+
+-- Calls `fn` and returns its error. `pattern` is matched with string.find.
+function M.errors(fn, pattern, msg)
+  local ok, err = pcall(fn)
+  if ok then
+    fail(msg, "expected an error")
+  end
+  if pattern and not tostring(err):find(pattern) then
+    fail(msg, "error " .. show(tostring(err)) .. " does not match " .. show(pattern))
+  end
+  return err
+end
+
+return M
@@ -0,0 +1,352 @@
+//! Headless test harness for Lua plugins.
+//!
+//! [`TestPlugin`] loads a plugin against a [`MockBackend`] so it can be driven
+//! from Rust, and [`run_specs`] runs the `*_spec.lua` files shipped with a
+//! plugin (see `lua/test.lua` for the `yal.test` module they use).
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use mlua::{Function, Lua, LuaSerdeExt, Table, Value as LuaValue};
+use serde_json::Value;
+use yal_plugin::{
+    deps::http::HttpTransport,
+    manager::{config::HttpMode, manifest::Manifest},
+    plugin::{LuaPlugin, PluginRef},
+    protocol::{Display, PluginExecuteContext, PluginExecuteResponse, PluginInitResponse, Window},
+    schema::InvalidConfig,
+};
+
+mod mock;
+
+pub use mock::MockBackend;
+
+const TEST_MODULE: &str = include_str!("../lua/test.lua");
+
+/// One display with one space, holding a focused terminal and a browser.
+pub fn test_context() -> PluginExecuteContext {
+    let display = || Display {
+        display_id: "display-1".to_string(),
+        current_space_id: 1,
+    };
+    let window = |window_id, app_name: &str, title: &str, is_focused| Window {
+        display_id: "display-1".to_string(),
+        space_id: 1,
+        space_index: 0,
+        window_id,
+        title: Some(title.to_string()),
+        pid: 1000 + window_id as i32,
+        app_name: app_name.to_string(),
+        is_focused,
+    };
+    PluginExecuteContext {
+        windows: vec![
+            window(1, "Terminal", "~", true),
+            window(2, "Safari", "Start Page", false),
+        ],
+        displays: vec![display()],
+        current_display: display(),
+    }
+}
+
+pub struct TestPlugin {
+    pub backend: MockBackend,
+    /// Passed to every `execute`; replace it to simulate other window layouts.
+    pub context: PluginExecuteContext,
+    config: Option<Value>,
+    info: PluginInitResponse,
+    plugin: LuaPlugin,
+}
+
+impl TestPlugin {
+    /// Loads the plugin in `dir` and runs its `init` with `config`. Fails like
+    /// the app would if the plugin needs another plugin API version or
+    /// `config` does not match the plugin's schema.
+    pub async fn load(dir: &Path, config: Option<Value>) -> Result<Self> {
+        Self::load_with_http(dir, config, HttpTransport::Live).await
+    }
+
+    /// Like [`TestPlugin::load`], with `yal.http` recording to or replaying
+    /// from a cassette.
+    pub async fn load_with_http(
+        dir: &Path,
+        config: Option<Value>,
+        http: HttpTransport,
+    ) -> Result<Self> {
+        let backend = MockBackend::new();
+        let name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "plugin".to_string());
+        let manifest = Manifest::load(dir)?;
+        // Packages fetched by `yal-cli plugins install`, if any.
+        let package_roots = manifest.package_roots(&name);
+        let config = match &manifest.config_schema {
+            Some(schema) => Some(
+                schema
+                    .apply(config)
+                    .map_err(|errors| InvalidConfig { errors })?,
+            )
+            .filter(|c| !c.is_null()),
+            None => config,
+        };
+        let plugin = LuaPlugin::new(
+            PluginRef {
+                name,
+                path: dir.to_path_buf(),
+                config: config.clone(),
+                http,
+                dependencies: Default::default(),
+                package_roots,
+            },
+            Arc::new(backend.clone()),
+        )?;
+        let info = plugin.initialize().await.context("init() failed")?;
+        yal_plugin::api::check(&info.name, info.api_version.as_deref())?;
+        if let (None, Some(schema)) = (&manifest.config_schema, &info.config_schema) {
+            schema
+                .apply(config.clone())
+                .map_err(|errors| InvalidConfig { errors })?;
+        }
+        Ok(Self {
+            backend,
+            context: test_context(),
+            config,
+            info,
+            plugin,
+        })
+    }
+
+    /// What `init` returned.
+    pub fn info(&self) -> &PluginInitResponse {
+        &self.info
+    }
+
+    pub async fn execute(
+        &self,
+        command: &str,
+        args: Option<Value>,
+    ) -> Result<PluginExecuteResponse> {
+        self.plugin
+            .run(command.to_string(), &self.context, args)
+            .await
+    }
+
+    pub fn lua(&self) -> &Lua {
+        self.plugin.lua()
+    }
+
+    /// Creates the `yal.test` module for this plugin's Lua state.
+    fn install_test_module(&self) -> Result<Table> {
+        let lua = self.lua();
+        let host = lua.create_table()?;
+        host.set("plugin", self.plugin.module().clone())?;
+        host.set("config", lua.to_value(&self.config)?)?;
+        host.set("context", lua.to_value(&self.context)?)?;
+
+        let backend = self.backend.clone();
+        host.set(
+            "push",
+            lua.create_function(move |lua, (kind, values): (String, LuaValue)| {
+                let values: Value = lua.from_value(values)?;
+                match kind.as_str() {
+                    "submit" => backend.push_submit(values),
+                    "state" => backend.push_state(values),
+                    "cancel" => backend.push_cancel(),
+                    other => {
+                        return Err(mlua::Error::runtime(format!(
+                            "unknown response '{}'",
+                            other
+                        )));
+                    }
+                }
+                Ok(())
+            })?,
+        )?;
+        let backend = self.backend.clone();
+        host.set(
+            "prompts",
+            lua.create_function(move |lua, ()| lua.to_value(&backend.prompts()))?,
+        )?;
+        let backend = self.backend.clone();
+        host.set(
+            "cancelled",
+            lua.create_function(move |lua, ()| lua.to_value(&backend.cancelled()))?,
+        )?;
+        let backend = self.backend.clone();
+        host.set(
+            "visibility",
+            lua.create_function(move |lua, ()| lua.to_value(&backend.visibility()))?,
+        )?;
+
+        let module: Table = lua.load(TEST_MODULE).set_name("yal.test").call(host)?;
+        let loaded: Table = lua.globals().get::<Table>("package")?.get("loaded")?;
+        loaded.set("yal.test", module.clone())?;
+        Ok(module)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseReport {
+    pub name: String,
+    /// `None` if the case passed.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecReport {
+    /// Relative to the plugin directory.
+    pub file: PathBuf,
+    pub cases: Vec<CaseReport>,
+}
+
+impl SpecReport {
+    pub fn failures(&self) -> impl Iterator<Item = &CaseReport> {
+        self.cases.iter().filter(|c| c.error.is_some())
+    }
+}
+
+/// Runs every `*_spec.lua` under `dir` (skipping `vendor/` and hidden
+/// directories). Each file gets a freshly loaded plugin; the mock backend
+/// and `t.context` are reset before every case.
+///
+/// `http` applies to each spec's own cassette: `foo_spec.lua` uses
+/// `cassettes/foo_spec.json`.
+pub async fn run_specs(
+    dir: &Path,
+    config: Option<Value>,
+    http: HttpMode,
+) -> Result<Vec<SpecReport>> {
+    let mut files = Vec::new();
+    find_specs(dir, &mut files)?;
+    files.sort();
+
+    let mut reports = Vec::new();
+    for file in files {
+        let relative = file.strip_prefix(dir).unwrap_or(&file).to_path_buf();
+        let cassette = dir.join("cassettes").join(relative.with_extension("json"));
+        let transport = http.transport(cassette);
+        let cases = match run_spec_file(dir, &file, config.clone(), transport).await {
+            Ok(cases) => cases,
+            Err(e) => vec![CaseReport {
+                name: "(loading spec)".to_string(),
+                error: Some(format!("{:#}", e)),
+            }],
+        };
+        reports.push(SpecReport {
+            file: relative,
+            cases,
+        });
+    }
+    Ok(reports)
+}
+
+async fn run_spec_file(
+    dir: &Path,
+    file: &Path,
+    config: Option<Value>,
+    http: HttpTransport,
+) -> Result<Vec<CaseReport>> {
+    let plugin = TestPlugin::load_with_http(dir, config, http).await?;
+    let test = plugin.install_test_module()?;
+    let src = std::fs::read_to_string(file)?;
+    plugin
+        .lua()
+        .load(src.as_str())
+        .set_name(file.to_string_lossy())
+        .exec_async()
+        .await?;
+
+    let cases: Table = test.get("cases")?;
+    let mut reports = Vec::new();
+    for case in cases.sequence_values::<Table>() {
+        let case = case?;
+        let name: String = case.get("name")?;
+        let run: Function = case.get("fn")?;
+
+        plugin.backend.reset();
+        test.set("context", plugin.lua().to_value(&plugin.context)?)?;
+        let error = run.call_async::<()>(()).await.err().map(|e| e.to_string());
+        reports.push(CaseReport { name, error });
+    }
+    Ok(reports)
+}
+
+fn find_specs(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            if name != "vendor" && !name.starts_with('.') {
+                find_specs(&path, out)?;
+            }
+        } else if name.ends_with("_spec.lua") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/greeter")
+    }
+
+    #[tokio::test]
+    async fn drives_plugin_with_scripted_prompts() {
+        let plugin = TestPlugin::load(&fixture(), Some(json!({ "greeting": "Hi" })))
+            .await
+            .unwrap();
+        assert_eq!(plugin.info().name, "greeter");
+
+        plugin.backend.push_submit(json!({ "name": "Ada" }));
+        let res = plugin.execute("greet", None).await.unwrap();
+        assert!(res.hide);
+        assert_eq!(plugin.backend.prompts().len(), 1);
+        assert_eq!(plugin.backend.visibility(), vec![false]);
+
+        // A cancelled prompt keeps the palette open and closes the prompt.
+        plugin.backend.push_cancel();
+        let res = plugin.execute("greet", None).await.unwrap();
+        assert!(!res.hide);
+        assert_eq!(plugin.backend.cancelled(), vec!["prompt-2".to_string()]);
+
+        // Running out of scripted responses is an error, not a hang.
+        assert!(plugin.execute("greet", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn runs_spec_files() {
+        let reports = run_specs(
+            &fixture(),
+            Some(json!({ "greeting": "Hi" })),
+            HttpMode::Replay,
+        )
+        .await
+        .unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].file, PathBuf::from("greeter_spec.lua"));
+
+        let names: Vec<_> = reports[0].cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "greet uses the configured greeting",
+                "greet stays open when cancelled",
+                "greet sees the focused window",
+                "greet fails on purpose",
+            ]
+        );
+        let failures: Vec<_> = reports[0].failures().collect();
+        assert_eq!(failures.len(), 1);
+        let error = failures[0].error.as_deref().unwrap();
+        assert!(error.contains("expected \"Bye\", got \"Hi\""), "{}", error);
+    }
+}
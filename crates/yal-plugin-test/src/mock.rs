@@ -0,0 +1,121 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use anyhow::{Result, bail};
+use serde_json::Value;
+use yal_core::{Prompt, PromptResponse};
+use yal_plugin::backend::{Backend, RequestId};
+
+/// A `Backend` that records what a plugin asks of the host and answers
+/// prompts from a script instead of a UI.
+///
+/// Clones share state, so a test can keep one handle while the plugin owns
+/// another.
+#[derive(Clone, Default)]
+pub struct MockBackend {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    prompts: Vec<Prompt>,
+    submissions: VecDeque<PromptResponse>,
+    states: VecDeque<PromptResponse>,
+    cancelled: Vec<RequestId>,
+    visibility: Vec<bool>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Answers the next `prompt:submission()` with these form values.
+    pub fn push_submit(&self, values: Value) {
+        self.inner()
+            .submissions
+            .push_back(PromptResponse::Submit { values });
+    }
+
+    /// Answers the next submission (or state poll) as if the user pressed Escape.
+    pub fn push_cancel(&self) {
+        self.inner().submissions.push_back(PromptResponse::Cancel);
+    }
+
+    /// Answers the next `prompt:state()` poll with these in-progress values.
+    pub fn push_state(&self, values: Value) {
+        self.inner()
+            .states
+            .push_back(PromptResponse::State { values });
+    }
+
+    /// Every prompt shown so far, oldest first.
+    pub fn prompts(&self) -> Vec<Prompt> {
+        self.inner().prompts.clone()
+    }
+
+    /// Ids of prompts the plugin (or a cancelled submission) closed.
+    pub fn cancelled(&self) -> Vec<RequestId> {
+        self.inner().cancelled.clone()
+    }
+
+    /// Every `set_visibility` call, oldest first.
+    pub fn visibility(&self) -> Vec<bool> {
+        self.inner().visibility.clone()
+    }
+
+    /// Forgets recorded calls and unused scripted responses.
+    pub fn reset(&self) {
+        *self.inner() = Inner::default();
+    }
+}
+
+impl Backend for MockBackend {
+    async fn prompt(&self, prompt: Prompt) -> Result<RequestId> {
+        let mut inner = self.inner();
+        inner.prompts.push(prompt);
+        Ok(format!("prompt-{}", inner.prompts.len()))
+    }
+
+    async fn prompt_state(&self, id: RequestId) -> Result<PromptResponse> {
+        // Once the state script runs out, polling sees the scripted submission,
+        // which is how a real form ends.
+        let mut inner = self.inner();
+        match inner.states.pop_front() {
+            Some(response) => Ok(response),
+            None => match inner.submissions.pop_front() {
+                Some(response) => Ok(response),
+                None => bail!(
+                    "no scripted response for {} (use ui.state or ui.submit)",
+                    id
+                ),
+            },
+        }
+    }
+
+    async fn prompt_submission(&self, id: RequestId) -> Result<PromptResponse> {
+        match self.inner().submissions.pop_front() {
+            Some(response) => Ok(response),
+            None => bail!(
+                "no scripted submission for {} (use ui.submit or ui.cancel)",
+                id
+            ),
+        }
+    }
+
+    async fn prompt_cancel(&self, id: RequestId) -> Result<()> {
+        self.inner().cancelled.push(id);
+        Ok(())
+    }
+
+    async fn set_visibility(&self, visible: bool) -> Result<()> {
+        self.inner().visibility.push(visible);
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+local t = require("yal.test")
+
+t.describe("greet", function()
+  t.it("uses the configured greeting", function()
+    t.ui.submit({ name = "Ada" })
+    t.eq(t.execute("greet"), { hide = true })
+    t.eq(t.plugin.last_greeting, "Hi, Ada")
+    t.eq(t.visibility(), { false })
+  end)
+
+  t.it("stays open when cancelled", function()
+    t.ui.cancel()
+    t.eq(t.execute("greet").hide, false)
+    t.eq(t.ui.cancelled(), { "prompt-1" })
+  end)
+
+  t.it("sees the focused window", function()
+    t.context.windows[1].is_focused = false
+    t.context.windows[2].is_focused = true
+    t.ui.submit({ name = "Ada" })
+    t.execute("greet")
+    t.eq(t.ui.prompts()[1].title, "Hi from Safari")
+  end)
+
+  t.it("fails on purpose", function()
+    t.ui.submit({ name = "Ada" })
+    t.execute("greet")
+    t.eq(t.ui.prompts()[1].title:match("^%a+"), "Bye")
+  end)
+end)
@@ -0,0 +1,56 @@
+local ui = require("yal.ui")
+
+local M = {}
+
+local greeting = "Hello"
+
+function M.init(config)
+  if config and config.greeting then
+    greeting = config.greeting
+  end
+  return {
+    name = "greeter",
+    version = "0.1.0",
+    api_version = "1.1",
+    commands = {
+      { name = "greet", description = "Say hello" },
+    },
+  }
+end
+
+local function focused_app(context)
+  for _, window in ipairs(context.windows) do
+    if window.is_focused then
+      return window.app_name
+    end
+  end
+  return "nowhere"
+end
+
+function M.execute(req)
+  if req.command ~= "greet" then
+    error("unknown command: " .. tostring(req.command))
+  end
+
+  local prompt = ui.prompt({
+    title = greeting .. " from " .. focused_app(req.context),
+    content = {
+      { type = "text", text = "Who should be greeted?" },
+    },
+  })
+  local ok, values = pcall(function()
+    return prompt:submission()
+  end)
+  if not ok then
+    if tostring(values):find("cancelled") then
+      return { hide = false }
+    end
+    error(values, 0)
+  end
+
+  M.last_greeting = greeting .. ", " .. values.name
+  ui.visibility():hide()
+  return { hide = true }
+end
+
+return M
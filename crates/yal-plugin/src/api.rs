@@ -0,0 +1,113 @@
+//! Versioning of the API plugins are written against: the `yal.*` Lua
+//! modules, the WIT world and the process protocol.
+//!
+//! Plugins declare the range they need as `api_version` in their `init`
+//! response; plugins whose range the host does not satisfy are not loaded.
+
+use std::{
+    collections::HashSet,
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+
+/// Bumped with every change to the plugin API: minor for additions, major
+/// for removals and other breaking changes.
+pub const API_VERSION: Version = Version::new(1, 5, 0);
+
+/// Assumed for plugins that do not declare `api_version`: the API as it was
+/// before it was versioned.
+pub const IMPLICIT_REQUIREMENT: &str = "^1.0";
+
+/// The current API as a plugin would require it, e.g. `"1.5"`.
+pub fn current_requirement() -> String {
+    format!("{}.{}", API_VERSION.major, API_VERSION.minor)
+}
+
+/// Parses a plugin's `api_version`. A bare version such as `"1.1"` means
+/// "1.1 or any later 1.x", as in Cargo.
+pub fn requirement(api_version: &str) -> Result<VersionReq> {
+    VersionReq::parse(api_version).with_context(|| format!("invalid api_version '{}'", api_version))
+}
+
+/// Whether this host can run a plugin that needs `api_version`.
+pub fn is_compatible(api_version: &VersionReq) -> bool {
+    api_version.matches(&API_VERSION)
+}
+
+/// Why a plugin was refused: it needs an API version this host does not
+/// provide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncompatibleApi {
+    pub required: String,
+}
+
+impl std::fmt::Display for IncompatibleApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "plugin needs API version {}, but this host provides {}",
+            self.required, API_VERSION
+        )
+    }
+}
+
+impl std::error::Error for IncompatibleApi {}
+
+/// Checks the `api_version` from a plugin's `init` response.
+pub fn check(plugin: &str, api_version: Option<&str>) -> Result<()> {
+    let required = match api_version {
+        Some(api_version) => api_version,
+        None => {
+            warn_once(
+                plugin,
+                "api_version",
+                format_args!(
+                    "Plugin '{}' does not declare api_version, so it is loaded as if it \
+                     needed {}; it will fail at runtime if it uses anything newer. Return \
+                     api_version = \"{}\" from init() instead",
+                    plugin,
+                    IMPLICIT_REQUIREMENT,
+                    current_requirement()
+                ),
+            );
+            IMPLICIT_REQUIREMENT
+        }
+    };
+    if is_compatible(&requirement(required)?) {
+        Ok(())
+    } else {
+        Err(IncompatibleApi {
+            required: required.to_string(),
+        }
+        .into())
+    }
+}
+
+/// Logs `message` the first time it comes up for `plugin` and `topic`.
+fn warn_once(plugin: &str, topic: &str, message: std::fmt::Arguments) {
+    static WARNED: LazyLock<Mutex<HashSet<(String, String)>>> = LazyLock::new(Default::default);
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if warned.insert((plugin.to_string(), topic.to_string())) {
+        log::warn!("{}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_requirements_against_the_host() {
+        assert!(is_compatible(&requirement("1").unwrap()));
+        assert!(is_compatible(&requirement("1.1").unwrap()));
+        assert!(is_compatible(&requirement("1.5").unwrap()));
+        assert!(is_compatible(&requirement(IMPLICIT_REQUIREMENT).unwrap()));
+        assert!(is_compatible(&requirement(">=1.0, <3").unwrap()));
+        let newer = format!("{}.{}", API_VERSION.major, API_VERSION.minor + 1);
+        assert!(!is_compatible(&requirement(&newer).unwrap()));
+        assert!(!is_compatible(&requirement("2").unwrap()));
+        assert!(requirement("latest").is_err());
+    }
+}
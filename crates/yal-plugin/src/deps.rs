@@ -7,16 +7,30 @@ use crate::{backend::Backend, protocol::PluginAPIRequest};
 
 pub mod base64;
 pub mod db;
+pub mod events;
 pub mod http;
 pub mod json;
 pub mod log;
+pub mod plugins;
 pub mod socket;
+pub mod task;
 pub mod ui;
 pub mod vendor;
+pub mod version;
 
 pub struct InstallOptions<'a> {
+    /// Name of the plugin the modules are installed for, used in its log lines.
+    pub plugin: &'a str,
+    /// Set when the plugin runs as one of several instances of a checkout
+    pub instance: Option<&'a str>,
     pub vendor_dir: Option<&'a std::path::Path>,
+    /// More directories the vendor searcher looks in, after `vendor_dir`
+    pub package_roots: &'a [std::path::PathBuf],
     pub http_limits: Option<http::HttpLimits>,
+    pub http_transport: http::HttpTransport,
+    pub dependencies: plugins::Dependencies,
+    pub events: events::EventQueue,
+    pub tasks: task::Tasks,
 }
 
 pub fn install_all<B: Backend>(
@@ -27,20 +41,33 @@ pub fn install_all<B: Backend>(
     json::install_json_preload(lua)?;
 
     let limits = opts.http_limits.unwrap_or_default();
-    let env = http::HttpEnv::new(limits)?;
+    let env = http::HttpEnv::with_transport(limits, opts.http_transport)?;
     http::install_http_preload(lua, env)?;
     socket::install_socket_preload(lua)?;
 
     base64::install_base64_preload(lua)?;
 
-    log::install_log_preload(lua)?;
+    log::install_log_preload(lua, opts.plugin)?;
+
+    version::install_version_preload(lua)?;
 
     ui::install_ui_preload(lua, plugin_backend.clone())?;
 
-    db::install_db_preload(lua)?;
+    db::install_db_preload(lua, opts.instance)?;
+
+    plugins::install_plugins_preload(lua, opts.plugin, opts.dependencies)?;
+
+    events::install_events_preload(lua, opts.events)?;
+
+    task::install_task_preload(lua, opts.tasks)?;
 
-    if let Some(vendor_dir) = opts.vendor_dir {
-        vendor::add_vendor_searcher(lua, vendor_dir)?;
+    let roots: Vec<&std::path::Path> = opts
+        .vendor_dir
+        .into_iter()
+        .chain(opts.package_roots.iter().map(|p| p.as_path()))
+        .collect();
+    if !roots.is_empty() {
+        vendor::add_vendor_searcher(lua, &roots)?;
     }
 
     Ok(())
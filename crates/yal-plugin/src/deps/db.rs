@@ -53,21 +53,26 @@ fn fallback_home() -> Option<PathBuf> {
     home_dir().map(|h| h.join(".yal"))
 }
 
-fn db_path_for(namespace: &str) -> PathBuf {
+/// Directory the `yal.db` namespaces of every plugin are stored in.
+pub(crate) fn db_dir() -> PathBuf {
     // 1) XDG_STATE_HOME
     if let Some(root) = xdg_state_home() {
-        return root.join("yal/plugins").join(format!("{namespace}.json"));
+        return root.join("yal/plugins");
     }
     // 2) XDG_CONFIG_HOME
     if let Some(root) = xdg_config_home() {
-        return root.join("yal/plugins").join(format!("{namespace}.json"));
+        return root.join("yal/plugins");
     }
     // 3) ~/.yal (last resort)
     if let Some(root) = fallback_home() {
-        return root.join("plugins").join(format!("{namespace}.json"));
+        return root.join("plugins");
     }
     // 4) cwd/plugins
-    PathBuf::from("plugins").join(format!("{namespace}.json"))
+    PathBuf::from("plugins")
+}
+
+pub(crate) fn db_path_for(namespace: &str) -> PathBuf {
+    db_dir().join(format!("{namespace}.json"))
 }
 
 /* ----------------------------- IO helpers ---------------------------- */
@@ -79,7 +84,7 @@ fn ensure_parent_dir(p: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-fn load_map_from(path: &Path) -> json::Map<String, JValue> {
+pub(crate) fn load_map_from(path: &Path) -> json::Map<String, JValue> {
     match fs::File::open(path) {
         Ok(mut f) => {
             let mut s = String::new();
@@ -99,7 +104,10 @@ fn load_map_from(path: &Path) -> json::Map<String, JValue> {
     }
 }
 
-fn atomic_write_json(path: &Path, map: &json::Map<String, JValue>) -> std::io::Result<()> {
+pub(crate) fn atomic_write_json(
+    path: &Path,
+    map: &json::Map<String, JValue>,
+) -> std::io::Result<()> {
     ensure_parent_dir(path)?;
     let tmp = path.with_extension("json.tmp");
     {
@@ -217,15 +225,22 @@ impl UserData for KV {
 
 /* --------------------------- Module preload -------------------------- */
 
-pub fn install_db_preload(lua: &Lua) -> LuaResult<()> {
+/// With an `instance`, namespaces are stored under a directory of that
+/// name, so instances of one plugin do not share data.
+pub fn install_db_preload(lua: &Lua, instance: Option<&str>) -> LuaResult<()> {
     let pkg: Table = lua.globals().get("package")?;
     let preload: Table = pkg.get("preload")?;
 
-    let loader = lua.create_function(|lua, ()| {
+    let instance = instance.map(str::to_string);
+    let loader = lua.create_function(move |lua, ()| {
         let m = lua.create_table()?;
 
-        let open_fn = lua.create_function(|lua, namespace: String| {
-            let path = db_path_for(&namespace);
+        let instance = instance.clone();
+        let open_fn = lua.create_function(move |lua, namespace: String| {
+            let path = match &instance {
+                Some(instance) => db_path_for(&format!("{instance}/{namespace}")),
+                None => db_path_for(&namespace),
+            };
             if let Err(e) = ensure_parent_dir(&path) {
                 return Err(mlua::Error::external(e));
             }
@@ -0,0 +1,206 @@
+//! `yal.events`: Lua callbacks for system events.
+//!
+//! Each plugin has a bounded queue that only takes events of topics it
+//! subscribed to. A worker task delivers them to the callbacks one at a
+//! time, in order. When a plugin falls behind and its queue is full, new
+//! events for it are dropped rather than holding up the host or other
+//! plugins.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use mlua::{Function, Lua, LuaSerdeExt, Result as LuaResult, Table};
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::events::SystemEvent;
+
+pub const QUEUE_CAPACITY: usize = 64;
+
+const DISPATCH_KEY: &str = "yal.events.dispatch";
+const MODULE_KEY: &str = "yal.events";
+
+/// Keeps callbacks in Lua, so the host holds no references into the state.
+const MODULE: &str = r#"
+local topics, watch = ...
+local handlers, next_id = {}, 0
+local M = {}
+
+function M.subscribe(topic, callback)
+  if not topics[topic] then error("unknown event topic '" .. tostring(topic) .. "'", 2) end
+  if type(callback) ~= "function" then error("callback must be a function", 2) end
+  next_id = next_id + 1
+  handlers[topic] = handlers[topic] or {}
+  handlers[topic][next_id] = callback
+  watch(topic, 1)
+  return next_id
+end
+
+function M.unsubscribe(id)
+  for topic, callbacks in pairs(handlers) do
+    if callbacks[id] then
+      callbacks[id] = nil
+      watch(topic, -1)
+      return true
+    end
+  end
+  return false
+end
+
+local function dispatch(event)
+  local errors = {}
+  local callbacks = {}
+  for id, callback in pairs(handlers[event.topic] or {}) do
+    callbacks[#callbacks + 1] = { id = id, callback = callback }
+  end
+  table.sort(callbacks, function(a, b) return a.id < b.id end)
+  for _, entry in ipairs(callbacks) do
+    local ok, err = pcall(entry.callback, event)
+    if not ok then errors[#errors + 1] = tostring(err) end
+  end
+  return errors
+end
+
+return M, dispatch
+"#;
+
+/// A plugin's event queue. Clones share it.
+#[derive(Clone)]
+pub struct EventQueue {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    plugin: String,
+    tx: kanal::Sender<SystemEvent>,
+    /// Taken by the worker when the first subscription starts it.
+    rx: Mutex<Option<kanal::Receiver<SystemEvent>>>,
+    /// Subscriptions per topic
+    topics: Mutex<HashMap<String, usize>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+    dropped: AtomicU64,
+    overflowing: AtomicBool,
+}
+
+impl EventQueue {
+    pub fn new(plugin: &str) -> Self {
+        let (tx, rx) = kanal::bounded(QUEUE_CAPACITY);
+        Self {
+            inner: Arc::new(Inner {
+                plugin: plugin.to_string(),
+                tx,
+                rx: Mutex::new(Some(rx)),
+                topics: Mutex::new(HashMap::new()),
+                worker: Mutex::new(None),
+                dropped: AtomicU64::new(0),
+                overflowing: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Queues `event` if the plugin subscribed to its topic. Never waits.
+    pub fn publish(&self, event: &SystemEvent) {
+        let inner = &self.inner;
+        if !inner.topics.lock().contains_key(event.topic()) {
+            return;
+        }
+        match inner.tx.try_send(event.clone()) {
+            Ok(true) => inner.overflowing.store(false, Ordering::Relaxed),
+            _ => {
+                inner.dropped.fetch_add(1, Ordering::Relaxed);
+                if !inner.overflowing.swap(true, Ordering::Relaxed) {
+                    log::warn!(
+                        "[{}] event queue is full, dropping events until it catches up",
+                        inner.plugin
+                    );
+                }
+            }
+        }
+    }
+
+    /// Events dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stops delivering events.
+    pub fn stop(&self) {
+        if let Some(worker) = self.inner.worker.lock().take() {
+            worker.abort();
+        }
+    }
+
+    fn watch(&self, lua: &Lua, topic: String, delta: i64) -> LuaResult<()> {
+        {
+            let mut topics = self.inner.topics.lock();
+            let count = topics.entry(topic.clone()).or_default();
+            *count = count.saturating_add_signed(delta as isize);
+            if *count == 0 {
+                topics.remove(&topic);
+            }
+        }
+        if delta > 0 {
+            self.start(lua)?;
+        }
+        Ok(())
+    }
+
+    fn start(&self, lua: &Lua) -> LuaResult<()> {
+        let Some(rx) = self.inner.rx.lock().take() else {
+            return Ok(());
+        };
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|_| mlua::Error::runtime("yal.events needs the host's runtime"))?;
+        let lua = lua.weak();
+        let plugin = self.inner.plugin.clone();
+        let worker = runtime.spawn(async move {
+            let rx = rx.to_async();
+            while let Ok(event) = rx.recv().await {
+                let Some(lua) = lua.try_upgrade() else {
+                    break;
+                };
+                if let Err(e) = deliver(&lua, &event).await {
+                    log::warn!("[{}] failed delivering {}: {}", plugin, event.topic(), e);
+                }
+            }
+        });
+        *self.inner.worker.lock() = Some(worker);
+        Ok(())
+    }
+}
+
+async fn deliver(lua: &Lua, event: &SystemEvent) -> LuaResult<()> {
+    let dispatch: Function = lua.named_registry_value(DISPATCH_KEY)?;
+    let errors: Vec<String> = dispatch.call_async(lua.to_value(event)?).await?;
+    for error in errors {
+        log::warn!("{} callback failed: {}", event.topic(), error);
+    }
+    Ok(())
+}
+
+pub fn install_events_preload(lua: &Lua, queue: EventQueue) -> LuaResult<()> {
+    let topics = lua.create_table()?;
+    for topic in SystemEvent::TOPICS {
+        topics.set(topic, true)?;
+    }
+    let watch = lua.create_function(move |lua, (topic, delta): (String, i64)| {
+        queue.watch(lua, topic, delta)
+    })?;
+    let (module, dispatch): (Table, Function) = lua
+        .load(MODULE)
+        .set_name("yal.events")
+        .call((topics, watch))?;
+    lua.set_named_registry_value(DISPATCH_KEY, dispatch)?;
+    lua.set_named_registry_value(MODULE_KEY, module)?;
+
+    let pkg: Table = lua.globals().get("package")?;
+    let preload: Table = pkg.get("preload")?;
+    let loader = lua.create_function(|lua, ()| lua.named_registry_value::<Table>(MODULE_KEY))?;
+    preload.set("yal.events", loader)?;
+    Ok(())
+}
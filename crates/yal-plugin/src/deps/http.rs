@@ -1,4 +1,4 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use futures::TryStreamExt;
 use mlua::{Error as LuaError, Lua, LuaSerdeExt, Result as LuaResult, Table, Value};
 use parking_lot::Mutex;
@@ -6,6 +6,12 @@ use reqwest::{Client, Method, StatusCode, redirect::Policy};
 use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::{fs::File, io::AsyncWriteExt, sync::Semaphore};
 
+use cassette::{Cassette, RecordedRequest};
+
+pub mod cassette;
+
+pub use cassette::HttpTransport;
+
 #[derive(Clone)]
 pub struct HttpLimits {
     pub max_concurrent: usize,
@@ -31,10 +37,21 @@ pub struct HttpEnv {
     limits: HttpLimits,
     gate: Arc<Semaphore>,
     default_headers: Arc<Mutex<HashMap<String, String>>>,
+    transport: Arc<Transport>,
+}
+
+enum Transport {
+    Live,
+    Record(Mutex<Cassette>),
+    Replay(Mutex<Cassette>),
 }
 
 impl HttpEnv {
     pub fn new(limits: HttpLimits) -> anyhow::Result<Self> {
+        Self::with_transport(limits, HttpTransport::Live)
+    }
+
+    pub fn with_transport(limits: HttpLimits, transport: HttpTransport) -> anyhow::Result<Self> {
         let client = Client::builder()
             .redirect(Policy::limited(limits.default_max_redirects))
             .tcp_keepalive(Some(Duration::from_secs(30)))
@@ -46,23 +63,61 @@ impl HttpEnv {
             limits: limits.clone(),
             gate: Arc::new(Semaphore::new(limits.max_concurrent)),
             default_headers: Arc::new(Mutex::new(HashMap::new())),
+            transport: Arc::new(match transport {
+                HttpTransport::Live => Transport::Live,
+                HttpTransport::Record(path) => {
+                    Transport::Record(Mutex::new(Cassette::record(&path)))
+                }
+                HttpTransport::Replay(path) => {
+                    Transport::Replay(Mutex::new(Cassette::load(&path)?))
+                }
+            }),
         })
     }
+
+    /// Options for a bodiless request with this env's default limits.
+    pub(crate) fn request_opts(&self, method: Method, url: String) -> RequestOpts {
+        RequestOpts {
+            method,
+            url,
+            headers: HashMap::new(),
+            query: HashMap::new(),
+            timeout_ms: self.limits.default_timeout_ms,
+            max_body_bytes: self.limits.default_max_body_bytes,
+            max_redirects: self.limits.default_max_redirects,
+            body_text: None,
+            body_json: None,
+            body_bytes: None,
+            save_to: None,
+        }
+    }
+
+    /// Sends a request the way `yal.http.request` does, for non-Lua runtimes.
+    pub(crate) async fn execute(
+        &self,
+        opts: RequestOpts,
+    ) -> anyhow::Result<(StatusCode, HashMap<String, String>, Vec<u8>)> {
+        execute_request(self.clone(), opts).await
+    }
+
+    pub(crate) fn set_default_header(&self, name: String, value: String) {
+        self.default_headers.lock().insert(name, value);
+    }
 }
 
 #[derive(Debug, Clone)]
-struct RequestOpts {
-    method: Method,
-    url: String,
-    headers: HashMap<String, String>,
-    query: HashMap<String, String>,
-    timeout_ms: u64,
-    max_body_bytes: usize,
-    max_redirects: usize,
-    body_text: Option<String>,
-    body_json: Option<serde_json::Value>,
-    body_bytes: Option<Vec<u8>>,
-    save_to: Option<String>,
+pub(crate) struct RequestOpts {
+    pub(crate) method: Method,
+    pub(crate) url: String,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) query: HashMap<String, String>,
+    pub(crate) timeout_ms: u64,
+    pub(crate) max_body_bytes: usize,
+    pub(crate) max_redirects: usize,
+    pub(crate) body_text: Option<String>,
+    pub(crate) body_json: Option<serde_json::Value>,
+    pub(crate) body_bytes: Option<Vec<u8>>,
+    pub(crate) save_to: Option<String>,
 }
 
 fn lua_table_to_map(v: Option<Value>) -> LuaResult<HashMap<String, String>> {
@@ -105,6 +160,48 @@ fn parse_method(s: &str) -> LuaResult<Method> {
 
 async fn execute_request(
     env: HttpEnv,
+    mut opts: RequestOpts,
+) -> anyhow::Result<(StatusCode, HashMap<String, String>, Vec<u8>)> {
+    match env.transport.as_ref() {
+        Transport::Live => send_request(&env, opts).await,
+        Transport::Record(cassette) => {
+            let request = RecordedRequest::new(&opts)?;
+            // Buffer the body so it can be recorded, then save it like a live request would.
+            let save_to = opts.save_to.take();
+            let (status, headers, body) = send_request(&env, opts).await?;
+            cassette.lock().push(request, status, &headers, &body)?;
+            finish_response(save_to, status, headers, body).await
+        }
+        Transport::Replay(cassette) => {
+            let request = RecordedRequest::new(&opts)?;
+            let (status, headers, body) = cassette.lock().replay(&request)?;
+            if body.len() > opts.max_body_bytes {
+                bail!("body exceeds max_body_bytes");
+            }
+            finish_response(opts.save_to, status, headers, body).await
+        }
+    }
+}
+
+async fn finish_response(
+    save_to: Option<String>,
+    status: StatusCode,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+) -> anyhow::Result<(StatusCode, HashMap<String, String>, Vec<u8>)> {
+    match save_to {
+        Some(path) => {
+            let mut file = File::create(&path).await?;
+            file.write_all(&body).await?;
+            file.flush().await?;
+            Ok((status, headers, Vec::new()))
+        }
+        None => Ok((status, headers, body)),
+    }
+}
+
+async fn send_request(
+    env: &HttpEnv,
     opts: RequestOpts,
 ) -> anyhow::Result<(StatusCode, HashMap<String, String>, Vec<u8>)> {
     let _permit = env.gate.acquire().await.unwrap();
@@ -333,7 +430,7 @@ pub fn install_http_preload(lua: &Lua, env: HttpEnv) -> LuaResult<()> {
             // --- set_default_header(k, v) -------------------------------------
             let env_hdr = env_arc.clone();
             let set_hdr = lua.create_function(move |_, (k, v): (String, String)| {
-                env_hdr.default_headers.lock().insert(k, v);
+                env_hdr.set_default_header(k, v);
                 Ok(())
             })?;
             m.set("set_default_header", set_hdr)?;
@@ -0,0 +1,275 @@
+//! Recorded HTTP exchanges ("cassettes") for testing plugins offline.
+//!
+//! A cassette is a JSON file of request/response pairs. Requests are matched
+//! on method, URL (including the query) and body; request headers are not
+//! recorded so tokens set through headers never end up on disk.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+
+use super::RequestOpts;
+
+/// Where `yal.http` requests go.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum HttpTransport {
+    /// Send requests to the network.
+    #[default]
+    Live,
+    /// Send requests to the network and write every exchange to a cassette,
+    /// replacing what was there.
+    Record(PathBuf),
+    /// Answer requests from a cassette; requests it does not contain fail.
+    Replay(PathBuf),
+}
+
+/// Response headers that are not worth keeping (or are secret).
+const SKIPPED_HEADERS: &[&str] = &["set-cookie", "date"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Body {
+    Text(String),
+    Base64(String),
+}
+
+impl Body {
+    fn new(bytes: &[u8]) -> Option<Self> {
+        if bytes.is_empty() {
+            return None;
+        }
+        Some(match std::str::from_utf8(bytes) {
+            Ok(text) => Body::Text(text.to_string()),
+            Err(_) => Body::Base64(STANDARD.encode(bytes)),
+        })
+    }
+
+    fn bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            Body::Text(text) => Ok(text.as_bytes().to_vec()),
+            Body::Base64(b64) => Ok(STANDARD.decode(b64)?),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Body>,
+}
+
+impl RecordedRequest {
+    pub(super) fn new(opts: &RequestOpts) -> Result<Self> {
+        let mut url = Url::parse(&opts.url).with_context(|| format!("invalid URL {}", opts.url))?;
+        if !opts.query.is_empty() {
+            let mut query: Vec<_> = opts.query.iter().collect();
+            query.sort();
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        let body = if let Some(json) = &opts.body_json {
+            Some(Body::Text(serde_json::to_string(json)?))
+        } else if let Some(text) = &opts.body_text {
+            Body::new(text.as_bytes())
+        } else {
+            opts.body_bytes.as_deref().and_then(Body::new)
+        };
+        Ok(Self {
+            method: opts.method.to_string(),
+            url: url.to_string(),
+            body,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Body>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+pub struct Cassette {
+    path: PathBuf,
+    interactions: Vec<Interaction>,
+    /// Per interaction, whether it has been replayed.
+    used: Vec<bool>,
+}
+
+impl Cassette {
+    /// An empty cassette that will be written to `path`.
+    pub fn record(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            interactions: Vec::new(),
+            used: Vec::new(),
+        }
+    }
+
+    /// Reads `path`. A missing file is an empty cassette, so every request
+    /// fails to match.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file: CassetteFile = match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("invalid cassette {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CassetteFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            used: vec![false; file.interactions.len()],
+            interactions: file.interactions,
+        })
+    }
+
+    /// Appends an exchange and rewrites the file.
+    pub fn push(
+        &mut self,
+        request: RecordedRequest,
+        status: StatusCode,
+        headers: &HashMap<String, String>,
+        body: &[u8],
+    ) -> Result<()> {
+        let headers = headers
+            .iter()
+            .filter(|(k, _)| !SKIPPED_HEADERS.contains(&k.to_ascii_lowercase().as_str()))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        self.interactions.push(Interaction {
+            request,
+            response: RecordedResponse {
+                status: status.as_u16(),
+                headers,
+                body: Body::new(body),
+            },
+        });
+        self.used.push(false);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = CassetteFile {
+            interactions: self.interactions.clone(),
+        };
+        std::fs::write(&self.path, serde_json::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed writing cassette {}", self.path.display()))
+    }
+
+    /// The response for `request`. Identical requests are answered in the
+    /// order they were recorded; once those run out the last one repeats.
+    pub fn replay(
+        &mut self,
+        request: &RecordedRequest,
+    ) -> Result<(StatusCode, HashMap<String, String>, Vec<u8>)> {
+        let matching: Vec<usize> = (0..self.interactions.len())
+            .filter(|&i| self.interactions[i].request == *request)
+            .collect();
+        let Some(&index) = matching
+            .iter()
+            .find(|&&i| !self.used[i])
+            .or(matching.last())
+        else {
+            bail!(
+                "no recorded response for {} {} in {}",
+                request.method,
+                request.url,
+                self.path.display()
+            );
+        };
+        self.used[index] = true;
+
+        let response = &self.interactions[index].response;
+        let body = match &response.body {
+            Some(body) => body.bytes()?,
+            None => Vec::new(),
+        };
+        Ok((
+            StatusCode::from_u16(response.status)?,
+            response.headers.clone().into_iter().collect(),
+            body,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(url: &str) -> RecordedRequest {
+        RecordedRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            body: None,
+        }
+    }
+
+    #[test]
+    fn replays_recorded_exchanges() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("nested/http.json");
+        let headers = HashMap::from([
+            ("content-type".to_string(), "application/json".to_string()),
+            ("set-cookie".to_string(), "session=secret".to_string()),
+        ]);
+
+        let mut cassette = Cassette::record(&path);
+        let url = "https://api.example.com/items?page=1";
+        cassette
+            .push(get(url), StatusCode::OK, &headers, b"[1]")
+            .unwrap();
+        cassette
+            .push(get(url), StatusCode::OK, &headers, b"[2]")
+            .unwrap();
+        cassette
+            .push(
+                get("https://api.example.com/bin"),
+                StatusCode::OK,
+                &headers,
+                &[0xff, 0],
+            )
+            .unwrap();
+
+        let mut cassette = Cassette::load(&path).unwrap();
+        let (status, headers, body) = cassette.replay(&get(url)).unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"[1]");
+        assert_eq!(headers.get("content-type").unwrap(), "application/json");
+        assert!(!headers.contains_key("set-cookie"));
+        assert_eq!(cassette.replay(&get(url)).unwrap().2, b"[2]");
+        assert_eq!(cassette.replay(&get(url)).unwrap().2, b"[2]");
+        assert_eq!(
+            cassette
+                .replay(&get("https://api.example.com/bin"))
+                .unwrap()
+                .2,
+            vec![0xff, 0]
+        );
+
+        let err = cassette
+            .replay(&get("https://api.example.com/items?page=2"))
+            .unwrap_err();
+        assert!(err.to_string().contains("no recorded response"));
+        assert!(Cassette::load(&dir.join("missing.json")).is_ok());
+    }
+}
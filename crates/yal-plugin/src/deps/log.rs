@@ -1,26 +1,31 @@
 use mlua::{Lua, Result as LuaResult, Table};
 
-pub fn install_log_preload(lua: &Lua) -> LuaResult<()> {
+pub fn install_log_preload(lua: &Lua, plugin: &str) -> LuaResult<()> {
     let pkg: Table = lua.globals().get("package")?;
     let preload: Table = pkg.get("preload")?;
 
-    let loader = lua.create_function(|lua, ()| {
+    let plugin = plugin.to_string();
+    let loader = lua.create_function(move |lua, ()| {
         let m = lua.create_table()?;
 
-        let debug = lua.create_function(|_, msg: String| {
-            log::debug!("[lua] {}", msg);
+        let name = plugin.clone();
+        let debug = lua.create_function(move |_, msg: String| {
+            log::debug!("[{}] {}", name, msg);
             Ok(())
         })?;
-        let info = lua.create_function(|_, msg: String| {
-            log::info!("[lua] {}", msg);
+        let name = plugin.clone();
+        let info = lua.create_function(move |_, msg: String| {
+            log::info!("[{}] {}", name, msg);
             Ok(())
         })?;
-        let warn = lua.create_function(|_, msg: String| {
-            log::warn!("[lua] {}", msg);
+        let name = plugin.clone();
+        let warn = lua.create_function(move |_, msg: String| {
+            log::warn!("[{}] {}", name, msg);
             Ok(())
         })?;
-        let error = lua.create_function(|_, msg: String| {
-            log::error!("[lua] {}", msg);
+        let name = plugin.clone();
+        let error = lua.create_function(move |_, msg: String| {
+            log::error!("[{}] {}", name, msg);
             Ok(())
         })?;
 
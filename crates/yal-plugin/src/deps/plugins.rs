@@ -0,0 +1,87 @@
+use std::{collections::HashMap, sync::Arc};
+
+use mlua::{Lua, LuaSerdeExt, Result as LuaResult, Table, Value};
+
+use crate::plugin::LuaPlugin;
+
+/// Lua plugins a plugin declared as dependencies, by name.
+pub type Dependencies = HashMap<String, Arc<LuaPlugin>>;
+
+/// Adds a searcher for `require("plugin:<name>")`. The module it loads is a
+/// table whose functions forward their arguments to `yal.plugins.call`.
+const SEARCHER: &str = r#"
+local caller, names = ...
+table.insert(package.searchers, 2, function(modname)
+  local name = modname:match("^plugin:(.+)$")
+  if not name then return nil end
+  if not names[name] then
+    return "plugin '" .. name .. "' is not a Lua plugin '" .. caller .. "' depends on (see manifest.lua)"
+  end
+  return function()
+    local call = require("yal.plugins").call
+    return setmetatable({}, {
+      __index = function(t, fn)
+        local f = function(...) return call(name, fn, { ... }) end
+        rawset(t, fn, f)
+        return f
+      end,
+    })
+  end
+end)
+"#;
+
+/// Installs `yal.plugins` and `require("plugin:<name>")`. Both only reach
+/// `dependencies`; arguments and results cross between plugins as JSON.
+pub fn install_plugins_preload(
+    lua: &Lua,
+    plugin: &str,
+    dependencies: Dependencies,
+) -> LuaResult<()> {
+    let names = lua.create_table()?;
+    for name in dependencies.keys() {
+        names.set(name.as_str(), true)?;
+    }
+    lua.load(SEARCHER)
+        .set_name("yal.plugins.searcher")
+        .call::<()>((plugin, names))?;
+
+    let pkg: Table = lua.globals().get("package")?;
+    let preload: Table = pkg.get("preload")?;
+
+    let dependencies = Arc::new(dependencies);
+    let caller = plugin.to_string();
+    let loader = lua.create_function(move |lua, ()| {
+        let m = lua.create_table()?;
+
+        // call(name, fn, args?) -> value
+        let dependencies = dependencies.clone();
+        let caller = caller.clone();
+        let call = lua.create_async_function(
+            move |lua, (name, function, args): (String, String, Value)| {
+                let target = dependencies.get(&name).cloned();
+                let caller = caller.clone();
+                async move {
+                    let target = target.ok_or_else(|| {
+                        mlua::Error::runtime(format!(
+                            "plugin '{}' is not a Lua plugin '{}' depends on",
+                            name, caller
+                        ))
+                    })?;
+                    let args: Vec<serde_json::Value> = match args {
+                        Value::Nil => Vec::new(),
+                        args => lua.from_value(args)?,
+                    };
+                    let result = target.call_export(&function, args).await.map_err(|e| {
+                        mlua::Error::runtime(format!("{}.{}: {:#}", name, function, e))
+                    })?;
+                    lua.to_value(&result)
+                }
+            },
+        )?;
+        m.set("call", call)?;
+        Ok(m)
+    })?;
+
+    preload.set("yal.plugins", loader)?;
+    Ok(())
+}
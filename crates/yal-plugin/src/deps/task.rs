@@ -0,0 +1,376 @@
+//! `yal.task`: background work for Lua plugins.
+//!
+//! Tasks belong to the plugin instance that spawned them and are cancelled
+//! when it is unloaded. Plugins stay loaded across palette openings and are
+//! only unloaded when their config changes, on an explicit reload and on
+//! exit (see `PluginManager::load_plugins`). Tasks run on the host's
+//! runtime next to the plugin's commands and hooks, and only give way to
+//! them while they wait, for example in `task.sleep` or a `yal.http` request.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::Local;
+use croner::Cron;
+use mlua::{
+    Function, Lua, MultiValue, Result as LuaResult, Table, UserData, UserDataFields,
+    UserDataMethods,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    task::JoinHandle,
+    time::{Instant, MissedTickBehavior},
+};
+
+/// Failed tasks kept for health reports, oldest dropped first.
+const FAILED_KEPT: usize = 16;
+
+/// Longest `every` sleeps before checking the clock again, so runs are not
+/// missed when the clock jumps or the machine wakes up.
+const MAX_NAP: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TaskStatus {
+    pub id: u64,
+    /// What the task runs and where its function is defined, e.g.
+    /// `interval 30s (init.lua:12)`
+    pub name: String,
+    pub running: bool,
+    /// Why the task stopped, or why the last run of a repeating task failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Entry {
+    status: TaskStatus,
+    handle: JoinHandle<()>,
+}
+
+/// A plugin's tasks. Clones share them.
+#[derive(Clone)]
+pub struct Tasks {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    plugin: String,
+    next_id: AtomicU64,
+    /// Running tasks and the last failed ones, by id
+    entries: Mutex<BTreeMap<u64, Entry>>,
+}
+
+impl Tasks {
+    pub fn new(plugin: &str) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                plugin: plugin.to_string(),
+                next_id: AtomicU64::new(0),
+                entries: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    /// Running and failed tasks, in the order they were spawned. Tasks that
+    /// finished or were cancelled are not listed.
+    pub fn status(&self) -> Vec<TaskStatus> {
+        self.inner
+            .entries
+            .lock()
+            .values()
+            .map(|entry| entry.status.clone())
+            .collect()
+    }
+
+    /// Cancels every task.
+    pub fn cancel_all(&self) {
+        let entries = std::mem::take(&mut *self.inner.entries.lock());
+        for entry in entries.into_values() {
+            entry.handle.abort();
+        }
+    }
+
+    /// Cancels task `id`. Returns whether it was still running.
+    fn cancel(&self, id: u64) -> bool {
+        let mut entries = self.inner.entries.lock();
+        if !entries.get(&id).is_some_and(|entry| entry.status.running) {
+            return false;
+        }
+        if let Some(entry) = entries.remove(&id) {
+            entry.handle.abort();
+        }
+        true
+    }
+
+    fn state(&self, id: u64, cancelled: bool) -> (&'static str, Option<String>) {
+        match self.inner.entries.lock().get(&id) {
+            Some(entry) if entry.status.running => ("running", entry.status.error.clone()),
+            Some(entry) => ("failed", entry.status.error.clone()),
+            None if cancelled => ("cancelled", None),
+            None => ("done", None),
+        }
+    }
+
+    /// Spawns the future `run` returns for the new task's id.
+    fn spawn<F>(&self, name: String, run: impl FnOnce(u64) -> F) -> LuaResult<u64>
+    where
+        F: Future<Output = LuaResult<()>> + Send + 'static,
+    {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|_| mlua::Error::runtime("yal.task needs the host's runtime"))?;
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let future = run(id);
+        let tasks = self.clone();
+        // Held while spawning so the task can't finish before it is listed.
+        let mut entries = self.inner.entries.lock();
+        let handle = runtime.spawn(async move {
+            let result = future.await;
+            tasks.finish(id, result);
+        });
+        entries.insert(
+            id,
+            Entry {
+                status: TaskStatus {
+                    id,
+                    name,
+                    running: true,
+                    error: None,
+                },
+                handle,
+            },
+        );
+        Ok(id)
+    }
+
+    fn finish(&self, id: u64, result: LuaResult<()>) {
+        let mut entries = self.inner.entries.lock();
+        let Err(e) = result else {
+            entries.remove(&id);
+            return;
+        };
+        if let Some(entry) = entries.get_mut(&id) {
+            log::warn!(
+                "[{}] task '{}' failed: {}",
+                self.inner.plugin,
+                entry.status.name,
+                e
+            );
+            entry.status.running = false;
+            entry.status.error = Some(message(&e));
+        }
+        let failed: Vec<u64> = entries
+            .iter()
+            .filter(|(_, entry)| !entry.status.running)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in failed.iter().take(failed.len().saturating_sub(FAILED_KEPT)) {
+            entries.remove(id);
+        }
+    }
+
+    /// Records a failed run of a repeating task, which keeps running.
+    fn run_failed(&self, id: u64, e: mlua::Error) {
+        if let Some(entry) = self.inner.entries.lock().get_mut(&id) {
+            log::warn!(
+                "[{}] run of task '{}' failed: {}",
+                self.inner.plugin,
+                entry.status.name,
+                e
+            );
+            entry.status.error = Some(message(&e));
+        }
+    }
+}
+
+/// Handle `spawn`, `interval` and `every` return to Lua.
+struct Task {
+    id: u64,
+    tasks: Tasks,
+    cancelled: AtomicBool,
+}
+
+impl UserData for Task {
+    fn add_fields<F: UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("id", |_, this| Ok(this.id));
+    }
+
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        // cancel() -> boolean, whether it was still running
+        methods.add_method("cancel", |_, this, ()| {
+            let stopped = this.tasks.cancel(this.id);
+            if stopped {
+                this.cancelled.store(true, Ordering::Relaxed);
+            }
+            Ok(stopped)
+        });
+
+        // status() -> "running"|"failed"|"cancelled"|"done", error|nil
+        methods.add_method("status", |_, this, ()| {
+            Ok(this
+                .tasks
+                .state(this.id, this.cancelled.load(Ordering::Relaxed)))
+        });
+    }
+}
+
+/// What went wrong, without the Lua traceback the log already has.
+fn message(e: &mlua::Error) -> String {
+    match e {
+        mlua::Error::RuntimeError(msg) => msg.lines().next().unwrap_or_default().to_string(),
+        mlua::Error::CallbackError { cause, .. } => message(cause),
+        e => e.to_string(),
+    }
+}
+
+fn seconds(value: f64) -> LuaResult<Duration> {
+    Duration::try_from_secs_f64(value)
+        .map_err(|_| mlua::Error::runtime(format!("invalid duration: {} seconds", value)))
+}
+
+/// `kind`, followed by where `function` is defined when Lua knows it.
+fn describe(kind: &str, function: &Function) -> String {
+    let info = function.info();
+    match (info.short_src, info.line_defined) {
+        (Some(src), Some(line)) => format!("{} ({}:{})", kind, src, line),
+        _ => kind.to_string(),
+    }
+}
+
+fn handle(lua: &Lua, tasks: &Tasks, id: u64) -> LuaResult<mlua::AnyUserData> {
+    lua.create_userdata(Task {
+        id,
+        tasks: tasks.clone(),
+        cancelled: AtomicBool::new(false),
+    })
+}
+
+pub fn install_task_preload(lua: &Lua, tasks: Tasks) -> LuaResult<()> {
+    let pkg: Table = lua.globals().get("package")?;
+    let preload: Table = pkg.get("preload")?;
+
+    let loader = lua.create_function(move |lua, ()| {
+        let m = lua.create_table()?;
+
+        // spawn(fn, ...) -> task
+        let spawn_tasks = tasks.clone();
+        let spawn_fn =
+            lua.create_function(move |lua, (function, args): (Function, MultiValue)| {
+                let name = describe("spawn", &function);
+                let id =
+                    spawn_tasks.spawn(name, |_| async move { function.call_async(args).await })?;
+                handle(lua, &spawn_tasks, id)
+            })?;
+        m.set("spawn", spawn_fn)?;
+
+        // sleep(seconds)
+        let sleep_fn = lua.create_async_function(|_, value: f64| async move {
+            tokio::time::sleep(seconds(value)?).await;
+            Ok(())
+        })?;
+        m.set("sleep", sleep_fn)?;
+
+        // interval(seconds, fn) -> task; first run after `seconds`
+        let interval_tasks = tasks.clone();
+        let interval_fn = lua.create_function(move |lua, (value, function): (f64, Function)| {
+            let period = seconds(value)?;
+            if period.is_zero() {
+                return Err(mlua::Error::runtime(
+                    "interval must be longer than 0 seconds",
+                ));
+            }
+            let name = describe(&format!("interval {:?}", period), &function);
+            let tasks = interval_tasks.clone();
+            let id = interval_tasks.spawn(name, move |id| async move {
+                let mut ticks = tokio::time::interval_at(Instant::now() + period, period);
+                ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    ticks.tick().await;
+                    if let Err(e) = function.call_async::<()>(()).await {
+                        tasks.run_failed(id, e);
+                    }
+                }
+            })?;
+            handle(lua, &interval_tasks, id)
+        })?;
+        m.set("interval", interval_fn)?;
+
+        // every(cron_expr, fn) -> task; at each match in local time
+        let every_tasks = tasks.clone();
+        let every_fn = lua.create_function(move |lua, (expr, function): (String, Function)| {
+            let cron = Cron::new(&expr)
+                .with_seconds_optional()
+                .parse()
+                .map_err(|e| {
+                    mlua::Error::runtime(format!("invalid cron expression '{}': {}", expr, e))
+                })?;
+            let name = describe(&format!("every '{}'", expr), &function);
+            let tasks = every_tasks.clone();
+            let id = every_tasks.spawn(name, move |id| async move {
+                loop {
+                    let next = cron
+                        .find_next_occurrence(&Local::now(), false)
+                        .map_err(mlua::Error::external)?;
+                    while let Ok(wait) = (next - Local::now()).to_std() {
+                        if wait.is_zero() {
+                            break;
+                        }
+                        tokio::time::sleep(wait.min(MAX_NAP)).await;
+                    }
+                    if let Err(e) = function.call_async::<()>(()).await {
+                        tasks.run_failed(id, e);
+                    }
+                }
+            })?;
+            handle(lua, &every_tasks, id)
+        })?;
+        m.set("every", every_fn)?;
+
+        Ok(m)
+    })?;
+
+    preload.set("yal.task", loader)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lists_running_and_failed_tasks() {
+        let lua = Lua::new();
+        let tasks = Tasks::new("test");
+        install_task_preload(&lua, tasks.clone()).unwrap();
+        lua.load(
+            r#"
+            local task = require("yal.task")
+            task.spawn(function() error("boom", 0) end)
+            task.interval(3600, function() end)
+            task.spawn(function() end)
+            task.spawn(function() task.sleep(3600) end):cancel()
+            "#,
+        )
+        .exec()
+        .unwrap();
+        tokio::task::yield_now().await;
+
+        let status: Vec<(u64, bool, Option<String>)> = tasks
+            .status()
+            .into_iter()
+            .map(|task| (task.id, task.running, task.error))
+            .collect();
+        assert_eq!(
+            status,
+            vec![(1, false, Some("boom".to_string())), (2, true, None)]
+        );
+        tasks.cancel_all();
+        assert!(tasks.status().is_empty());
+    }
+}
@@ -1,18 +1,21 @@
+use std::path::Path;
+
 use mlua::{Lua, Result as LuaResult};
 
-/// Add a package.searcher that loads pure-Lua files from `vendor_dir`.
-/// Supports `require("a.b.c")` -> vendor/a/b/c.lua or vendor/a/b/c/init.lua
-pub fn add_vendor_searcher(lua: &Lua, vendor_dir: &std::path::Path) -> LuaResult<()> {
-    let vendor = vendor_dir.to_string_lossy().replace('\\', "\\\\");
-    let script = format!(
-        r#"
-local vendor = "{vendor}"
+/// Add a package.searcher that loads pure-Lua files from `roots`, in order:
+/// the plugin's `vendor` dir, then its cached packages.
+/// Supports `require("a.b.c")` -> <root>/a/b/c.lua or <root>/a/b/c/init.lua
+pub fn add_vendor_searcher(lua: &Lua, roots: &[&Path]) -> LuaResult<()> {
+    let roots = lua.create_sequence_from(roots.iter().map(|r| r.to_string_lossy().into_owned()))?;
+    let script = r#"
+local roots = ...
 table.insert(package.searchers, 2, function(modname)
   local rel = (modname:gsub('%.','/'))
-  local tried = {{
-    vendor .. "/" .. rel .. ".lua",
-    vendor .. "/" .. rel .. "/init.lua",
-  }}
+  local tried = {}
+  for _, root in ipairs(roots) do
+    table.insert(tried, root .. "/" .. rel .. ".lua")
+    table.insert(tried, root .. "/" .. rel .. "/init.lua")
+  end
   for _, p in ipairs(tried) do
     local f = io.open(p, "r")
     if f then
@@ -24,7 +27,6 @@ table.insert(package.searchers, 2, function(modname)
   end
   return "\nno module '"..modname.."' in vendor ("..table.concat(tried, ", ")..")"
 end)
-"#
-    );
-    lua.load(&script).exec()
+"#;
+    lua.load(script).call(roots)
 }
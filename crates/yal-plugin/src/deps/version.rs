@@ -0,0 +1,27 @@
+use mlua::{Lua, Result as LuaResult, Table};
+
+use crate::api::{self, API_VERSION};
+
+pub fn install_version_preload(lua: &Lua) -> LuaResult<()> {
+    let pkg: Table = lua.globals().get("package")?;
+    let preload: Table = pkg.get("preload")?;
+
+    let loader = lua.create_function(|lua, ()| {
+        let m = lua.create_table()?;
+        m.set("api", API_VERSION.to_string())?;
+        m.set("major", API_VERSION.major)?;
+        m.set("minor", API_VERSION.minor)?;
+        m.set("patch", API_VERSION.patch)?;
+
+        // satisfies("1.2") -> bool
+        let satisfies = lua.create_function(|_, range: String| {
+            let requirement = api::requirement(&range).map_err(mlua::Error::external)?;
+            Ok(api::is_compatible(&requirement))
+        })?;
+        m.set("satisfies", satisfies)?;
+        Ok(m)
+    })?;
+
+    preload.set("yal.version", loader)?;
+    Ok(())
+}
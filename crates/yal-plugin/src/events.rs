@@ -0,0 +1,55 @@
+//! System events the host publishes and plugins subscribe to with
+//! `yal.events`.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "topic", rename_all = "snake_case")]
+pub enum SystemEvent {
+    AppLaunched { app_name: String, pid: i32 },
+    AppTerminated { app_name: String, pid: i32 },
+    SpaceChanged,
+    DisplayChanged,
+    ConfigReloaded,
+    ThemeApplied { theme: String },
+}
+
+impl SystemEvent {
+    pub const TOPICS: [&'static str; 6] = [
+        "app_launched",
+        "app_terminated",
+        "space_changed",
+        "display_changed",
+        "config_reloaded",
+        "theme_applied",
+    ];
+
+    pub fn topic(&self) -> &'static str {
+        match self {
+            SystemEvent::AppLaunched { .. } => "app_launched",
+            SystemEvent::AppTerminated { .. } => "app_terminated",
+            SystemEvent::SpaceChanged => "space_changed",
+            SystemEvent::DisplayChanged => "display_changed",
+            SystemEvent::ConfigReloaded => "config_reloaded",
+            SystemEvent::ThemeApplied { .. } => "theme_applied",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_carries_the_topic() {
+        let event = SystemEvent::AppLaunched {
+            app_name: "Safari".to_string(),
+            pid: 42,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "topic": "app_launched", "app_name": "Safari", "pid": 42 })
+        );
+        assert!(SystemEvent::TOPICS.contains(&event.topic()));
+    }
+}
@@ -0,0 +1,102 @@
+//! Optional plugin functions the host calls outside of commands, when the
+//! palette is shown or hidden, the query changes or the plugin is unloaded.
+
+use std::{sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::plugin::Plugin;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    /// The palette was revealed; gets the execution context
+    OnShow,
+    /// The palette was hidden
+    OnHide,
+    /// The search query changed; gets the query
+    OnQueryChange,
+    /// The plugin is about to be unloaded
+    OnShutdown,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 4] = [
+        Hook::OnShow,
+        Hook::OnHide,
+        Hook::OnQueryChange,
+        Hook::OnShutdown,
+    ];
+
+    /// Name of the module function (or JSON-RPC method) implementing it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::OnShow => "on_show",
+            Hook::OnHide => "on_hide",
+            Hook::OnQueryChange => "on_query_change",
+            Hook::OnShutdown => "on_shutdown",
+        }
+    }
+
+    /// How long the host waits for the hook before abandoning it. Hooks run
+    /// after the palette has been shown or hidden, so this bounds how long
+    /// they hold up the plugin's next command rather than the palette.
+    pub fn budget(self) -> Duration {
+        match self {
+            Hook::OnShow | Hook::OnHide => Duration::from_millis(250),
+            Hook::OnQueryChange => Duration::from_millis(50),
+            Hook::OnShutdown => Duration::from_secs(1),
+        }
+    }
+}
+
+/// Runs `hook` with `arg` on every plugin in `plugins` that implements it,
+/// all at once. Each gets the hook's budget, so this returns within it.
+pub async fn run_all(plugins: &[Arc<Plugin>], hook: Hook, arg: Option<&serde_json::Value>) {
+    let runs = plugins.iter().map(|p| p.run_hook(hook, arg));
+    futures::future::join_all(runs).await;
+}
+
+/// Delivers `on_query_change` in the background, one query at a time. A
+/// query that comes in while the hooks still run for an earlier one replaces
+/// whatever query was waiting, so fast typing only reaches plugins with the
+/// latest query instead of queueing a call per keystroke.
+#[derive(Clone, Default)]
+pub struct QueryChanges {
+    state: Arc<Mutex<QueryState>>,
+}
+
+#[derive(Default)]
+struct QueryState {
+    next: Option<(Vec<Arc<Plugin>>, Option<serde_json::Value>)>,
+    delivering: bool,
+}
+
+impl QueryChanges {
+    pub fn push(&self, plugins: Vec<Arc<Plugin>>, query: Option<serde_json::Value>) {
+        {
+            let mut state = self.state.lock();
+            state.next = Some((plugins, query));
+            if state.delivering {
+                return;
+            }
+            state.delivering = true;
+        }
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            loop {
+                let next = {
+                    let mut state = state.lock();
+                    let next = state.next.take();
+                    state.delivering = next.is_some();
+                    next
+                };
+                let Some((plugins, query)) = next else {
+                    return;
+                };
+                run_all(&plugins, Hook::OnQueryChange, query.as_ref()).await;
+            }
+        });
+    }
+}
@@ -1,7 +1,13 @@
+pub mod api;
 pub mod backend;
 pub mod deps;
+pub mod events;
+pub mod hooks;
 pub mod manager;
 pub mod plugin;
+pub mod process;
 pub mod protocol;
+pub mod schema;
+pub mod wasm;
 
 pub use manager::PluginManager;
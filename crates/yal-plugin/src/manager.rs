@@ -1,17 +1,45 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use git2::Repository;
+use semver::{Version, VersionReq};
 use tokio::fs;
 
 use crate::{
+    api::{self, API_VERSION, IncompatibleApi},
     backend,
-    manager::config::PluginConfig,
-    plugin::{Plugin, PluginManifest},
+    deps::plugins::Dependencies,
+    events::SystemEvent,
+    hooks::{self, Hook, QueryChanges},
+    manager::{
+        auth::Authenticator,
+        config::{PluginConfig, PluginConfigEntry, PluginKind, check_dir_name},
+        health::{PluginHealth, PluginStatus, UpdateOutcome},
+        integrity::Untrusted,
+        manifest::{
+            Dependency, Manifest, Resolution, ResolvedPlugin, Unresolved, resolve, resolve_each,
+        },
+        prune::{Locations, Orphan},
+    },
+    plugin::{LuaPlugin, Plugin, PluginManifest, PluginRef, PluginRuntime},
+    process::ProcessPlugin,
     protocol::{PluginExecuteContext, PluginExecuteResponse},
+    schema::{ConfigSchema, InvalidConfig},
+    wasm::{self, WasmPlugin},
 };
 
-mod config;
+pub mod auth;
+pub mod config;
+pub mod health;
+pub mod integrity;
+pub mod manifest;
+pub mod packages;
+pub mod prune;
+pub mod settings;
 
 pub fn plugins_config_path() -> PathBuf {
     let mut path = dirs::home_dir().expect("Failed to get home directory");
@@ -25,96 +53,457 @@ pub fn plugins_dir() -> PathBuf {
     dir
 }
 
+/// Checkout directory of an installed plugin.
+pub fn plugin_dir(name: &str) -> PathBuf {
+    plugins_dir().join(name)
+}
+
+/// Full clone URL for a `git` entry; `owner/repo` is shorthand for GitHub.
+pub fn git_url(source: &str) -> String {
+    if source.starts_with("http://")
+        || source.starts_with("https://")
+        || source.starts_with("git@")
+        || source.starts_with("ssh://")
+        || source.starts_with("file://")
+    {
+        source.to_string()
+    } else {
+        format!("https://github.com/{}.git", source)
+    }
+}
+
 pub struct PluginManager<T: backend::Backend> {
     pub config: PluginConfig,
-    pub plugins: Vec<Plugin>,
+    /// Where plugins are checked out, `plugins_dir()` unless overridden.
+    pub plugins_dir: PathBuf,
+    pub plugins: Vec<Arc<Plugin>>,
+    /// One entry per configured plugin, refreshed by `load_plugins`.
+    pub health: Vec<PluginHealth>,
+    /// Config schemas of the plugins that have one, by plugin name.
+    pub schemas: HashMap<String, ConfigSchema>,
+    /// What each plugin in `health` was loaded from, by plugin name, so
+    /// `load_plugins` only reloads the ones that changed.
+    fingerprints: HashMap<String, String>,
+    /// Plugins switched on or off at runtime, by plugin name. These win over
+    /// `enabled` in `plugins.lua`.
+    pub overrides: HashMap<String, bool>,
     pub execution_context: Option<PluginExecuteContext>,
     pub backend: Arc<T>,
+    query_changes: QueryChanges,
 }
 
 impl<T: backend::Backend> PluginManager<T> {
     pub fn new(backend: T) -> Self {
         Self {
             config: PluginConfig::default(),
+            plugins_dir: plugins_dir(),
             plugins: Vec::new(),
+            health: Vec::new(),
+            schemas: HashMap::new(),
+            fingerprints: HashMap::new(),
+            overrides: HashMap::new(),
             execution_context: None,
             backend: Arc::new(backend),
+            query_changes: QueryChanges::default(),
         }
     }
 
+    /// Checkout directory of the plugin installed from `source`.
+    pub fn plugin_dir(&self, source: &str) -> PathBuf {
+        self.plugins_dir.join(source)
+    }
+
     pub async fn init(&self) -> Result<()> {
-        let dir = plugins_dir();
+        let dir = &self.plugins_dir;
         if !dir.exists() {
-            fs::create_dir_all(&dir)
+            fs::create_dir_all(dir)
                 .await
                 .with_context(|| format!("Failed creating plugins directory {}", dir.display()))?;
         }
         Ok(())
     }
 
+    /// Reads `plugins.lua`. Names and sources become directories under
+    /// YAL's data directory, so a config with one that is not a plain
+    /// directory name is rejected and the current config kept.
     pub async fn load_config(&mut self) -> Result<()> {
         let path = plugins_config_path();
         let config = yal_config::load_config::<PluginConfig>(&path);
+        for entry in &config {
+            check_dir_name("plugin name", &entry.name)
+                .and_then(|_| check_dir_name("plugin source", entry.source()))
+                .with_context(|| format!("Invalid entry in {}", path.display()))?;
+        }
         self.config = config;
         Ok(())
     }
 
-    pub async fn install(&mut self) -> Result<()> {
+    /// Clones every configured plugin that is not installed yet, along with
+    /// the plugins they depend on, and fetches the Lua packages each one
+    /// lists. Fresh clones get an integrity record. Returns the sources
+    /// cloned.
+    pub async fn install(&mut self) -> Result<Vec<String>> {
         self.load_config().await?;
-        for plugin in &self.config {
-            log::info!("Installing plugin: {}", plugin.name);
+        let mut installed = Vec::new();
+        let mut synced = Vec::new();
+        resolve(&self.config, |plugin| {
+            let source = plugin.source();
+            let plugin_dir = self.plugin_dir(source);
+            // Instances sharing a checkout only need it set up once.
+            if synced.iter().any(|s| s == source) {
+                return Manifest::load(&plugin_dir);
+            }
+            log::info!("Installing plugin: {}", source);
             log::info!("  from: {}", plugin.git);
-            let plugin_dir = plugins_dir().join(&plugin.name);
-            if plugin_dir.exists() {
+            let cloned = !plugin_dir.exists();
+            if !cloned {
                 log::info!("  already installed, skipping");
-                continue;
+            } else {
+                let repo = Authenticator::new(plugin.auth.as_ref())?
+                    .clone_repo(&git_url(&plugin.git), &plugin_dir)
+                    .with_context(|| format!("Failed cloning {}", plugin.git))?;
+                log::info!("  cloned to: {}", repo.path().parent().unwrap().display());
+                installed.push(source.to_string());
+            }
+            let manifest = Manifest::load(&plugin_dir)?;
+            packages::sync(source, &plugin_dir, &manifest, plugin.auth.as_ref())?;
+            if cloned {
+                integrity::record(&plugin_dir)?;
             }
+            synced.push(source.to_string());
+            Ok(manifest)
+        })?;
+        Ok(installed)
+    }
 
-            let giturl = if plugin.git.starts_with("http://")
-                || plugin.git.starts_with("https://")
-                || plugin.git.starts_with("git@")
-            {
-                plugin.git.clone()
-            } else {
-                format!("https://github.com/{}.git", plugin.git)
-            };
+    /// Fast-forwards an installed plugin to the tip of its upstream branch
+    /// and brings its Lua packages in line with the new manifest. `name` is
+    /// the plugin's source, which every instance of it shares. A checkout
+    /// modified since it was recorded is left alone.
+    pub async fn update(&self, name: &str) -> Result<UpdateOutcome> {
+        let dir = self.installed_dir(name)?;
+        let auth = self
+            .config
+            .iter()
+            .find(|e| e.source() == name)
+            .and_then(|e| e.auth.as_ref());
+        integrity::verify(&dir, &[]).with_context(|| {
+            format!(
+                "Not updating '{}'; run `yal-cli plugins trust {}` to keep the changes",
+                name, name
+            )
+        })?;
+        let outcome = fast_forward(&dir, &Authenticator::new(auth)?)
+            .with_context(|| format!("Failed updating plugin '{}'", name))?;
+        packages::sync(name, &dir, &Manifest::load(&dir)?, auth)
+            .with_context(|| format!("Failed updating the packages of '{}'", name))?;
+        integrity::record(&dir)?;
+        Ok(outcome)
+    }
 
-            let repo = Repository::clone(&giturl, &plugin_dir)
-                .with_context(|| format!("Failed cloning {}", plugin.git))?;
-            log::info!("  cloned to: {}", repo.path().parent().unwrap().display());
+    /// Deletes a plugin checkout and its Lua packages and unloads every
+    /// instance running from it. The entries in `plugins.lua` are left alone,
+    /// so the plugin is reinstalled unless they are removed there too.
+    pub async fn remove(&mut self, name: &str) -> Result<()> {
+        let dir = self.installed_dir(name)?;
+        let mut instances: Vec<String> = self
+            .config
+            .iter()
+            .filter(|e| e.source() == name)
+            .map(|e| e.name.clone())
+            .collect();
+        if instances.is_empty() {
+            instances.push(name.to_string());
         }
+        for plugin in self.plugins.iter().filter(|p| instances.contains(&p.name)) {
+            plugin.unload().await;
+        }
+        fs::remove_dir_all(&dir)
+            .await
+            .with_context(|| format!("Failed removing {}", dir.display()))?;
+        let packages = packages::packages_dir(name);
+        if packages.exists() {
+            fs::remove_dir_all(&packages)
+                .await
+                .with_context(|| format!("Failed removing {}", packages.display()))?;
+        }
+        self.plugins.retain(|p| !instances.contains(&p.name));
+        self.health.retain(|h| !instances.contains(&h.name));
+        self.fingerprints
+            .retain(|name, _| !instances.contains(name));
+        Ok(())
+    }
+
+    /// Records an installed plugin's checkout as it is now, accepting local
+    /// changes made since it was installed.
+    pub fn trust(&self, name: &str) -> Result<()> {
+        let dir = self.installed_dir(name)?;
+        integrity::record(&dir).with_context(|| format!("Failed recording plugin '{}'", name))?;
         Ok(())
     }
 
-    pub async fn load_plugins(&mut self) -> Result<()> {
-        self.plugins.clear();
-        for plugin in &self.config {
-            let plugin_dir = plugins_dir().join(&plugin.name);
-            if !plugin_dir.exists() {
-                log::warn!("Plugin '{}' is not installed, skipping", plugin.name);
+    /// Checkout of the installed plugin `name`, a source given on the
+    /// command line.
+    fn installed_dir(&self, name: &str) -> Result<PathBuf> {
+        check_dir_name("plugin name", name)?;
+        let dir = self.plugin_dir(name);
+        if !dir.exists() {
+            bail!("Plugin '{}' is not installed", name);
+        }
+        Ok(dir)
+    }
+
+    /// Leftovers of plugins that are neither configured nor a dependency of
+    /// a configured plugin, with their disk usage.
+    pub fn orphans(&self) -> Result<Vec<Orphan>> {
+        let resolved = resolve(&self.config, |entry| {
+            Manifest::load(&self.plugin_dir(entry.source()))
+        })?;
+        let sources = resolved
+            .iter()
+            .map(|p| p.entry.source().to_string())
+            .collect();
+        let names = resolved.iter().map(|p| p.entry.name.clone()).collect();
+        let locations = Locations {
+            plugins: self.plugins_dir.clone(),
+            ..Locations::default()
+        };
+        prune::find(&locations, &sources, &names)
+    }
+
+    /// Deletes what `orphans` left behind. Returns the bytes freed.
+    pub fn prune(&self, orphans: &[Orphan]) -> Result<u64> {
+        let mut freed = 0;
+        for orphan in orphans {
+            prune::remove(orphan)
+                .with_context(|| format!("Failed pruning plugin '{}'", orphan.name))?;
+            log::info!("Pruned plugin '{}'", orphan.name);
+            freed += orphan.bytes;
+        }
+        Ok(freed)
+    }
+
+    /// Brings the loaded plugins in line with the config, dependencies first.
+    /// Plugins whose entry, settings, manifest or dependencies are unchanged
+    /// since the last call stay loaded as they are; the others are unloaded
+    /// and loaded again, and plugins no longer configured are unloaded. A
+    /// plugin that fails to load is skipped and its error recorded in
+    /// `health`, so one broken plugin does not take the others down (apart
+    /// from plugins depending on it). The same goes for a broken manifest, an
+    /// invalid dependency or a dependency cycle.
+    pub async fn load_plugins(&mut self) {
+        let Resolution {
+            plugins: resolved,
+            unresolved,
+        } = resolve_each(&self.config, |entry| {
+            Manifest::load(&self.plugin_dir(entry.source()))
+        });
+        let mut fingerprints = HashMap::new();
+        for resolved_plugin in &resolved {
+            let fingerprint = self.fingerprint(resolved_plugin, &fingerprints);
+            fingerprints.insert(resolved_plugin.entry.name.clone(), fingerprint);
+        }
+        self.fingerprints
+            .retain(|name, fingerprint| fingerprints.get(name) == Some(fingerprint));
+        let (kept, stale): (Vec<_>, Vec<_>) = std::mem::take(&mut self.plugins)
+            .into_iter()
+            .partition(|p| self.fingerprints.contains_key(&p.name));
+        // Dependents were loaded after their dependencies, so go backwards.
+        for plugin in stale.into_iter().rev() {
+            plugin.unload().await;
+        }
+        self.plugins = kept;
+        self.schemas
+            .retain(|name, _| self.fingerprints.contains_key(name));
+        let previous = std::mem::take(&mut self.health);
+
+        for resolved_plugin in &resolved {
+            let name = &resolved_plugin.entry.name;
+            if self.fingerprints.contains_key(name)
+                && let Some(health) = previous.iter().find(|h| &h.name == name)
+            {
+                self.health.push(health.clone());
                 continue;
             }
-            let plugin_ref = crate::plugin::PluginRef {
-                name: plugin.name.clone(),
-                path: plugin_dir.clone(),
-                config: plugin.config.clone(),
-            };
-            let lua_plugin = crate::plugin::LuaPlugin::new(plugin_ref, self.backend.clone())
-                .with_context(|| format!("Failed loading plugin '{}'", plugin.name))?;
-            let init_response = lua_plugin.initialize().await?;
-            let plugin = Plugin {
-                name: plugin.name.clone(),
-                commands: init_response.commands,
-                lua: lua_plugin,
+            let status = if !self.is_enabled(&resolved_plugin.entry) {
+                log::info!("Plugin '{}' is disabled, skipping", name);
+                PluginStatus::Disabled
+            } else if !self.plugin_dir(resolved_plugin.entry.source()).exists() {
+                log::warn!("Plugin '{}' is not installed, skipping", name);
+                PluginStatus::NotInstalled
+            } else {
+                match self.load_resolved(resolved_plugin).await {
+                    Ok((plugin, version)) => {
+                        log::info!(
+                            "Plugin '{}' initialized with {} commands",
+                            plugin.name,
+                            plugin.commands.len()
+                        );
+                        let status = PluginStatus::Loaded {
+                            version,
+                            commands: plugin.commands.len(),
+                        };
+                        self.plugins.push(Arc::new(plugin));
+                        status
+                    }
+                    Err(e) => {
+                        log::error!("Failed loading plugin '{}': {:#}", name, e);
+                        if let Some(incompatible) = e.downcast_ref::<IncompatibleApi>() {
+                            PluginStatus::Incompatible {
+                                required: incompatible.required.clone(),
+                                host: API_VERSION.to_string(),
+                            }
+                        } else if let Some(invalid) = e.downcast_ref::<InvalidConfig>() {
+                            PluginStatus::InvalidConfig {
+                                errors: invalid.errors.iter().map(|e| e.to_string()).collect(),
+                            }
+                        } else if let Some(untrusted) = e.downcast_ref::<Untrusted>() {
+                            PluginStatus::Untrusted {
+                                reason: untrusted.reason.clone(),
+                            }
+                        } else {
+                            PluginStatus::Failed {
+                                error: format!("{:#}", e),
+                            }
+                        }
+                    }
+                }
             };
-            log::info!(
-                "Plugin '{}' initialized with {} commands",
-                plugin.name,
-                plugin.commands.len()
-            );
-            self.plugins.push(plugin);
+            self.health.push(PluginHealth {
+                name: name.clone(),
+                status,
+                tasks: Vec::new(),
+            });
+            self.fingerprints
+                .insert(name.clone(), fingerprints[name].clone());
         }
-        Ok(())
+        for Unresolved { entry, error } in unresolved {
+            log::error!("Failed resolving plugin '{}': {:#}", entry.name, error);
+            self.health.push(PluginHealth {
+                name: entry.name,
+                status: PluginStatus::Failed {
+                    error: format!("{:#}", error),
+                },
+                tasks: Vec::new(),
+            });
+        }
+    }
+
+    /// Everything loading `plugin` depends on, apart from the files in its
+    /// checkout, which only change through `install`, `update` or an explicit
+    /// reload. `loaded` holds the fingerprints of the plugins resolved before
+    /// it, its dependencies among them.
+    fn fingerprint(&self, plugin: &ResolvedPlugin, loaded: &HashMap<String, String>) -> String {
+        let ResolvedPlugin { entry, manifest } = plugin;
+        let dependencies: Vec<_> = manifest
+            .dependencies
+            .iter()
+            .map(|d| loaded.get(&d.name))
+            .collect();
+        serde_json::json!({
+            "entry": entry,
+            "manifest": manifest,
+            "settings": settings::load(&entry.name).map_err(|e| e.to_string()),
+            "enabled": self.is_enabled(entry),
+            "installed": self.plugin_dir(entry.source()).exists(),
+            "dependencies": dependencies,
+        })
+        .to_string()
+    }
+
+    pub fn is_enabled(&self, entry: &PluginConfigEntry) -> bool {
+        self.overrides
+            .get(&entry.name)
+            .copied()
+            .or(entry.enabled)
+            .unwrap_or(true)
+    }
+
+    /// Loads an installed plugin with its config: `plugins.lua` overridden by
+    /// saved settings, checked against the plugin's schema. The checkout must
+    /// match its integrity record and be signed by one of the entry's
+    /// `signers`, if it has any. Every entry gets its own runtime, even when
+    /// it shares a checkout with another.
+    async fn load_resolved(
+        &mut self,
+        ResolvedPlugin { entry, manifest }: &ResolvedPlugin,
+    ) -> Result<(Plugin, String)> {
+        let plugin_dir = self.plugin_dir(entry.source());
+        integrity::verify(&plugin_dir, &entry.signers)?;
+        let dependencies = self.loaded_dependencies(&manifest.dependencies)?;
+        let mut config = settings::merge(entry.config.clone(), settings::load(&entry.name)?);
+        if let Some(schema) = &manifest.config_schema {
+            self.schemas.insert(entry.name.clone(), schema.clone());
+            let checked = schema
+                .apply(config)
+                .map_err(|errors| InvalidConfig { errors })?;
+            config = Some(checked).filter(|c| !c.is_null());
+        }
+        let plugin_ref = PluginRef {
+            name: entry.name.clone(),
+            path: plugin_dir.clone(),
+            config: config.clone(),
+            http: entry
+                .http
+                .as_ref()
+                .map(|h| h.transport(&plugin_dir))
+                .unwrap_or_default(),
+            dependencies,
+            package_roots: manifest.package_roots(entry.source()),
+        };
+        let (mut plugin, version) = load_plugin(entry, plugin_ref, self.backend.clone()).await?;
+        match (&manifest.config_schema, &plugin.config_schema) {
+            (Some(schema), _) => plugin.config_schema = Some(schema.clone()),
+            // Declared by `init`, so it can only be checked afterwards.
+            (None, Some(schema)) => {
+                self.schemas.insert(entry.name.clone(), schema.clone());
+                if let Err(errors) = schema.apply(config) {
+                    plugin.runtime.shutdown().await;
+                    return Err(InvalidConfig { errors }.into());
+                }
+            }
+            (None, None) => {}
+        }
+        Ok((plugin, version))
+    }
+
+    /// The Lua runtimes of `dependencies`, once each has loaded with a
+    /// version in the declared range.
+    fn loaded_dependencies(&self, dependencies: &[Dependency]) -> Result<Dependencies> {
+        let mut loaded = Dependencies::new();
+        for dependency in dependencies {
+            let version = self
+                .health
+                .iter()
+                .find_map(|h| match &h.status {
+                    PluginStatus::Loaded { version, .. } if h.name == dependency.name => {
+                        Some(version)
+                    }
+                    _ => None,
+                })
+                .with_context(|| format!("Dependency '{}' is not loaded", dependency.name))?;
+            if let Some(range) = &dependency.version {
+                let requirement = VersionReq::parse(range).with_context(|| {
+                    format!("Invalid version '{}' for '{}'", range, dependency.name)
+                })?;
+                let version = Version::parse(version).with_context(|| {
+                    format!("'{}' has an invalid version '{}'", dependency.name, version)
+                })?;
+                if !requirement.matches(&version) {
+                    bail!(
+                        "Needs '{}' {}, but {} is installed",
+                        dependency.name,
+                        range,
+                        version
+                    );
+                }
+            }
+            let plugin = self.plugins.iter().find(|p| p.name == dependency.name);
+            if let Some(PluginRuntime::Lua(lua)) = plugin.map(|p| &p.runtime) {
+                loaded.insert(dependency.name.clone(), lua.clone());
+            }
+        }
+        Ok(loaded)
     }
 
     pub async fn run_command(
@@ -148,7 +537,10 @@ impl<T: backend::Backend> PluginManager<T> {
                 command_name,
                 plugin_name,
             );
-            let resp = plugin.lua.run(command_name.to_string(), ctx, args).await?;
+            let resp = plugin
+                .runtime
+                .run(command_name.to_string(), ctx, args)
+                .await?;
 
             Ok(resp)
         } else {
@@ -163,18 +555,353 @@ impl<T: backend::Backend> PluginManager<T> {
         }
     }
 
+    /// Runs `on_shutdown` of every plugin, stops the out-of-process ones and
+    /// unloads all plugins, so the next `load_plugins` loads them afresh.
+    pub async fn shutdown(&mut self) {
+        for plugin in self.plugins.drain(..).rev() {
+            plugin.unload().await;
+        }
+        self.health.clear();
+        self.schemas.clear();
+        self.fingerprints.clear();
+    }
+
+    /// Runs `hook` with `arg` on every loaded plugin that implements it, in
+    /// the background, and returns right away so hooks never hold up commands
+    /// or loads. Query changes are coalesced (see `QueryChanges`).
+    pub fn dispatch_hook(&self, hook: Hook, arg: Option<serde_json::Value>) {
+        let plugins = self.plugins.clone();
+        if hook == Hook::OnQueryChange {
+            self.query_changes.push(plugins, arg);
+        } else {
+            tokio::spawn(async move { hooks::run_all(&plugins, hook, arg.as_ref()).await });
+        }
+    }
+
+    /// `health` with the current state of each Lua plugin's tasks.
+    pub fn health_report(&self) -> Vec<PluginHealth> {
+        let mut report = self.health.clone();
+        for health in &mut report {
+            let runtime = self.plugins.iter().find(|p| p.name == health.name);
+            if let Some(PluginRuntime::Lua(plugin)) = runtime.map(|p| &p.runtime) {
+                health.tasks = plugin.tasks().status();
+            }
+        }
+        report
+    }
+
+    /// Queues `event` for the Lua plugins subscribed to its topic.
+    pub fn publish(&self, event: &SystemEvent) {
+        for plugin in &self.plugins {
+            if let PluginRuntime::Lua(lua) = &plugin.runtime {
+                lua.events().publish(event);
+            }
+        }
+    }
+
     pub fn set_execution_context(&mut self, context: PluginExecuteContext) {
         log::info!("Setting execution context");
         self.execution_context = Some(context);
     }
 
+    /// One entry per configured plugin. Only loaded plugins have commands;
+    /// the others are listed so they can still be configured or toggled.
     pub async fn commands(&self) -> Vec<PluginManifest> {
-        self.plugins
+        self.health
             .iter()
-            .map(|p| PluginManifest {
-                plugin_name: p.name.clone(),
-                commands: p.commands.clone(),
+            .map(|health| PluginManifest {
+                plugin_name: health.name.clone(),
+                commands: self
+                    .plugins
+                    .iter()
+                    .find(|p| p.name == health.name)
+                    .map(|p| p.commands.clone())
+                    .unwrap_or_default(),
+                configurable: self.schemas.contains_key(&health.name),
+                enabled: health.status != PluginStatus::Disabled,
             })
             .collect()
     }
+
+    /// The config schema of `name` and its current config to fill the
+    /// settings form with: `plugins.lua` overridden by saved settings, with
+    /// the schema's defaults applied unless the config is invalid.
+    pub fn current_settings(&self, name: &str) -> Result<(ConfigSchema, serde_json::Value)> {
+        let schema = self
+            .schemas
+            .get(name)
+            .with_context(|| format!("Plugin '{}' has no config schema", name))?;
+        let entry = self
+            .config
+            .iter()
+            .find(|e| e.name == name)
+            .and_then(|e| e.config.clone());
+        let current = settings::merge(entry, settings::load(name)?);
+        // Show the defaults even when the current config is invalid.
+        let current = schema
+            .apply(current.clone())
+            .unwrap_or_else(|_| current.unwrap_or_default());
+        Ok((schema.clone(), current))
+    }
+}
+
+async fn load_plugin<T: backend::Backend>(
+    entry: &PluginConfigEntry,
+    plugin_ref: PluginRef,
+    backend: Arc<T>,
+) -> Result<(Plugin, String)> {
+    let name = plugin_ref.name.clone();
+    let kind = entry.kind.unwrap_or_else(|| {
+        if wasm::is_wasm_plugin(&plugin_ref.path) {
+            PluginKind::Wasm
+        } else {
+            PluginKind::Lua
+        }
+    });
+    let runtime = match kind {
+        PluginKind::Lua => PluginRuntime::Lua(Arc::new(LuaPlugin::new(plugin_ref, backend)?)),
+        PluginKind::Wasm => PluginRuntime::Wasm(
+            WasmPlugin::new(plugin_ref, entry.capabilities.clone(), backend).await?,
+        ),
+        PluginKind::Process => {
+            let exec = entry
+                .exec
+                .clone()
+                .context("process plugins need an `exec` entry")?;
+            PluginRuntime::Process(ProcessPlugin::new(
+                plugin_ref,
+                exec,
+                entry.args.clone(),
+                backend,
+            )?)
+        }
+    };
+    let init_response = match runtime.initialize().await.context("init() failed") {
+        Ok(response) => response,
+        Err(e) => {
+            runtime.shutdown().await;
+            return Err(e);
+        }
+    };
+    if let Err(e) = api::check(&name, init_response.api_version.as_deref()) {
+        runtime.shutdown().await;
+        return Err(e);
+    }
+    let plugin = Plugin {
+        name,
+        hooks: runtime.hooks(&init_response),
+        commands: init_response.commands,
+        config_schema: init_response.config_schema,
+        runtime,
+    };
+    Ok((plugin, init_response.version))
+}
+
+fn fast_forward(dir: &Path, auth: &Authenticator) -> Result<UpdateOutcome> {
+    let repo = Repository::open(dir)?;
+    let head = repo.head()?;
+    let branch = head
+        .shorthand()
+        .filter(|_| head.is_branch())
+        .context("HEAD is detached")?
+        .to_string();
+    let from = head.peel_to_commit()?.id();
+
+    auth.fetch(&repo, &[&branch])?;
+    let fetched = repo.reference_to_annotated_commit(&repo.find_reference("FETCH_HEAD")?)?;
+    let (analysis, _) = repo.merge_analysis(&[&fetched])?;
+    if analysis.is_up_to_date() {
+        return Ok(UpdateOutcome::UpToDate);
+    }
+    if !analysis.is_fast_forward() {
+        bail!("local checkout has diverged from origin/{}", branch);
+    }
+
+    let refname = format!("refs/heads/{}", branch);
+    repo.find_reference(&refname)?
+        .set_target(fetched.id(), "yal: fast-forward")?;
+    repo.set_head(&refname)?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+    Ok(UpdateOutcome::Updated {
+        from: short_id(from),
+        to: short_id(fetched.id()),
+    })
+}
+
+fn short_id(oid: git2::Oid) -> String {
+    oid.to_string()[..7].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yal_core::{Prompt, PromptResponse};
+
+    #[derive(Clone)]
+    struct NoBackend;
+
+    impl backend::Backend for NoBackend {
+        async fn prompt(&self, _prompt: Prompt) -> Result<String> {
+            bail!("no UI in tests")
+        }
+
+        async fn prompt_state(&self, _id: String) -> Result<PromptResponse> {
+            bail!("no UI in tests")
+        }
+
+        async fn prompt_submission(&self, _id: String) -> Result<PromptResponse> {
+            bail!("no UI in tests")
+        }
+
+        async fn prompt_cancel(&self, _id: String) -> Result<()> {
+            bail!("no UI in tests")
+        }
+
+        async fn set_visibility(&self, _visible: bool) -> Result<()> {
+            bail!("no UI in tests")
+        }
+    }
+
+    fn lua_runtime(manager: &PluginManager<NoBackend>) -> Arc<LuaPlugin> {
+        match &manager.plugins[0].runtime {
+            PluginRuntime::Lua(lua) => lua.clone(),
+            _ => panic!("not a Lua plugin"),
+        }
+    }
+
+    /// A manager configured with one Lua plugin, installed under `dir`,
+    /// whose `init.lua` runs `setup` while it loads, with the module table as
+    /// `M`.
+    fn manager_with(dir: &Path, setup: &str) -> PluginManager<NoBackend> {
+        let entry = PluginConfigEntry::new("yal-manager-test-ticker", "me/ticker");
+        let checkout = dir.join(entry.source());
+        std::fs::create_dir(&checkout).unwrap();
+        std::fs::write(
+            checkout.join("init.lua"),
+            format!(
+                r#"
+                local M = {{
+                  init = function()
+                    return {{ name = "ticker", version = "0.1.0", api_version = "{}", commands = {{}} }}
+                  end,
+                  execute = function() end,
+                }}
+                {}
+                return M
+                "#,
+                api::current_requirement(),
+                setup
+            ),
+        )
+        .unwrap();
+        let mut manager = PluginManager::new(NoBackend);
+        manager.plugins_dir = dir.to_path_buf();
+        manager.config = vec![entry];
+        manager
+    }
+
+    #[tokio::test]
+    async fn refuses_names_outside_the_plugins_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_with(dir.path(), "");
+        for name in ["../escape", "/tmp", "a/b"] {
+            let err = manager.trust(name).unwrap_err();
+            assert_eq!(err.to_string(), format!("Invalid plugin name '{}'", name));
+            assert!(manager.update(name).await.is_err());
+            assert!(manager.remove(name).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_tasks_running_across_unchanged_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_with(
+            dir.path(),
+            r#"require("yal.task").interval(3600, function() end)"#,
+        );
+
+        manager.load_plugins().await;
+        let first = lua_runtime(&manager);
+        manager.load_plugins().await;
+        manager.load_plugins().await;
+
+        assert!(Arc::ptr_eq(&first, &lua_runtime(&manager)));
+        let report = manager.health_report();
+        assert!(report[0].is_ok(), "{:?}", report[0]);
+        let running: Vec<bool> = report[0].tasks.iter().map(|t| t.running).collect();
+        assert_eq!(running, vec![true]);
+
+        manager.config[0].config = Some(serde_json::json!({ "changed": true }));
+        manager.load_plugins().await;
+        assert!(!Arc::ptr_eq(&first, &lua_runtime(&manager)));
+        assert!(first.tasks().status().is_empty());
+
+        manager.shutdown().await;
+        assert!(manager.health_report().is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeps_event_subscriptions_across_unchanged_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_with(
+            dir.path(),
+            r#"
+            reloads = 0
+            require("yal.events").subscribe("config_reloaded", function()
+              reloads = reloads + 1
+            end)
+            "#,
+        );
+
+        manager.load_plugins().await;
+        manager.publish(&SystemEvent::ConfigReloaded);
+        manager.load_plugins().await;
+        manager.publish(&SystemEvent::ConfigReloaded);
+
+        let lua = lua_runtime(&manager);
+        for _ in 0..100 {
+            if lua.lua().globals().get::<i64>("reloads").unwrap() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(lua.lua().globals().get::<i64>("reloads").unwrap(), 2);
+        manager.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn cuts_off_hooks_that_never_yield() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_with(dir.path(), "function M.on_hide() while true do end end");
+        manager.load_plugins().await;
+
+        let started = std::time::Instant::now();
+        manager.plugins[0].run_hook(Hook::OnHide, None).await;
+        assert!(started.elapsed() < Hook::OnHide.budget() * 4);
+        let lua = lua_runtime(&manager);
+        assert_eq!(lua.lua().load("return 1").eval::<i64>().unwrap(), 1);
+        manager.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn only_delivers_the_latest_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_with(
+            dir.path(),
+            r#"
+            queries = {}
+            function M.on_query_change(query) table.insert(queries, query) end
+            "#,
+        );
+        manager.load_plugins().await;
+
+        for query in ["s", "sp", "spo"] {
+            manager.dispatch_hook(Hook::OnQueryChange, Some(query.into()));
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let lua = lua_runtime(&manager);
+        let queries: Vec<String> = lua.lua().globals().get("queries").unwrap();
+        assert_eq!(queries, vec!["spo"]);
+        manager.shutdown().await;
+    }
 }
@@ -0,0 +1,298 @@
+//! Credentials for cloning and fetching private repositories.
+//!
+//! SSH URLs try the entry's `ssh_key` and then ssh-agent. HTTPS URLs try the
+//! token in the entry's `token_env` and then the token for the host in
+//! `~/.config/yal/credentials.lua`:
+//!
+//! ```lua
+//! return {
+//!   ["git.example.com"] = { token = "glpat-...", username = "oauth2" },
+//! }
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use git2::{Cred, CredentialType, ErrorCode, FetchOptions, RemoteCallbacks, Repository};
+use serde::{Deserialize, Serialize};
+
+/// `auth` in a `plugins.lua` entry.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GitAuth {
+    /// Private key for SSH URLs, tried before ssh-agent ("~" is expanded)
+    pub ssh_key: Option<PathBuf>,
+    /// Environment variable holding the passphrase of `ssh_key`
+    pub passphrase_env: Option<String>,
+    /// Environment variable holding a token for HTTPS URLs
+    pub token_env: Option<String>,
+    /// User name sent with a token (defaults to the one in the URL, or `git`)
+    pub username: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HostCredentials {
+    pub token: String,
+    pub username: Option<String>,
+}
+
+/// `credentials.lua`: tokens by host.
+pub type Credentials = HashMap<String, HostCredentials>;
+
+/// `~/.config/yal/credentials.lua`
+pub fn credentials_path() -> PathBuf {
+    yal_config::config_base_path().join("credentials.lua")
+}
+
+/// One way of authenticating, as reported when all of them failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    SshKey(PathBuf),
+    SshAgent,
+    TokenEnv(String),
+    CredentialsFile(String),
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::SshKey(path) => write!(f, "SSH key {}", path.display()),
+            Method::SshAgent => f.write_str("ssh-agent"),
+            Method::TokenEnv(var) => write!(f, "token from ${}", var),
+            Method::CredentialsFile(host) => write!(f, "token for {} in credentials.lua", host),
+        }
+    }
+}
+
+/// Answers libgit2's credential requests for one clone or fetch, trying
+/// every configured method once.
+pub struct Authenticator {
+    auth: GitAuth,
+    credentials: Credentials,
+    env: fn(&str) -> Option<String>,
+    tried: RefCell<Vec<Method>>,
+}
+
+impl Authenticator {
+    /// Uses `auth` from a `plugins.lua` entry and the credentials file.
+    pub fn new(auth: Option<&GitAuth>) -> Result<Self> {
+        let credentials = yal_config::try_load_config(&credentials_path())?;
+        Ok(Self::with(
+            auth.cloned().unwrap_or_default(),
+            credentials,
+            |name| std::env::var(name).ok(),
+        ))
+    }
+
+    pub fn with(auth: GitAuth, credentials: Credentials, env: fn(&str) -> Option<String>) -> Self {
+        Self {
+            auth,
+            credentials,
+            env,
+            tried: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Methods tried so far, in order.
+    pub fn tried(&self) -> Vec<Method> {
+        self.tried.borrow().clone()
+    }
+
+    pub fn clone_repo(&self, url: &str, dir: &Path) -> Result<Repository> {
+        git2::build::RepoBuilder::new()
+            .fetch_options(self.fetch_options())
+            .clone(url, dir)
+            .map_err(|e| self.explain(url, e))
+    }
+
+    /// Fetches `refspecs` from the `origin` remote of `repo`.
+    pub fn fetch(&self, repo: &Repository, refspecs: &[&str]) -> Result<()> {
+        let mut remote = repo.find_remote("origin")?;
+        let url = remote.url().unwrap_or_default().to_string();
+        remote
+            .fetch(refspecs, Some(&mut self.fetch_options()), None)
+            .map_err(|e| self.explain(&url, e))
+    }
+
+    fn fetch_options(&self) -> FetchOptions<'_> {
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|url, username, allowed| self.credential(url, username, allowed));
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks);
+        options
+    }
+
+    /// The next credential for `url` of a kind in `allowed`; libgit2 asks
+    /// again after each one is rejected.
+    pub fn credential(
+        &self,
+        url: &str,
+        username: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(self.auth.username.as_deref().unwrap_or("git"));
+        }
+        let Some(method) = self
+            .methods(url, allowed)
+            .into_iter()
+            .find(|m| !self.tried.borrow().contains(m))
+        else {
+            return Err(git2::Error::from_str("no more credentials to try"));
+        };
+        self.tried.borrow_mut().push(method.clone());
+
+        let username = username.unwrap_or("git");
+        match method {
+            Method::SshKey(path) => {
+                let passphrase = self.auth.passphrase_env.as_deref().and_then(self.env);
+                Cred::ssh_key(username, None, &path, passphrase.as_deref())
+            }
+            Method::SshAgent => Cred::ssh_key_from_agent(username),
+            Method::TokenEnv(var) => {
+                let token = (self.env)(&var).unwrap_or_default();
+                let username = self.auth.username.as_deref().unwrap_or(username);
+                Cred::userpass_plaintext(username, &token)
+            }
+            Method::CredentialsFile(host) => {
+                let credentials = &self.credentials[&host];
+                let username = credentials.username.as_deref().unwrap_or(username);
+                Cred::userpass_plaintext(username, &credentials.token)
+            }
+        }
+    }
+
+    /// Every configured method that fits `allowed`, in the order they are tried.
+    fn methods(&self, url: &str, allowed: CredentialType) -> Vec<Method> {
+        let mut methods = Vec::new();
+        if allowed.contains(CredentialType::SSH_KEY) {
+            if let Some(key) = &self.auth.ssh_key {
+                methods.push(Method::SshKey(expand_home(key)));
+            }
+            methods.push(Method::SshAgent);
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if let Some(var) = &self.auth.token_env
+                && (self.env)(var).is_some()
+            {
+                methods.push(Method::TokenEnv(var.clone()));
+            }
+            if let Some(host) = host(url)
+                && self.credentials.contains_key(host)
+            {
+                methods.push(Method::CredentialsFile(host.to_string()));
+            }
+        }
+        methods
+    }
+
+    /// Adds what was tried to an authentication failure.
+    fn explain(&self, url: &str, e: git2::Error) -> anyhow::Error {
+        let tried = self.tried.borrow();
+        if tried.is_empty() {
+            if e.code() == ErrorCode::Auth {
+                let host = host(url).unwrap_or(url);
+                return anyhow!("{} (no credentials configured for {})", e.message(), host);
+            }
+            return e.into();
+        }
+        let tried: Vec<String> = tried.iter().map(|m| m.to_string()).collect();
+        anyhow!("{} (tried {})", e.message(), tried.join(", "))
+    }
+}
+
+/// Host of an `https://`, `ssh://` or `git@host:path` URL.
+fn host(url: &str) -> Option<&str> {
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        None => url.split_once(':')?.0,
+    };
+    let authority = rest.split('/').next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tries_each_configured_method_once() {
+        let auth = GitAuth {
+            ssh_key: Some(PathBuf::from("/keys/work")),
+            token_env: Some("WORK_TOKEN".to_string()),
+            ..Default::default()
+        };
+        let credentials = Credentials::from([(
+            "git.example.com".to_string(),
+            HostCredentials {
+                token: "file-token".to_string(),
+                username: None,
+            },
+        )]);
+        let auth = Authenticator::with(auth, credentials, |name| {
+            (name == "WORK_TOKEN").then(|| "env-token".to_string())
+        });
+
+        let ssh = "git@git.example.com:me/private.git";
+        let key = CredentialType::SSH_KEY;
+        while auth.credential(ssh, Some("git"), key).is_ok() {}
+        let https = "https://git.example.com/me/private.git";
+        let plain = CredentialType::USER_PASS_PLAINTEXT;
+        while auth.credential(https, None, plain).is_ok() {}
+        assert_eq!(
+            auth.tried(),
+            vec![
+                Method::SshKey(PathBuf::from("/keys/work")),
+                Method::SshAgent,
+                Method::TokenEnv("WORK_TOKEN".to_string()),
+                Method::CredentialsFile("git.example.com".to_string()),
+            ]
+        );
+
+        let error = auth.explain(https, git2::Error::from_str("authentication failed"));
+        assert_eq!(
+            error.to_string(),
+            "authentication failed (tried SSH key /keys/work, ssh-agent, \
+             token from $WORK_TOKEN, token for git.example.com in credentials.lua)"
+        );
+    }
+
+    #[test]
+    fn clones_and_fetches_local_repositories() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let upstream = Repository::init(root.join("upstream")).unwrap();
+        let signature = git2::Signature::now("yal", "yal@example.com").unwrap();
+        let tree = upstream
+            .find_tree(upstream.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        upstream
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        let auth = Authenticator::with(GitAuth::default(), Credentials::new(), |_| None);
+        let url = format!("file://{}", root.join("upstream").display());
+        let clone = auth.clone_repo(&url, &root.join("clone")).unwrap();
+        auth.fetch(&clone, &["+refs/heads/*:refs/remotes/origin/*"])
+            .unwrap();
+        let missing = auth
+            .clone_repo(&format!("{}-missing", url), &root.join("missing"))
+            .is_err();
+
+        assert!(missing);
+        assert!(auth.tried().is_empty());
+    }
+}
@@ -1,13 +1,158 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
 
+use crate::{deps::http::HttpTransport, manager::auth::GitAuth};
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PluginConfigEntry {
     /// Human/plugin key (the key under [plugins] if present), or fallback
     pub name: String,
     /// Full git URL (e.g. https://github.com/owner/repo.git) or shorthand "owner/repo"
     pub git: String,
+    /// Credentials for a private `git` repository (see `manager::auth`)
+    #[serde(default)]
+    pub auth: Option<GitAuth>,
+    /// Public SSH keys, one of which must have signed the checked-out commit
+    /// or a tag on it (any code is accepted when empty)
+    #[serde(default)]
+    pub signers: Vec<String>,
+    /// Checkout the entry runs, for several instances of one plugin with
+    /// different configs (defaults to `name`). Entries with the same source
+    /// share an install directory but nothing else.
+    #[serde(default)]
+    pub source: Option<String>,
     /// Free-form plugin config handed to the plugin
     pub config: Option<serde_json::Value>,
+    /// `false` keeps the plugin installed but does not load it (defaults to
+    /// `true`; the palette's Enable/Disable commands override it)
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// `lua`, `wasm` or `process`. Without it, a directory containing
+    /// `plugin.wasm` and no `init.lua` is a WASM plugin, anything else Lua.
+    #[serde(default)]
+    pub kind: Option<PluginKind>,
+    /// Executable of a `process` plugin, relative to the plugin directory if
+    /// it contains a `/`, otherwise looked up on PATH
+    pub exec: Option<String>,
+    /// Arguments for `exec`
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Host APIs a WASM plugin may use besides logging
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    /// Where the plugin's `yal.http` requests go (defaults to the network)
+    #[serde(default)]
+    pub http: Option<HttpConfig>,
+}
+
+impl PluginConfigEntry {
+    /// An entry with nothing but a source, as for plugins installed because
+    /// another plugin depends on them.
+    pub fn new(name: &str, git: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            git: git.to_string(),
+            auth: None,
+            signers: Vec::new(),
+            source: None,
+            config: None,
+            enabled: None,
+            kind: None,
+            exec: None,
+            args: Vec::new(),
+            capabilities: Vec::new(),
+            http: None,
+        }
+    }
+
+    /// Name of the install directory the entry runs from.
+    pub fn source(&self) -> &str {
+        self.source.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginKind {
+    /// A folder with an `init.lua`
+    #[default]
+    Lua,
+    /// A folder with a `plugin.wasm` component (see wit/plugin.wit)
+    Wasm,
+    /// An executable speaking JSON-RPC on stdio (see docs/process-plugins.md)
+    Process,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Http,
+    Db,
+    Ui,
+}
+
+impl Capability {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Capability::Http => "http",
+            Capability::Db => "db",
+            Capability::Ui => "ui",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpMode {
+    #[default]
+    Live,
+    Record,
+    Replay,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HttpConfig {
+    #[serde(default)]
+    pub mode: HttpMode,
+    /// Cassette file, relative to the plugin directory (default `cassettes/http.json`)
+    pub cassette: Option<PathBuf>,
+}
+
+impl HttpMode {
+    pub fn transport(self, cassette: PathBuf) -> HttpTransport {
+        match self {
+            HttpMode::Live => HttpTransport::Live,
+            HttpMode::Record => HttpTransport::Record(cassette),
+            HttpMode::Replay => HttpTransport::Replay(cassette),
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn transport(&self, plugin_dir: &Path) -> HttpTransport {
+        let cassette = plugin_dir.join(
+            self.cassette
+                .as_deref()
+                .unwrap_or(Path::new("cassettes/http.json")),
+        );
+        self.mode.transport(cassette)
+    }
 }
 
 pub type PluginConfig = Vec<PluginConfigEntry>;
+
+/// Fails unless `name` is a single plain path component, as the names that
+/// end up as directories under YAL's data directory must be. `what` names it
+/// in the error, e.g. "plugin name".
+pub fn check_dir_name(what: &str, name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        bail!("Invalid {} '{}'", what, name);
+    }
+    Ok(())
+}
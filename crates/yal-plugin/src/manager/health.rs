@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use crate::deps::task::TaskStatus;
+
+/// Outcome of the last attempt to load a configured plugin.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PluginStatus {
+    Disabled,
+    NotInstalled,
+    Failed { error: String },
+    Incompatible { required: String, host: String },
+    InvalidConfig { errors: Vec<String> },
+    Untrusted { reason: String },
+    Loaded { version: String, commands: usize },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PluginHealth {
+    pub name: String,
+    #[serde(flatten)]
+    pub status: PluginStatus,
+    /// The plugin's running and failed `yal.task` tasks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<TaskStatus>,
+}
+
+impl PluginHealth {
+    pub fn is_ok(&self) -> bool {
+        matches!(
+            self.status,
+            PluginStatus::Loaded { .. } | PluginStatus::Disabled
+        )
+    }
+}
+
+/// Result of fast-forwarding a plugin checkout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    UpToDate,
+    Updated { from: String, to: String },
+}
@@ -0,0 +1,288 @@
+//! Checks that an installed plugin is still the code it was installed as.
+//!
+//! Installing or updating a plugin records the hash of its checkout in
+//! `.git/yal-integrity.json`: every tracked file as it is on disk, plus
+//! untracked Lua and WASM files. Loading hashes the checkout again and
+//! refuses the plugin if anything changed. Entries with `signers` also need
+//! the checked-out commit, or a tag on it, signed by one of those SSH keys.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use git2::{IndexAddOption, Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+pub const RECORD_FILE: &str = "yal-integrity.json";
+const ALLOWED_SIGNERS_FILE: &str = "yal-allowed-signers";
+
+/// What a checkout looked like when it was installed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub commit: String,
+    pub tree: String,
+}
+
+/// Why a plugin was refused: its checkout changed since it was installed,
+/// or it is not signed by an approved key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Untrusted {
+    pub reason: String,
+}
+
+impl fmt::Display for Untrusted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "untrusted checkout: {}", self.reason)
+    }
+}
+
+impl std::error::Error for Untrusted {}
+
+fn untrusted(reason: String) -> anyhow::Error {
+    Untrusted { reason }.into()
+}
+
+/// Records the checkout in `dir` as it is now.
+pub fn record(dir: &Path) -> Result<Record> {
+    let repo = Repository::open(dir)?;
+    let record = Record {
+        commit: repo.head()?.peel_to_commit()?.id().to_string(),
+        tree: worktree(&repo)?.to_string(),
+    };
+    let path = repo.path().join(RECORD_FILE);
+    let json = serde_json::to_string_pretty(&record)?;
+    std::fs::write(&path, json + "\n")
+        .with_context(|| format!("Failed writing {}", path.display()))?;
+    Ok(record)
+}
+
+fn load(repo: &Repository) -> Result<Option<Record>> {
+    let path = repo.path().join(RECORD_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json)
+            .map(Some)
+            .with_context(|| format!("Invalid {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Checks the checkout in `dir` against its record and, if there are any,
+/// `signers`. Checkouts without a record, such as ones installed before
+/// records existed or linked for development, pass unless `signers` is set.
+pub fn verify(dir: &Path, signers: &[String]) -> Result<()> {
+    let repo = match Repository::open(dir) {
+        Ok(repo) => repo,
+        Err(_) if signers.is_empty() => return Ok(()),
+        Err(e) => return Err(untrusted(format!("not a git checkout ({})", e.message()))),
+    };
+    let head = repo.head()?.peel_to_commit()?.id();
+
+    if let Some(record) = load(&repo)? {
+        if head.to_string() != record.commit {
+            return Err(untrusted(format!(
+                "{} is checked out instead of {}",
+                short_id(&head.to_string()),
+                short_id(&record.commit)
+            )));
+        }
+        let tree = worktree(&repo)?;
+        if tree.to_string() != record.tree {
+            return Err(untrusted(match changed(&repo, &record.tree, tree) {
+                Ok(paths) if !paths.is_empty() => {
+                    format!("modified since install: {}", paths.join(", "))
+                }
+                _ => "modified since install".to_string(),
+            }));
+        }
+    } else {
+        log::debug!("No integrity record in {}, skipping", dir.display());
+    }
+
+    if !signers.is_empty() {
+        verify_signature(&repo, head, signers)?;
+    }
+    Ok(())
+}
+
+/// Hash of the tree the checkout would have if everything on disk were
+/// committed. Tracked files are hashed afresh rather than trusting the
+/// index's cached stat data, which edits can preserve.
+fn worktree(repo: &Repository) -> Result<Oid> {
+    let workdir = repo.workdir().context("Bare repository")?.to_path_buf();
+    let mut index = repo.index()?;
+    let tracked: Vec<PathBuf> = index
+        .iter()
+        .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned()))
+        .collect();
+    index.clear()?;
+    for path in tracked {
+        // Deleted files drop out; submodules are not hashed.
+        if std::fs::symlink_metadata(workdir.join(&path)).is_ok_and(|m| !m.is_dir()) {
+            index.add_path(&path)?;
+        }
+    }
+    index.add_all(["*.lua", "*.wasm"], IndexAddOption::DEFAULT, None)?;
+    Ok(index.write_tree()?)
+}
+
+/// Paths that differ between the recorded tree and `tree`.
+fn changed(repo: &Repository, recorded: &str, tree: Oid) -> Result<Vec<String>> {
+    let recorded = repo.find_tree(Oid::from_str(recorded)?)?;
+    let tree = repo.find_tree(tree)?;
+    let diff = repo.diff_tree_to_tree(Some(&recorded), Some(&tree), None)?;
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+        .map(|path| path.display().to_string())
+        .collect())
+}
+
+/// Runs `git verify-commit` on `commit`, then `git verify-tag` on the tags
+/// pointing at it, with `signers` as the allowed SSH signers. GPG and X.509
+/// signatures are never accepted, however good.
+fn verify_signature(repo: &Repository, commit: Oid, signers: &[String]) -> Result<()> {
+    let allowed = repo.path().join(ALLOWED_SIGNERS_FILE);
+    let lines: Vec<String> = signers.iter().map(|key| format!("* {}", key)).collect();
+    std::fs::write(&allowed, lines.join("\n") + "\n")
+        .with_context(|| format!("Failed writing {}", allowed.display()))?;
+
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let target = repo
+            .revparse_single(&format!("refs/tags/{}", name))
+            .and_then(|object| object.peel_to_commit());
+        if target.is_ok_and(|target| target.id() == commit) {
+            tags.push(name.to_string());
+        }
+    }
+
+    let git_dir = repo.path();
+    let verify = |args: &[&str]| -> Result<bool> {
+        let output = Command::new("git")
+            .arg("--git-dir")
+            .arg(git_dir)
+            .arg("-c")
+            .arg(format!("gpg.ssh.allowedSignersFile={}", allowed.display()))
+            .args(["-c", "gpg.program=false", "-c", "gpg.x509.program=false"])
+            .args(args)
+            .output()
+            .context("Failed running git")?;
+        Ok(output.status.success())
+    };
+    if verify(&["verify-commit", &commit.to_string()])? {
+        return Ok(());
+    }
+    for tag in &tags {
+        if verify(&["verify-tag", tag])? {
+            return Ok(());
+        }
+    }
+    Err(untrusted(format!(
+        "{} is not signed by an approved key",
+        short_id(&commit.to_string())
+    )))
+}
+
+fn short_id(id: &str) -> &str {
+    &id[..id.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_changes_since_install() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let repo = Repository::init(dir).unwrap();
+        std::fs::write(dir.join("init.lua"), "return 1").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("init.lua")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("yal", "yal@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        record(dir).unwrap();
+
+        let mut results = vec![verify(dir, &[]).map_err(|e| e.to_string())];
+        std::fs::write(dir.join("init.lua"), "return 2").unwrap();
+        results.push(verify(dir, &[]).map_err(|e| e.to_string()));
+        std::fs::write(dir.join("init.lua"), "return 1").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not code").unwrap();
+        results.push(verify(dir, &[]).map_err(|e| e.to_string()));
+        std::fs::write(dir.join("extra.lua"), "os.exit()").unwrap();
+        results.push(verify(dir, &[]).map_err(|e| e.to_string()));
+
+        assert_eq!(
+            results,
+            vec![
+                Ok(()),
+                Err("untrusted checkout: modified since install: init.lua".to_string()),
+                Ok(()),
+                Err("untrusted checkout: modified since install: extra.lua".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_only_commits_signed_by_a_listed_key() {
+        let tmp = tempfile::tempdir().unwrap();
+        let keygen = |name: &str| -> Option<(PathBuf, String)> {
+            let key = tmp.path().join(name);
+            let status = Command::new("ssh-keygen")
+                .args(["-q", "-t", "ed25519", "-N", "", "-C", name, "-f"])
+                .arg(&key)
+                .status()
+                .ok()?;
+            let public = std::fs::read_to_string(key.with_extension("pub")).ok()?;
+            status.success().then(|| (key, public.trim().to_string()))
+        };
+        let (Some((signer, signer_public)), Some((_, other_public))) =
+            (keygen("signer"), keygen("other"))
+        else {
+            eprintln!("ssh-keygen is not available, skipping");
+            return;
+        };
+
+        let dir = tmp.path().join("plugin");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("init.lua"), "return 1").unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .current_dir(&dir)
+                .env("GIT_CONFIG_GLOBAL", "/dev/null")
+                .env("GIT_CONFIG_NOSYSTEM", "1")
+                .args(["-c", "user.name=yal", "-c", "user.email=yal@example.com"])
+                .args(["-c", "gpg.format=ssh", "-c"])
+                .arg(format!("user.signingKey={}", signer.display()))
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        git(&["add", "init.lua"]);
+        git(&["commit", "-q", "-S", "-m", "init"]);
+        let head = Repository::open(&dir)
+            .unwrap()
+            .head()
+            .unwrap()
+            .target()
+            .unwrap()
+            .to_string();
+
+        assert!(verify(&dir, &[other_public.clone(), signer_public]).is_ok());
+        assert_eq!(
+            verify(&dir, &[other_public]).unwrap_err().to_string(),
+            format!(
+                "untrusted checkout: {} is not signed by an approved key",
+                short_id(&head)
+            )
+        );
+    }
+}
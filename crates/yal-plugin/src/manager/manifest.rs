@@ -0,0 +1,379 @@
+//! `manifest.lua`: what a plugin needs besides its own checkout.
+//!
+//! ```lua
+//! return {
+//!   dependencies = {
+//!     { name = "spotify-api", git = "me/yal-spotify-api", version = "^1.2" },
+//!   },
+//!   packages = {
+//!     { name = "inspect", git = "kikito/inspect.lua", ref = "v3.1.3" },
+//!   },
+//!   config_schema = {
+//!     type = "object",
+//!     properties = { token = { type = "string" } },
+//!   },
+//! }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    manager::{
+        config::{PluginConfig, PluginConfigEntry, check_dir_name},
+        packages,
+    },
+    schema::ConfigSchema,
+};
+
+pub const MANIFEST_FILE: &str = "manifest.lua";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Manifest {
+    /// Plugins that must be installed and loaded before this one
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+    /// Pure-Lua libraries fetched into the plugin's package cache
+    #[serde(default)]
+    pub packages: Vec<LuaPackage>,
+    /// What `config` in `plugins.lua` may contain; checked before `init`
+    pub config_schema: Option<ConfigSchema>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    /// Where to install it from if `plugins.lua` does not configure it
+    pub git: String,
+    /// Semver range the dependency's `version` must match
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LuaPackage {
+    /// Directory name in the cache; not what the package is `require`d as
+    pub name: String,
+    pub git: String,
+    /// Branch, tag or commit (defaults to the remote's default branch)
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    /// Directory in the repository that modules are found under (defaults to its root)
+    pub path: Option<PathBuf>,
+}
+
+impl Manifest {
+    /// Reads the manifest of the plugin checked out in `dir`. A plugin
+    /// without one has no dependencies.
+    pub fn load(dir: &Path) -> Result<Self> {
+        yal_config::try_load_config(&dir.join(MANIFEST_FILE))
+    }
+
+    /// Where `require` looks for the plugin's packages, in manifest order.
+    pub fn package_roots(&self, plugin: &str) -> Vec<PathBuf> {
+        self.packages
+            .iter()
+            .map(|package| {
+                let dir = packages::package_dir(plugin, &package.name);
+                match &package.path {
+                    Some(path) => dir.join(path),
+                    None => dir,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A plugin to load, with its manifest.
+#[derive(Debug, Clone)]
+pub struct ResolvedPlugin {
+    pub entry: PluginConfigEntry,
+    pub manifest: Manifest,
+}
+
+/// A plugin that could not be resolved, with the reason.
+#[derive(Debug)]
+pub struct Unresolved {
+    pub entry: PluginConfigEntry,
+    pub error: anyhow::Error,
+}
+
+/// What `resolve_each` made of a config.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    /// Ordered so that every plugin comes after its dependencies
+    pub plugins: Vec<ResolvedPlugin>,
+    /// Plugins with a broken manifest, an invalid dependency or a dependency
+    /// cycle, and the plugins depending on them
+    pub unresolved: Vec<Unresolved>,
+}
+
+/// Expands `config` with the plugins it depends on, transitively, ordered
+/// so that every plugin comes after its dependencies. Fails on the first
+/// plugin that cannot be resolved; see `resolve_each`.
+pub fn resolve(
+    config: &PluginConfig,
+    manifest_of: impl FnMut(&PluginConfigEntry) -> Result<Manifest>,
+) -> Result<Vec<ResolvedPlugin>> {
+    let resolution = resolve_each(config, manifest_of);
+    match resolution.unresolved.into_iter().next() {
+        Some(unresolved) => Err(unresolved.error),
+        None => Ok(resolution.plugins),
+    }
+}
+
+/// Like `resolve`, but a plugin that cannot be resolved only takes down the
+/// plugins depending on it.
+///
+/// `manifest_of` is called once per plugin. A dependency that is also
+/// configured in `plugins.lua` uses that entry (and its config); otherwise it
+/// is installed from the `git` of the first plugin that declared it.
+pub fn resolve_each(
+    config: &PluginConfig,
+    manifest_of: impl FnMut(&PluginConfigEntry) -> Result<Manifest>,
+) -> Resolution {
+    let mut resolver = Resolver {
+        config,
+        manifest_of,
+        stack: Vec::new(),
+        resolution: Resolution::default(),
+    };
+    for entry in config {
+        resolver.visit(entry.clone());
+    }
+    resolver.resolution
+}
+
+struct Resolver<'a, F> {
+    config: &'a PluginConfig,
+    manifest_of: F,
+    /// Plugins whose dependencies are being resolved, outermost first.
+    stack: Vec<String>,
+    resolution: Resolution,
+}
+
+impl<F: FnMut(&PluginConfigEntry) -> Result<Manifest>> Resolver<'_, F> {
+    /// Resolves `entry` and its dependencies. Returns whether it resolved.
+    fn visit(&mut self, entry: PluginConfigEntry) -> bool {
+        if self
+            .resolution
+            .plugins
+            .iter()
+            .any(|p| p.entry.name == entry.name)
+        {
+            return true;
+        }
+        if self.is_unresolved(&entry.name) {
+            return false;
+        }
+        if let Some(start) = self.stack.iter().position(|name| *name == entry.name) {
+            let mut cycle = self.stack[start..].to_vec();
+            cycle.push(entry.name.clone());
+            self.fail(
+                entry,
+                anyhow!("Plugin dependency cycle: {}", cycle.join(" -> ")),
+            );
+            return false;
+        }
+
+        let manifest = match (self.manifest_of)(&entry)
+            .with_context(|| format!("Failed reading the manifest of '{}'", entry.name))
+        {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                self.fail(entry, e);
+                return false;
+            }
+        };
+        self.stack.push(entry.name.clone());
+        let mut error = None;
+        for dependency in &manifest.dependencies {
+            if let Err(e) = check_dir_name("dependency name", &dependency.name) {
+                error = Some(e);
+                break;
+            }
+            let dependency_entry = self
+                .config
+                .iter()
+                .find(|e| e.name == dependency.name)
+                .cloned()
+                .unwrap_or_else(|| PluginConfigEntry::new(&dependency.name, &dependency.git));
+            if !self.visit(dependency_entry) {
+                error = Some(anyhow!(
+                    "Dependency '{}' could not be resolved",
+                    dependency.name
+                ));
+                break;
+            }
+        }
+        self.stack.pop();
+
+        match error {
+            Some(e) => {
+                self.fail(entry, e);
+                false
+            }
+            None => {
+                self.resolution
+                    .plugins
+                    .push(ResolvedPlugin { entry, manifest });
+                true
+            }
+        }
+    }
+
+    fn is_unresolved(&self, name: &str) -> bool {
+        self.resolution
+            .unresolved
+            .iter()
+            .any(|u| u.entry.name == name)
+    }
+
+    /// Records why `entry` did not resolve. The first reason sticks: in a
+    /// cycle, the plugin that closed it keeps the cycle as its error.
+    fn fail(&mut self, entry: PluginConfigEntry, error: anyhow::Error) {
+        if !self.is_unresolved(&entry.name) {
+            self.resolution.unresolved.push(Unresolved { entry, error });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::bail;
+
+    use super::*;
+
+    fn dependency(name: &str) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            git: format!("me/{}", name),
+            version: None,
+        }
+    }
+
+    fn resolve_each_with(config: &[&str], graph: &[(&str, &[&str])]) -> Resolution {
+        let config = config
+            .iter()
+            .map(|name| PluginConfigEntry::new(name, &format!("configured/{}", name)))
+            .collect();
+        let graph: HashMap<_, _> = graph.iter().copied().collect();
+        resolve_each(&config, |entry| {
+            if entry.name == "broken" {
+                bail!("syntax error");
+            }
+            Ok(Manifest {
+                dependencies: graph
+                    .get(entry.name.as_str())
+                    .map(|deps| deps.iter().map(|d| dependency(d)).collect())
+                    .unwrap_or_default(),
+                ..Default::default()
+            })
+        })
+    }
+
+    fn resolve_with(config: &[&str], graph: &[(&str, &[&str])]) -> Result<Vec<(String, String)>> {
+        let resolution = resolve_each_with(config, graph);
+        if let Some(unresolved) = resolution.unresolved.into_iter().next() {
+            return Err(unresolved.error);
+        }
+        Ok(resolution
+            .plugins
+            .into_iter()
+            .map(|p| (p.entry.name, p.entry.git))
+            .collect())
+    }
+
+    fn names(resolution: &Resolution) -> (Vec<&str>, Vec<(&str, String)>) {
+        (
+            resolution
+                .plugins
+                .iter()
+                .map(|p| p.entry.name.as_str())
+                .collect(),
+            resolution
+                .unresolved
+                .iter()
+                .map(|u| (u.entry.name.as_str(), u.error.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn orders_dependencies_first() {
+        let order = resolve_with(
+            &["app", "lib"],
+            &[("app", &["lib", "http"]), ("lib", &["http"])],
+        )
+        .unwrap();
+        assert_eq!(
+            order,
+            vec![
+                ("http".to_string(), "me/http".to_string()),
+                ("lib".to_string(), "configured/lib".to_string()),
+                ("app".to_string(), "configured/app".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let err = resolve_with(&["a"], &[("a", &["b"]), ("b", &["c"]), ("c", &["a"])]).unwrap_err();
+        assert_eq!(err.to_string(), "Plugin dependency cycle: a -> b -> c -> a");
+    }
+
+    #[test]
+    fn cycles_only_fail_the_plugins_in_them() {
+        let resolution =
+            resolve_each_with(&["a", "ok"], &[("a", &["b"]), ("b", &["c"]), ("c", &["a"])]);
+        assert_eq!(
+            names(&resolution),
+            (
+                vec!["ok"],
+                vec![
+                    ("a", "Plugin dependency cycle: a -> b -> c -> a".to_string()),
+                    ("c", "Dependency 'a' could not be resolved".to_string()),
+                    ("b", "Dependency 'c' could not be resolved".to_string()),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn broken_manifests_fail_their_dependents() {
+        let resolution = resolve_each_with(
+            &["app", "other", "lib"],
+            &[("app", &["broken"]), ("lib", &["http"])],
+        );
+        assert_eq!(
+            names(&resolution),
+            (
+                vec!["other", "http", "lib"],
+                vec![
+                    (
+                        "broken",
+                        "Failed reading the manifest of 'broken'".to_string()
+                    ),
+                    (
+                        "app",
+                        "Dependency 'broken' could not be resolved".to_string()
+                    ),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_dependency_names_that_are_not_plain() {
+        for name in ["../escape", "/abs", "a/b", ".."] {
+            let err = resolve_with(&["app"], &[("app", &[name])]).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("Invalid dependency name '{}'", name)
+            );
+        }
+    }
+}
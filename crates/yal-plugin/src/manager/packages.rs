@@ -0,0 +1,170 @@
+//! Pure-Lua packages listed under `packages` in a plugin's `manifest.lua`.
+//!
+//! Each package is a git checkout in the plugin's package cache, which the
+//! vendor searcher consults after `vendor/`. The commit every package
+//! resolved to is recorded in `manifest.lock` next to the manifest; plugins
+//! that commit the lock get the same commits on every machine.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use git2::{Oid, Repository, build::CheckoutBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::manager::{
+    auth::{Authenticator, GitAuth},
+    config::check_dir_name,
+    git_url,
+    manifest::{LuaPackage, Manifest},
+};
+
+pub const LOCK_FILE: &str = "manifest.lock";
+
+/// `~/.local/share/yal/packages`
+pub fn packages_root() -> PathBuf {
+    let mut dir = dirs::home_dir().expect("Failed to get home directory");
+    dir.push(".local/share/yal/packages");
+    dir
+}
+
+/// `~/.local/share/yal/packages/<plugin>`
+pub fn packages_dir(plugin: &str) -> PathBuf {
+    packages_root().join(plugin)
+}
+
+pub fn package_dir(plugin: &str, package: &str) -> PathBuf {
+    packages_dir(plugin).join(package)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lock {
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub git: String,
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    pub commit: String,
+}
+
+impl Lock {
+    /// Reads the lock of the plugin in `dir`; a missing lock is empty.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(LOCK_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(json) => {
+                serde_json::from_str(&json).with_context(|| format!("Invalid {}", path.display()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(LOCK_FILE);
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json + "\n")
+            .with_context(|| format!("Failed writing {}", path.display()))
+    }
+
+    /// The locked commit for `package`, unless its source changed since.
+    fn commit_for(&self, package: &LuaPackage) -> Option<&str> {
+        self.packages
+            .iter()
+            .find(|p| {
+                p.name == package.name && p.git == package.git && p.reference == package.reference
+            })
+            .map(|p| p.commit.as_str())
+    }
+}
+
+/// Checks out the packages of the plugin `plugin`, installed in `dir`, at
+/// their locked commits (or the tips of their refs when not locked), removes
+/// cached packages the manifest no longer lists and updates the lock.
+/// Packages are fetched with the plugin's `auth`.
+pub fn sync(plugin: &str, dir: &Path, manifest: &Manifest, auth: Option<&GitAuth>) -> Result<()> {
+    let cache = packages_dir(plugin);
+    if manifest.packages.is_empty() && !cache.exists() {
+        return Ok(());
+    }
+
+    let lock = Lock::load(dir)?;
+    let mut updated = Lock::default();
+    for package in &manifest.packages {
+        check_dir_name("package name", &package.name)?;
+        let commit = checkout(
+            &cache.join(&package.name),
+            package,
+            lock.commit_for(package),
+            &Authenticator::new(auth)?,
+        )
+        .with_context(|| format!("Failed fetching package '{}'", package.name))?;
+        updated.packages.push(LockedPackage {
+            name: package.name.clone(),
+            git: package.git.clone(),
+            reference: package.reference.clone(),
+            commit,
+        });
+    }
+
+    if cache.exists() {
+        for entry in std::fs::read_dir(&cache)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !manifest.packages.iter().any(|p| p.name == name) {
+                log::info!("Removing unused package {}", path.display());
+                std::fs::remove_dir_all(&path)?;
+            }
+        }
+    }
+
+    if updated != lock {
+        updated.save(dir)?;
+    }
+    Ok(())
+}
+
+/// Brings the checkout in `dir` to `locked`, or to the tip of the package's
+/// ref, and returns the commit it is at.
+fn checkout(
+    dir: &Path,
+    package: &LuaPackage,
+    locked: Option<&str>,
+    auth: &Authenticator,
+) -> Result<String> {
+    let repo = if dir.exists() {
+        let repo = Repository::open(dir)?;
+        if let (Some(locked), Ok(head)) = (locked, repo.head())
+            && head.target().map(|oid| oid.to_string()).as_deref() == Some(locked)
+        {
+            return Ok(locked.to_string());
+        }
+        auth.fetch(
+            &repo,
+            &[
+                "+refs/heads/*:refs/remotes/origin/*",
+                "+refs/tags/*:refs/tags/*",
+            ],
+        )?;
+        repo
+    } else {
+        log::info!("Fetching package {} from {}", package.name, package.git);
+        auth.clone_repo(&git_url(&package.git), dir)?
+    };
+
+    let commit = match (locked, &package.reference) {
+        (Some(locked), _) => repo.find_commit(Oid::from_str(locked)?)?,
+        (None, Some(reference)) => repo
+            .revparse_single(&format!("origin/{}", reference))
+            .or_else(|_| repo.revparse_single(reference))
+            .with_context(|| format!("Unknown ref '{}'", reference))?
+            .peel_to_commit()?,
+        (None, None) => repo.revparse_single("origin/HEAD")?.peel_to_commit()?,
+    };
+    repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))?;
+    repo.set_head_detached(commit.id())?;
+    Ok(commit.id().to_string())
+}
@@ -0,0 +1,299 @@
+//! Leftovers of plugins that are no longer configured: checkouts, Lua
+//! packages, saved settings and `yal.db` data.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use yal_core::{Node, Prompt, PromptResponse, TextVariant};
+
+use crate::{
+    backend::Backend,
+    deps::db,
+    manager::{packages, plugins_dir, settings},
+};
+
+/// Where plugins keep things on disk.
+#[derive(Debug, Clone)]
+pub struct Locations {
+    pub plugins: PathBuf,
+    pub packages: PathBuf,
+    pub settings: PathBuf,
+    pub data: PathBuf,
+}
+
+impl Default for Locations {
+    fn default() -> Self {
+        Self {
+            plugins: plugins_dir(),
+            packages: packages::packages_root(),
+            settings: settings::settings_dir(),
+            data: db::db_dir(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Leftover {
+    Checkout,
+    Packages,
+    Settings,
+    Data,
+}
+
+impl fmt::Display for Leftover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Leftover::Checkout => "checkout",
+            Leftover::Packages => "packages",
+            Leftover::Settings => "settings",
+            Leftover::Data => "data",
+        })
+    }
+}
+
+/// Everything left behind by one plugin that is no longer configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Orphan {
+    pub name: String,
+    pub items: Vec<(Leftover, PathBuf)>,
+    /// Disk usage of all items
+    pub bytes: u64,
+}
+
+/// Shows `orphans` with their disk usage and waits until the user confirms
+/// deleting them or backs out. Returns whether they confirmed.
+pub async fn confirm<T: Backend>(backend: &T, orphans: &[Orphan]) -> Result<bool> {
+    let content = if orphans.is_empty() {
+        vec![Node::Text {
+            text: "Removed plugins left nothing behind.".to_string(),
+            variant: None,
+        }]
+    } else {
+        let total: u64 = orphans.iter().map(|o| o.bytes).sum();
+        let lines: Vec<String> = orphans
+            .iter()
+            .map(|o| {
+                let kinds: Vec<String> = o.items.iter().map(|(k, _)| k.to_string()).collect();
+                format!(
+                    "- **{}**: {} ({})",
+                    o.name,
+                    human_size(o.bytes),
+                    kinds.join(", ")
+                )
+            })
+            .collect();
+        vec![
+            Node::Markdown {
+                md: format!("{}\n\n**Total:** {}", lines.join("\n"), human_size(total)),
+            },
+            Node::Text {
+                text: "Press Enter to delete them or Escape to keep them.".to_string(),
+                variant: Some(TextVariant::Muted),
+            },
+        ]
+    };
+    let id = backend
+        .prompt(Prompt {
+            title: Some("Clean up removed plugins".to_string()),
+            width: None,
+            height: None,
+            content,
+            ui_schema_version: None,
+        })
+        .await?;
+    loop {
+        match backend.prompt_submission(id.clone()).await? {
+            PromptResponse::Submit { .. } => return Ok(!orphans.is_empty()),
+            PromptResponse::Cancel => return Ok(false),
+            PromptResponse::State { .. } => continue,
+        }
+    }
+}
+
+/// Finds leftovers in `locations` of plugins outside `sources` (install
+/// directories in use) and `names` (instances in use).
+///
+/// `yal.db` namespaces are picked by plugins, so only the namespace named
+/// like an orphaned checkout and the data directories of removed instances
+/// count as theirs.
+pub fn find(
+    locations: &Locations,
+    sources: &HashSet<String>,
+    names: &HashSet<String>,
+) -> Result<Vec<Orphan>> {
+    let mut found: BTreeMap<String, Vec<(Leftover, PathBuf)>> = BTreeMap::new();
+    for (name, path) in entries(&locations.plugins)? {
+        if !sources.contains(&name) {
+            found
+                .entry(name)
+                .or_default()
+                .push((Leftover::Checkout, path));
+        }
+    }
+    for (name, path) in entries(&locations.packages)? {
+        if !sources.contains(&name) {
+            found
+                .entry(name)
+                .or_default()
+                .push((Leftover::Packages, path));
+        }
+    }
+    for (name, path) in entries(&locations.settings)? {
+        if let Some(name) = name.strip_suffix(".json")
+            && !names.contains(name)
+        {
+            let name = name.to_string();
+            found
+                .entry(name)
+                .or_default()
+                .push((Leftover::Settings, path));
+        }
+    }
+    for (name, path) in entries(&locations.data)? {
+        let namespace = name.strip_suffix(".json");
+        let owner = namespace.unwrap_or(&name);
+        if names.contains(owner) || sources.contains(owner) {
+            continue;
+        }
+        // A directory holds the namespaces of an instance; a file is one
+        // namespace, which only counts when its plugin is gone too.
+        let is_instance = namespace.is_none() && path.is_dir();
+        if is_instance || found.contains_key(owner) {
+            let owner = owner.to_string();
+            found.entry(owner).or_default().push((Leftover::Data, path));
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|(name, mut items)| {
+            items.sort();
+            let mut bytes = 0;
+            for (_, path) in &items {
+                bytes += disk_usage(path)
+                    .with_context(|| format!("Failed reading {}", path.display()))?;
+            }
+            Ok(Orphan { name, items, bytes })
+        })
+        .collect()
+}
+
+/// Deletes everything `orphan` left behind.
+pub fn remove(orphan: &Orphan) -> Result<()> {
+    for (_, path) in &orphan.items {
+        let metadata = match std::fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        // A linked development checkout loses the link, not the code.
+        if metadata.is_dir() {
+            std::fs::remove_dir_all(path)
+        } else {
+            std::fs::remove_file(path)
+        }
+        .with_context(|| format!("Failed removing {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// `1.2 MB` and the like.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn entries(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let read = match std::fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed reading {}", dir.display())),
+    };
+    let mut entries = Vec::new();
+    for entry in read {
+        let entry = entry?;
+        entries.push((
+            entry.file_name().to_string_lossy().into_owned(),
+            entry.path(),
+        ));
+    }
+    Ok(entries)
+}
+
+/// Bytes used by `path`, without following symlinks.
+fn disk_usage(path: &Path) -> Result<u64> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += disk_usage(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_what_removed_plugins_left_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let locations = Locations {
+            plugins: root.join("plugins"),
+            packages: root.join("packages"),
+            settings: root.join("settings"),
+            data: root.join("data"),
+        };
+        let write = |path: PathBuf, contents: &str| {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(locations.plugins.join("jira/init.lua"), "return {}");
+        write(locations.plugins.join("old/init.lua"), "return {}");
+        write(locations.packages.join("old/inspect/inspect.lua"), "--");
+        write(locations.settings.join("jira-home.json"), "{}");
+        write(locations.data.join("old.json"), "{}");
+        write(locations.data.join("cache.json"), "{}");
+        write(locations.data.join("jira-home/jira.json"), "{}");
+        write(locations.data.join("jira-work/jira.json"), "{}");
+
+        let sources = HashSet::from(["jira".to_string()]);
+        let names = HashSet::from(["jira-work".to_string()]);
+        let orphans = find(&locations, &sources, &names).unwrap();
+
+        let summary: Vec<(&str, Vec<Leftover>, u64)> = orphans
+            .iter()
+            .map(|o| {
+                let kinds = o.items.iter().map(|(kind, _)| *kind).collect();
+                (o.name.as_str(), kinds, o.bytes)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("jira-home", vec![Leftover::Settings, Leftover::Data], 4),
+                (
+                    "old",
+                    vec![Leftover::Checkout, Leftover::Packages, Leftover::Data],
+                    13,
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Plugin settings saved from the "Configure <plugin>" form.
+//!
+//! They live in `plugin-settings/<plugin>.json` next to `plugins.lua` and
+//! override its `config` key by key, so hand-written config keeps working
+//! for everything the form did not touch.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use yal_core::{Node, Prompt, PromptResponse};
+
+use crate::{
+    backend::Backend,
+    schema::{ConfigSchema, SchemaError},
+};
+
+/// `~/.config/yal/plugin-settings`
+pub fn settings_dir() -> PathBuf {
+    yal_config::config_base_path().join("plugin-settings")
+}
+
+/// `~/.config/yal/plugin-settings/<plugin>.json`
+pub fn settings_path(plugin: &str) -> PathBuf {
+    settings_dir().join(format!("{}.json", plugin))
+}
+
+/// The saved settings of `plugin`, if any.
+pub fn load(plugin: &str) -> Result<Option<Value>> {
+    let path = settings_path(plugin);
+    match std::fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json)
+            .map(Some)
+            .with_context(|| format!("Invalid {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save(plugin: &str, settings: &Value) -> Result<()> {
+    let path = settings_path(plugin);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(settings)?;
+    std::fs::write(&path, json + "\n").with_context(|| format!("Failed writing {}", path.display()))
+}
+
+/// `config` with `settings` laid over it; tables merge, anything else in
+/// `settings` replaces what is in `config`.
+pub fn merge(config: Option<Value>, settings: Option<Value>) -> Option<Value> {
+    match (config, settings) {
+        (Some(Value::Object(mut config)), Some(Value::Object(settings))) => {
+            for (key, value) in settings {
+                let merged = merge(config.remove(&key), Some(value));
+                if let Some(merged) = merged {
+                    config.insert(key, merged);
+                }
+            }
+            Some(Value::Object(config))
+        }
+        (config, None) => config,
+        (_, settings) => settings,
+    }
+}
+
+/// Shows the settings form of `plugin`, filled in with `current`, until it is
+/// submitted with a config `schema` accepts, which is returned, or cancelled.
+pub async fn edit<T: Backend>(
+    backend: &T,
+    plugin: &str,
+    schema: &ConfigSchema,
+    current: &Value,
+) -> Result<Option<Value>> {
+    let mut errors: Vec<SchemaError> = Vec::new();
+    loop {
+        let mut content = Vec::new();
+        if !errors.is_empty() {
+            let list: Vec<String> = errors
+                .iter()
+                .map(|e| format!("- `{}`: {}", e.path, e.message))
+                .collect();
+            content.push(Node::Markdown {
+                md: format!("**Invalid settings**\n\n{}", list.join("\n")),
+            });
+        }
+        content.push(Node::Form(schema.form(plugin, current)));
+        let id = backend
+            .prompt(Prompt {
+                title: Some(format!("Configure {}", plugin)),
+                width: None,
+                height: None,
+                content,
+                ui_schema_version: None,
+            })
+            .await?;
+        let values = loop {
+            match backend.prompt_submission(id.clone()).await? {
+                PromptResponse::Submit { values } => break values,
+                PromptResponse::Cancel => return Ok(None),
+                PromptResponse::State { .. } => continue,
+            }
+        };
+        match schema.apply_form(current, &values) {
+            Ok(config) => return Ok(Some(config)),
+            Err(e) => errors = e,
+        }
+    }
+}
@@ -1,51 +1,173 @@
 use anyhow::{Context, Result, bail};
 use mlua::prelude::LuaSerdeExt;
-use mlua::{Function, Lua, Table, Value as LuaValue};
+use mlua::{Function, HookTriggers, Lua, MultiValue, Table, Value as LuaValue, VmState};
+use std::ffi::OsStr;
 use std::path::PathBuf;
 
 use std::sync::Arc;
-#[cfg(debug_assertions)]
 use std::time::Instant;
 
 use crate::backend::Backend;
+use crate::deps::{events::EventQueue, http::HttpTransport, plugins::Dependencies, task::Tasks};
+use crate::hooks::Hook;
+use crate::process::ProcessPlugin;
 use crate::protocol::{
     PluginAPIRequest, PluginCommand, PluginExecuteContext, PluginExecuteRequest,
     PluginExecuteResponse, PluginInitResponse,
 };
+use crate::schema::ConfigSchema;
+use crate::wasm::WasmPlugin;
 
 pub struct PluginRef {
     pub name: String,
     pub path: PathBuf,
     pub config: Option<serde_json::Value>,
+    pub http: HttpTransport,
+    /// Loaded Lua plugins this one depends on, reachable via `yal.plugins`
+    pub dependencies: Dependencies,
+    /// Checkouts of the Lua packages from the plugin's manifest, searched
+    /// after `vendor/`
+    pub package_roots: Vec<PathBuf>,
 }
 
 pub struct Plugin {
     pub name: String,
     pub commands: Vec<PluginCommand>,
-    pub lua: LuaPlugin,
+    pub config_schema: Option<ConfigSchema>,
+    /// Hooks the plugin implements
+    pub hooks: Vec<Hook>,
+    pub runtime: PluginRuntime,
 }
 
+impl Plugin {
+    /// Calls `hook` with `arg` if the plugin implements it, abandoning it
+    /// once it runs over the hook's budget. Failures are only logged.
+    pub async fn run_hook(&self, hook: Hook, arg: Option<&serde_json::Value>) {
+        if !self.hooks.contains(&hook) {
+            return;
+        }
+        match tokio::time::timeout(hook.budget(), self.runtime.call_hook(hook, arg)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::warn!("Plugin '{}' failed in {}: {:#}", self.name, hook.name(), e),
+            Err(_) => log::warn!(
+                "Plugin '{}' did not finish {} within {:?}",
+                self.name,
+                hook.name(),
+                hook.budget()
+            ),
+        }
+    }
+
+    /// Runs `on_shutdown` and stops the plugin's runtime.
+    pub async fn unload(&self) {
+        self.run_hook(Hook::OnShutdown, None).await;
+        self.runtime.shutdown().await;
+    }
+}
+
+pub enum PluginRuntime {
+    Lua(Arc<LuaPlugin>),
+    Wasm(WasmPlugin),
+    Process(ProcessPlugin),
+}
+
+impl PluginRuntime {
+    pub async fn initialize(&self) -> Result<PluginInitResponse> {
+        match self {
+            PluginRuntime::Lua(plugin) => plugin.initialize().await,
+            PluginRuntime::Wasm(plugin) => plugin.initialize().await,
+            PluginRuntime::Process(plugin) => plugin.initialize().await,
+        }
+    }
+
+    pub async fn run(
+        &self,
+        command: String,
+        context: &PluginExecuteContext,
+        args: Option<serde_json::Value>,
+    ) -> Result<PluginExecuteResponse> {
+        match self {
+            PluginRuntime::Lua(plugin) => plugin.run(command, context, args).await,
+            PluginRuntime::Wasm(plugin) => plugin.run(command, context, args).await,
+            PluginRuntime::Process(plugin) => plugin.run(command, context, args).await,
+        }
+    }
+
+    /// Hooks the plugin implements, given its `init` response. WASM plugins
+    /// have none.
+    pub fn hooks(&self, init: &PluginInitResponse) -> Vec<Hook> {
+        match self {
+            PluginRuntime::Lua(plugin) => plugin.hooks(),
+            PluginRuntime::Wasm(_) => Vec::new(),
+            PluginRuntime::Process(_) => init.hooks.clone(),
+        }
+    }
+
+    pub async fn call_hook(&self, hook: Hook, arg: Option<&serde_json::Value>) -> Result<()> {
+        match self {
+            PluginRuntime::Lua(plugin) => plugin.call_hook(hook, arg).await,
+            PluginRuntime::Wasm(_) => Ok(()),
+            PluginRuntime::Process(plugin) => plugin.call_hook(hook, arg).await,
+        }
+    }
+
+    /// Stops out-of-process plugins, and Lua plugins' tasks and event
+    /// delivery; WASM plugins need nothing.
+    pub async fn shutdown(&self) {
+        match self {
+            PluginRuntime::Lua(plugin) => {
+                plugin.tasks().cancel_all();
+                plugin.events().stop();
+            }
+            PluginRuntime::Wasm(_) => {}
+            PluginRuntime::Process(plugin) => plugin.shutdown().await,
+        }
+    }
+}
+
+/// How often a Lua hook checks whether it has run over its budget, in VM
+/// instructions.
+const HOOK_CHECK_INTERVAL: u32 = 10_000;
+
 pub struct LuaPlugin {
     lua: Lua,
     module: Table,
     execute: Function,
     config: Option<serde_json::Value>,
+    events: EventQueue,
+    tasks: Tasks,
 }
 
 pub struct PluginManifest {
     pub plugin_name: String,
     pub commands: Vec<PluginCommand>,
+    /// Whether the plugin has a settings form
+    pub configurable: bool,
+    pub enabled: bool,
 }
 
 impl LuaPlugin {
     pub fn new<T: Backend>(plugin_ref: PluginRef, backend: Arc<T>) -> Result<Self> {
         let lua = Lua::new();
+        let events = EventQueue::new(&plugin_ref.name);
+        let tasks = Tasks::new(&plugin_ref.name);
 
+        // An entry whose name differs from its checkout is an instance of
+        // the plugin, possibly one of several.
+        let instance = (plugin_ref.path.file_name() != Some(OsStr::new(&plugin_ref.name)))
+            .then_some(plugin_ref.name.as_str());
         crate::deps::install_all(
             &lua,
             crate::deps::InstallOptions {
+                plugin: &plugin_ref.name,
+                instance,
                 vendor_dir: Some(&plugin_ref.path.join("vendor")), // ok if missing
-                http_limits: None,                                 // or Some(HttpLimits { ... })
+                package_roots: &plugin_ref.package_roots,
+                http_limits: None, // or Some(HttpLimits { ... })
+                http_transport: plugin_ref.http.clone(),
+                dependencies: plugin_ref.dependencies.clone(),
+                events: events.clone(),
+                tasks: tasks.clone(),
             },
             backend,
         )?;
@@ -73,26 +195,51 @@ return mod
         );
 
         // Evaluate the bootstrap and capture the returned module table
-        let module: Table = lua
+        let loaded = lua
             .load(&bootstrap)
             .set_name(format!("plugin://{}/{}", plugin_ref.name, "init"))
-            .eval()
-            .with_context(|| format!("Failed to load plugin '{}'", plugin_ref.name))?;
-
-        // Cache `execute`
-        let execute = match module.get("execute")? {
-            mlua::Value::Function(f) => f,
-            _ => bail!("plugin 'execute' is not a function"),
-        };
+            .eval::<Table>()
+            .with_context(|| format!("Failed to load plugin '{}'", plugin_ref.name))
+            .and_then(|module| {
+                // Cache `execute`
+                match module.get("execute")? {
+                    mlua::Value::Function(f) => Ok((module, f)),
+                    _ => bail!("plugin 'execute' is not a function"),
+                }
+            });
+        // Tasks the module spawned while loading go with it.
+        let (module, execute) = loaded.inspect_err(|_| tasks.cancel_all())?;
 
         Ok(Self {
             lua,
             module,
             execute,
             config: plugin_ref.config,
+            events,
+            tasks,
         })
     }
 
+    /// The plugin's Lua state, for tools that drive a plugin directly.
+    pub fn lua(&self) -> &Lua {
+        &self.lua
+    }
+
+    /// Queue of the system events the plugin subscribed to.
+    pub fn events(&self) -> &EventQueue {
+        &self.events
+    }
+
+    /// Tasks the plugin spawned with `yal.task`.
+    pub fn tasks(&self) -> &Tasks {
+        &self.tasks
+    }
+
+    /// The table returned by the plugin's `init.lua`.
+    pub fn module(&self) -> &Table {
+        &self.module
+    }
+
     pub async fn initialize(&self) -> Result<PluginInitResponse> {
         let init_v = self.module.get("init")?;
         match init_v {
@@ -106,6 +253,67 @@ return mod
         }
     }
 
+    /// Calls `exports[function]` from the plugin's module with `args`, for
+    /// plugins that depend on this one.
+    pub async fn call_export(
+        &self,
+        function: &str,
+        args: Vec<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let Some(exports) = self.module.get::<Option<Table>>("exports")? else {
+            bail!("plugin has no exports");
+        };
+        let Some(export) = exports.get::<Option<Function>>(function)? else {
+            bail!("'{}' is not exported", function);
+        };
+        let args = args
+            .iter()
+            .map(|arg| self.lua.to_value(arg))
+            .collect::<mlua::Result<MultiValue>>()?;
+        let ret: LuaValue = export.call_async(args).await?;
+        Ok(self.lua.from_value(ret)?)
+    }
+
+    /// The hooks defined as functions in the plugin's module.
+    pub fn hooks(&self) -> Vec<Hook> {
+        Hook::ALL
+            .into_iter()
+            .filter(|hook| {
+                matches!(
+                    self.module.get::<LuaValue>(hook.name()),
+                    Ok(LuaValue::Function(_))
+                )
+            })
+            .collect()
+    }
+
+    /// Calls `hook` on a coroutine of its own that errors once it runs past
+    /// the hook's budget, so a hook that never yields can't hold the plugin's
+    /// Lua state either.
+    pub async fn call_hook(&self, hook: Hook, arg: Option<&serde_json::Value>) -> Result<()> {
+        let Some(function) = self.module.get::<Option<Function>>(hook.name())? else {
+            return Ok(());
+        };
+        let thread = self.lua.create_thread(function)?;
+        let deadline = Instant::now() + hook.budget();
+        thread.set_hook(
+            HookTriggers::new().every_nth_instruction(HOOK_CHECK_INTERVAL),
+            move |_, _| {
+                if Instant::now() < deadline {
+                    Ok(VmState::Continue)
+                } else {
+                    Err(mlua::Error::runtime(format!(
+                        "{} ran over its budget of {:?}",
+                        hook.name(),
+                        hook.budget()
+                    )))
+                }
+            },
+        )?;
+        let _: LuaValue = thread.into_async(self.lua.to_value(&arg)?)?.await?;
+        Ok(())
+    }
+
     pub async fn run(
         &self,
         command: String,
@@ -0,0 +1,630 @@
+//! Plugins that run as their own executable and speak line-delimited
+//! JSON-RPC 2.0 on stdin/stdout. See `docs/process-plugins.md` for the
+//! protocol.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, Command},
+    sync::oneshot,
+};
+
+use crate::{
+    api::API_VERSION,
+    backend::Backend,
+    hooks::Hook,
+    plugin::PluginRef,
+    protocol::{
+        PluginExecuteContext, PluginExecuteRequest, PluginExecuteResponse, PluginInitRequest,
+        PluginInitResponse,
+    },
+};
+
+/// How long `init` may take, including after a restart.
+const INIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a plugin gets to exit after `shutdown` before it is killed.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+/// A plugin that crashes this often within `RESTART_WINDOW` stays down.
+const MAX_RESTARTS: usize = 3;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const SERVER_ERROR: i64 = -32000;
+
+type Spawn = Box<dyn Fn() -> Result<Connection> + Send + Sync>;
+
+pub struct ProcessPlugin {
+    name: String,
+    config: Option<Value>,
+    spawn: Spawn,
+    connection: Mutex<Option<Arc<Connection>>>,
+    /// Held while the process is restarted or shut down, so only one caller
+    /// does either.
+    lifecycle: tokio::sync::Mutex<()>,
+    /// Set by `shutdown`; the process is not restarted after that.
+    shut_down: AtomicBool,
+    restarts: Mutex<Vec<Instant>>,
+}
+
+impl ProcessPlugin {
+    /// Starts `exec` in the plugin directory. `exec` is resolved against the
+    /// plugin directory when it contains a `/`, and looked up on `PATH`
+    /// otherwise.
+    pub fn new<T: Backend>(
+        plugin_ref: PluginRef,
+        exec: String,
+        args: Vec<String>,
+        backend: Arc<T>,
+    ) -> Result<Self> {
+        let dir = plugin_ref.path;
+        if !dir.is_dir() {
+            bail!("Plugin directory does not exist: {}", dir.display());
+        }
+        let program = if exec.contains('/') {
+            dir.join(&exec)
+        } else {
+            PathBuf::from(&exec)
+        };
+
+        let name = plugin_ref.name;
+        let spawn: Spawn = {
+            let name = name.clone();
+            Box::new(move || Connection::spawn(&name, &program, &args, &dir, backend.clone()))
+        };
+        let connection = spawn()?;
+        Ok(Self {
+            name,
+            config: plugin_ref.config,
+            spawn,
+            connection: Mutex::new(Some(Arc::new(connection))),
+            lifecycle: tokio::sync::Mutex::new(()),
+            shut_down: AtomicBool::new(false),
+            restarts: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub async fn initialize(&self) -> Result<PluginInitResponse> {
+        match self.connect().await? {
+            // A restarted process was just initialised.
+            (_, Some(response)) => Ok(response),
+            (connection, None) => self.init(&connection).await,
+        }
+    }
+
+    pub async fn run(
+        &self,
+        command: String,
+        context: &PluginExecuteContext,
+        args: Option<Value>,
+    ) -> Result<PluginExecuteResponse> {
+        let connection = self.connection().await?;
+        let request = PluginExecuteRequest {
+            command,
+            context,
+            args,
+        };
+        connection.call("execute", &request).await
+    }
+
+    /// Calls the hook's method on the running process. A plugin that is not
+    /// running is not started for a hook.
+    pub async fn call_hook(&self, hook: Hook, arg: Option<&Value>) -> Result<()> {
+        let Some(connection) = self.connection.lock().clone() else {
+            return Ok(());
+        };
+        if connection.closed() {
+            return Ok(());
+        }
+        let _: Value = connection.call(hook.name(), &arg).await?;
+        Ok(())
+    }
+
+    /// Asks the plugin to exit and kills it if it does not within
+    /// `SHUTDOWN_GRACE`. Later calls fail instead of restarting it.
+    pub async fn shutdown(&self) {
+        self.shut_down.store(true, Ordering::SeqCst);
+        // Waits for a restart in progress, whose process would outlive us.
+        let _lifecycle = self.lifecycle.lock().await;
+        let Some(connection) = self.connection.lock().take() else {
+            return;
+        };
+        connection.shutdown(&self.name).await;
+    }
+
+    async fn init(&self, connection: &Connection) -> Result<PluginInitResponse> {
+        let request = PluginInitRequest {
+            config: self.config.clone(),
+            api_version: API_VERSION.to_string(),
+        };
+        tokio::time::timeout(INIT_TIMEOUT, connection.call("init", &request))
+            .await
+            .map_err(|_| anyhow!("init() did not answer within {:?}", INIT_TIMEOUT))?
+    }
+
+    /// The running process, restarted (and re-initialised) if it exited.
+    async fn connection(&self) -> Result<Arc<Connection>> {
+        Ok(self.connect().await?.0)
+    }
+
+    /// `connection`, along with the `init` response if the process had to be
+    /// restarted.
+    async fn connect(&self) -> Result<(Arc<Connection>, Option<PluginInitResponse>)> {
+        if let Some(connection) = self.running()? {
+            return Ok((connection, None));
+        }
+
+        let _lifecycle = self.lifecycle.lock().await;
+        // Another caller may have restarted it while we waited.
+        if let Some(connection) = self.running()? {
+            return Ok((connection, None));
+        }
+        {
+            let mut restarts = self.restarts.lock();
+            restarts.retain(|at| at.elapsed() < RESTART_WINDOW);
+            if restarts.len() >= MAX_RESTARTS {
+                bail!(
+                    "Plugin '{}' exited {} times within {:?}, not restarting it",
+                    self.name,
+                    restarts.len(),
+                    RESTART_WINDOW
+                );
+            }
+            restarts.push(Instant::now());
+        }
+        log::warn!("Plugin '{}' is not running, restarting it", self.name);
+
+        let connection = Arc::new((self.spawn)()?);
+        let response = self
+            .init(&connection)
+            .await
+            .with_context(|| format!("Failed restarting plugin '{}'", self.name))?;
+        *self.connection.lock() = Some(connection.clone());
+        Ok((connection, Some(response)))
+    }
+
+    /// The process if it is running. Fails once the plugin was shut down.
+    fn running(&self) -> Result<Option<Arc<Connection>>> {
+        if self.shut_down.load(Ordering::SeqCst) {
+            bail!("Plugin '{}' was shut down", self.name);
+        }
+        Ok(self
+            .connection
+            .lock()
+            .clone()
+            .filter(|connection| !connection.closed()))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Any message the plugin writes: a response to one of our calls, or a
+/// request (with `id`) or notification (without) of its own.
+#[derive(Deserialize)]
+struct Incoming {
+    id: Option<Value>,
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+struct Connection {
+    child: tokio::sync::Mutex<Child>,
+    stdin: Arc<tokio::sync::Mutex<Option<ChildStdin>>>,
+    pending: Pending,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+}
+
+impl Connection {
+    fn spawn<T: Backend>(
+        name: &str,
+        program: &Path,
+        args: &[String],
+        dir: &Path,
+        backend: Arc<T>,
+    ) -> Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed starting {}", program.display()))?;
+
+        let stdin = Arc::new(tokio::sync::Mutex::new(child.stdin.take()));
+        let stdout = child.stdout.take().context("plugin stdout is not piped")?;
+        let stderr = child.stderr.take().context("plugin stderr is not piped")?;
+        let pending: Pending = Arc::default();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let plugin = name.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                log::warn!("[{}] {}", plugin, line);
+            }
+        });
+
+        let plugin = name.to_string();
+        let (stdin_r, pending_r, closed_r) = (stdin.clone(), pending.clone(), closed.clone());
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Incoming>(&line) {
+                    Ok(message) => dispatch(&plugin, message, &stdin_r, &pending_r, &backend),
+                    Err(e) => log::warn!("[{}] ignoring invalid message: {} ({})", plugin, line, e),
+                }
+            }
+            log::info!("Plugin '{}' closed its output", plugin);
+            closed_r.store(true, Ordering::SeqCst);
+            for (_, tx) in pending_r.lock().drain() {
+                let _ = tx.send(Err(format!("plugin '{}' exited", plugin)));
+            }
+        });
+
+        Ok(Self {
+            child: tokio::sync::Mutex::new(child),
+            stdin,
+            pending,
+            next_id: AtomicU64::new(1),
+            closed,
+        })
+    }
+
+    fn closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    async fn call<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: &P) -> Result<R> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+        // The reader sets `closed` before failing what is pending, so a call
+        // that raced with the exit is caught here instead of waiting forever.
+        if self.closed() {
+            self.pending.lock().remove(&id);
+            bail!("plugin exited");
+        }
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Err(e) = write_message(&self.stdin, &message).await {
+            self.pending.lock().remove(&id);
+            return Err(e);
+        }
+        let result = rx
+            .await
+            .map_err(|_| anyhow!("plugin exited"))?
+            .map_err(|e| anyhow!(e))?;
+        serde_json::from_value(result).with_context(|| format!("invalid {} response", method))
+    }
+
+    async fn shutdown(&self, name: &str) {
+        let message = json!({ "jsonrpc": "2.0", "method": "shutdown" });
+        let _ = write_message(&self.stdin, &message).await;
+        // Closing stdin tells plugins that ignore `shutdown` to stop reading.
+        self.stdin.lock().await.take();
+
+        let mut child = self.child.lock().await;
+        match tokio::time::timeout(SHUTDOWN_GRACE, child.wait()).await {
+            Ok(_) => log::info!("Plugin '{}' exited", name),
+            Err(_) => {
+                log::warn!("Plugin '{}' did not exit in time, killing it", name);
+                let _ = child.kill().await;
+            }
+        }
+    }
+}
+
+fn dispatch<T: Backend>(
+    plugin: &str,
+    message: Incoming,
+    stdin: &Arc<tokio::sync::Mutex<Option<ChildStdin>>>,
+    pending: &Pending,
+    backend: &Arc<T>,
+) {
+    match (message.method, message.id) {
+        (Some(method), Some(id)) => {
+            // Host calls such as `prompt_submission` wait on the user, so they
+            // must not hold up the reader.
+            let (stdin, backend) = (stdin.clone(), backend.clone());
+            tokio::spawn(async move {
+                let reply = match host_call(backend.as_ref(), &method, message.params).await {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+                };
+                let _ = write_message(&stdin, &reply).await;
+            });
+        }
+        (Some(method), None) => match method.as_str() {
+            "log" => log::info!("[{}] {}", plugin, message.params),
+            _ => log::debug!("[{}] ignoring notification '{}'", plugin, method),
+        },
+        (None, Some(id)) => {
+            let Some(tx) = id.as_u64().and_then(|id| pending.lock().remove(&id)) else {
+                log::warn!("[{}] response to unknown request {}", plugin, id);
+                return;
+            };
+            let result = match message.error {
+                Some(error) => Err(error.message),
+                None => Ok(message.result.unwrap_or(Value::Null)),
+            };
+            let _ = tx.send(result);
+        }
+        (None, None) => log::warn!("[{}] message without method or id", plugin),
+    }
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct VisibilityParams {
+    visible: bool,
+}
+
+/// Runs a `Backend` call on behalf of the plugin.
+async fn host_call<T: Backend>(
+    backend: &T,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    fn params_as<P: DeserializeOwned>(params: Value) -> Result<P, RpcError> {
+        serde_json::from_value(params).map_err(|e| RpcError {
+            code: SERVER_ERROR,
+            message: format!("invalid params: {}", e),
+        })
+    }
+
+    let result = match method {
+        "prompt" => backend
+            .prompt(params_as(params)?)
+            .await
+            .map(|id| json!({ "id": id })),
+        "prompt_state" => backend
+            .prompt_state(params_as::<IdParams>(params)?.id)
+            .await
+            .map(|r| json!(r)),
+        "prompt_submission" => backend
+            .prompt_submission(params_as::<IdParams>(params)?.id)
+            .await
+            .map(|r| json!(r)),
+        "prompt_cancel" => backend
+            .prompt_cancel(params_as::<IdParams>(params)?.id)
+            .await
+            .map(|()| Value::Null),
+        "set_visibility" => backend
+            .set_visibility(params_as::<VisibilityParams>(params)?.visible)
+            .await
+            .map(|()| Value::Null),
+        _ => {
+            return Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method '{}'", method),
+            });
+        }
+    };
+    result.map_err(|e| RpcError {
+        code: SERVER_ERROR,
+        message: format!("{:#}", e),
+    })
+}
+
+async fn write_message(
+    stdin: &tokio::sync::Mutex<Option<ChildStdin>>,
+    message: &Value,
+) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    let mut stdin = stdin.lock().await;
+    let stdin = stdin.as_mut().context("plugin stdin is closed")?;
+    stdin.write_all(&line).await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Display;
+    use yal_core::{Prompt, PromptResponse};
+
+    #[derive(Clone, Default)]
+    struct RecordingBackend {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Backend for RecordingBackend {
+        async fn prompt(&self, prompt: Prompt) -> Result<String> {
+            self.calls
+                .lock()
+                .push(format!("prompt {}", prompt.title.unwrap_or_default()));
+            Ok("p1".to_string())
+        }
+
+        async fn prompt_state(&self, _id: String) -> Result<PromptResponse> {
+            bail!("not scripted")
+        }
+
+        async fn prompt_submission(&self, id: String) -> Result<PromptResponse> {
+            self.calls.lock().push(format!("submission {}", id));
+            Ok(PromptResponse::Submit {
+                values: json!({ "name": "Ada" }),
+            })
+        }
+
+        async fn prompt_cancel(&self, id: String) -> Result<()> {
+            self.calls.lock().push(format!("cancel {}", id));
+            Ok(())
+        }
+
+        async fn set_visibility(&self, visible: bool) -> Result<()> {
+            self.calls.lock().push(format!("visible {}", visible));
+            Ok(())
+        }
+    }
+
+    fn context() -> PluginExecuteContext {
+        let display = || Display {
+            display_id: "1".to_string(),
+            current_space_id: 1,
+        };
+        PluginExecuteContext {
+            windows: Vec::new(),
+            displays: vec![display()],
+            current_display: display(),
+        }
+    }
+
+    fn start(backend: &RecordingBackend) -> ProcessPlugin {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/echo-plugin");
+        ProcessPlugin::new(
+            PluginRef {
+                name: "echo".to_string(),
+                path: dir,
+                config: Some(json!({ "greeting": "Hi" })),
+                http: Default::default(),
+                dependencies: Default::default(),
+                package_roots: Vec::new(),
+            },
+            "./plugin.py".to_string(),
+            Vec::new(),
+            Arc::new(backend.clone()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn runs_commands_with_host_callbacks() {
+        let backend = RecordingBackend::default();
+        let plugin = start(&backend);
+
+        let info = plugin.initialize().await.unwrap();
+        assert_eq!(info.name, "echo");
+        assert_eq!(info.commands.len(), 2);
+
+        let res = plugin
+            .run("greet".to_string(), &context(), None)
+            .await
+            .unwrap();
+        assert!(res.hide);
+        assert_eq!(
+            *backend.calls.lock(),
+            vec!["prompt Hi", "submission p1", "visible false"]
+        );
+
+        let err = plugin
+            .run("nope".to_string(), &context(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown command nope"), "{}", err);
+
+        plugin.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn calls_declared_hooks() {
+        let backend = RecordingBackend::default();
+        let plugin = start(&backend);
+        let info = plugin.initialize().await.unwrap();
+        assert_eq!(info.hooks, vec![Hook::OnQueryChange]);
+
+        plugin
+            .call_hook(Hook::OnQueryChange, Some(&json!("Ada")))
+            .await
+            .unwrap();
+        plugin
+            .run("greet".to_string(), &context(), None)
+            .await
+            .unwrap();
+        assert_eq!(backend.calls.lock()[0], "prompt Hi Ada");
+
+        plugin.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn restarts_after_a_crash() {
+        let backend = RecordingBackend::default();
+        let plugin = start(&backend);
+        plugin.initialize().await.unwrap();
+
+        assert!(
+            plugin
+                .run("crash".to_string(), &context(), None)
+                .await
+                .is_err()
+        );
+        // The next call starts a fresh process and runs `init` on it first.
+        let res = plugin
+            .run("greet".to_string(), &context(), None)
+            .await
+            .unwrap();
+        assert!(res.hide);
+
+        for _ in 0..MAX_RESTARTS - 1 {
+            let _ = plugin.run("crash".to_string(), &context(), None).await;
+            let _ = plugin.run("greet".to_string(), &context(), None).await;
+        }
+        let _ = plugin.run("crash".to_string(), &context(), None).await;
+        let err = plugin
+            .run("greet".to_string(), &context(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not restarting"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn restarts_once_and_not_after_shutdown() {
+        let backend = RecordingBackend::default();
+        let plugin = start(&backend);
+        plugin.initialize().await.unwrap();
+        let _ = plugin.run("crash".to_string(), &context(), None).await;
+
+        // Restarting runs `init`, which `initialize` must not repeat.
+        let info = plugin.initialize().await.unwrap();
+        assert_eq!(info.description.as_deref(), Some("init #1"));
+        assert_eq!(plugin.restarts.lock().len(), 1);
+        let _ = plugin.run("crash".to_string(), &context(), None).await;
+
+        // The fixture handles one call at a time, and `init` needs no host calls.
+        let (a, b) = tokio::join!(plugin.initialize(), plugin.initialize());
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(plugin.restarts.lock().len(), 2);
+
+        plugin.shutdown().await;
+        let err = plugin
+            .run("greet".to_string(), &context(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("shut down"), "{}", err);
+        assert_eq!(plugin.restarts.lock().len(), 2);
+    }
+}
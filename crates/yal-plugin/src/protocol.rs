@@ -2,6 +2,8 @@ use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
 use yal_core::{Prompt, PromptResponse};
 
+use crate::{hooks::Hook, schema::ConfigSchema};
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PluginCommand {
     pub name: String,
@@ -13,6 +15,8 @@ pub struct PluginCommand {
 #[derive(Serialize, Deserialize)]
 pub struct PluginInitRequest {
     pub config: Option<serde_json::Value>,
+    /// The host's plugin API version (`crate::api::API_VERSION`).
+    pub api_version: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -22,9 +26,19 @@ pub struct PluginInitResponse {
     pub version: String,
     pub author: Option<String>,
     pub commands: Vec<PluginCommand>,
+    /// Semver range of plugin API versions the plugin works with, e.g. `"1.1"`.
+    #[serde(default)]
+    pub api_version: Option<String>,
+    /// What `config` may contain, for plugins without one in `manifest.lua`
+    #[serde(default)]
+    pub config_schema: Option<ConfigSchema>,
+    /// Hooks a process plugin handles; Lua plugins are asked for the hooks
+    /// their module defines instead
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct PluginExecuteResponse {
     pub hide: bool,
 }
@@ -0,0 +1,467 @@
+//! Descriptions of plugin configs, in a small subset of JSON Schema.
+//!
+//! A plugin publishes one as `config_schema` in its `manifest.lua` or from
+//! `init`. The host checks `config` from `plugins.lua` against it, fills in
+//! defaults, and renders it as the plugin's settings form.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use yal_core::{Field, Form, OptionKV, SelectField, SliderField, TextField};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Object,
+    Array,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ConfigSchema {
+    /// Any type if missing
+    #[serde(rename = "type")]
+    pub kind: Option<SchemaType>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Used when the value is missing
+    pub default: Option<Value>,
+    /// The only values allowed
+    #[serde(rename = "enum")]
+    pub choices: Option<Vec<Value>>,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    /// Known keys of an object; other keys are allowed and left alone
+    #[serde(default)]
+    pub properties: BTreeMap<String, ConfigSchema>,
+    /// Keys of an object that must be present (after defaults)
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Schema of every element of an array
+    pub items: Option<Box<ConfigSchema>>,
+}
+
+/// A value that does not match its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// Where the value is, e.g. `config.servers[1].url`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Why a plugin was not loaded: its config does not match its schema.
+#[derive(Debug, Clone)]
+pub struct InvalidConfig {
+    pub errors: Vec<SchemaError>,
+}
+
+impl fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self.errors.iter().map(|e| e.to_string()).collect();
+        write!(f, "invalid config: {}", errors.join("; "))
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
+
+impl ConfigSchema {
+    /// Checks `value` and returns it with defaults filled in, or every
+    /// mismatch found.
+    pub fn apply(&self, value: Option<Value>) -> Result<Value, Vec<SchemaError>> {
+        let mut errors = Vec::new();
+        let value = self.check("config".to_string(), value, &mut errors);
+        if errors.is_empty() {
+            Ok(value.unwrap_or(Value::Null))
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check(
+        &self,
+        path: String,
+        value: Option<Value>,
+        errors: &mut Vec<SchemaError>,
+    ) -> Option<Value> {
+        let value = value
+            .filter(|v| !v.is_null())
+            .or_else(|| self.default.clone())
+            .or_else(|| {
+                // So that defaults of nested properties apply.
+                (self.kind == Some(SchemaType::Object) && !self.properties.is_empty())
+                    .then(|| Value::Object(Map::new()))
+            })?;
+        let mut error = |message: String| {
+            errors.push(SchemaError {
+                path: path.clone(),
+                message,
+            })
+        };
+
+        if let Some(kind) = self.kind
+            && !matches_type(kind, &value)
+        {
+            error(format!(
+                "expected {}, got {}",
+                type_name(kind),
+                describe(&value)
+            ));
+            return Some(value);
+        }
+        if let Some(choices) = &self.choices
+            && !choices.contains(&value)
+        {
+            let choices: Vec<String> = choices.iter().map(Value::to_string).collect();
+            error(format!("must be one of {}", choices.join(", ")));
+        }
+        if let Some(n) = value.as_f64() {
+            if let Some(min) = self.minimum
+                && n < min
+            {
+                error(format!("must be at least {}", min));
+            }
+            if let Some(max) = self.maximum
+                && n > max
+            {
+                error(format!("must be at most {}", max));
+            }
+        }
+
+        match value {
+            Value::Object(mut object) => {
+                for (key, schema) in &self.properties {
+                    let child =
+                        schema.check(format!("{}.{}", path, key), object.remove(key), errors);
+                    if let Some(child) = child {
+                        object.insert(key.clone(), child);
+                    }
+                }
+                for key in &self.required {
+                    if !object.contains_key(key) {
+                        errors.push(SchemaError {
+                            path: format!("{}.{}", path, key),
+                            message: "is required".to_string(),
+                        });
+                    }
+                }
+                Some(Value::Object(object))
+            }
+            Value::Array(items) => match &self.items {
+                Some(schema) => Some(Value::Array(
+                    items
+                        .into_iter()
+                        .enumerate()
+                        .filter_map(|(i, item)| {
+                            schema.check(format!("{}[{}]", path, i + 1), Some(item), errors)
+                        })
+                        .collect(),
+                )),
+                None => Some(Value::Array(items)),
+            },
+            value => Some(value),
+        }
+    }
+
+    /// The settings form for a config currently equal to `current`. Nested
+    /// objects are flattened into fields named `a.b`.
+    pub fn form(&self, name: &str, current: &Value) -> Form {
+        let mut fields = Vec::new();
+        for (path, schema) in self.leaves() {
+            let value = lookup(current, &path);
+            fields.push(schema.field(&path, value));
+        }
+        Form {
+            name: Some(name.to_string()),
+            fields,
+        }
+    }
+
+    /// Applies the values submitted from [`ConfigSchema::form`] to `current`
+    /// and checks the result. Empty text fields keep their current value.
+    pub fn apply_form(
+        &self,
+        current: &Value,
+        submitted: &Value,
+    ) -> Result<Value, Vec<SchemaError>> {
+        let mut config = current.clone();
+        let mut errors = Vec::new();
+        for (path, schema) in self.leaves() {
+            let name = path.join(".");
+            let Some(value) = submitted.get(&name) else {
+                continue;
+            };
+            let value = match (value, schema.kind) {
+                (Value::String(text), _) if text.is_empty() => continue,
+                (Value::String(text), Some(SchemaType::String)) => Value::String(text.clone()),
+                (Value::String(text), _) => match serde_json::from_str(text) {
+                    Ok(value) => value,
+                    Err(_) if schema.kind.is_none() => Value::String(text.clone()),
+                    Err(_) => {
+                        errors.push(SchemaError {
+                            path: format!("config.{}", name),
+                            message: format!(
+                                "'{}' is not a valid {}",
+                                text,
+                                type_name(schema.kind.unwrap())
+                            ),
+                        });
+                        continue;
+                    }
+                },
+                (Value::Number(n), Some(SchemaType::Integer)) => match n.as_f64() {
+                    Some(f) if f.fract() == 0.0 => Value::from(f as i64),
+                    _ => Value::Number(n.clone()),
+                },
+                (value, _) => value.clone(),
+            };
+            insert(&mut config, &path, value);
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        self.apply(Some(config))
+    }
+
+    /// Schemas that become form fields, with their paths. The root is a
+    /// single field named `config` unless it is an object with properties.
+    fn leaves(&self) -> Vec<(Vec<String>, &ConfigSchema)> {
+        fn walk<'a>(
+            schema: &'a ConfigSchema,
+            path: Vec<String>,
+            out: &mut Vec<(Vec<String>, &'a ConfigSchema)>,
+        ) {
+            if schema.properties.is_empty() {
+                out.push((path, schema));
+                return;
+            }
+            for (key, child) in &schema.properties {
+                let mut child_path = path.clone();
+                child_path.push(key.clone());
+                walk(child, child_path, out);
+            }
+        }
+        let mut out = Vec::new();
+        if self.properties.is_empty() {
+            out.push((vec!["config".to_string()], self));
+        } else {
+            walk(self, Vec::new(), &mut out);
+        }
+        out
+    }
+
+    fn field(&self, path: &[String], current: Option<&Value>) -> Field {
+        let name = path.join(".");
+        let mut label = self.title.clone().unwrap_or_else(|| name.clone());
+        if let Some(description) = &self.description {
+            label = format!("{} ({})", label, description);
+        }
+
+        let choices = match (&self.choices, self.kind) {
+            (Some(choices), _) => Some(choices.clone()),
+            (None, Some(SchemaType::Boolean)) => Some(vec![Value::Bool(true), Value::Bool(false)]),
+            _ => None,
+        };
+        if let Some(mut choices) = choices {
+            // The frontend preselects the first option.
+            if let Some(i) = current.and_then(|c| choices.iter().position(|v| v == c)) {
+                let selected = choices.remove(i);
+                choices.insert(0, selected);
+            }
+            return Field::Select(SelectField {
+                name,
+                label: Some(label),
+                options: choices
+                    .into_iter()
+                    .map(|value| OptionKV {
+                        label: display(&value),
+                        value,
+                    })
+                    .collect(),
+            });
+        }
+
+        if let (Some(SchemaType::Number | SchemaType::Integer), Some(min), Some(max)) =
+            (self.kind, self.minimum, self.maximum)
+        {
+            let step = if self.kind == Some(SchemaType::Integer) {
+                1.0
+            } else {
+                (max - min) / 100.0
+            };
+            return Field::Slider(SliderField {
+                name,
+                label: Some(label),
+                min,
+                max,
+                step,
+                value: current.and_then(Value::as_f64),
+                show_value: true,
+            });
+        }
+
+        Field::Text(TextField {
+            name,
+            label: Some(label),
+            placeholder: current.map(display),
+            max_length: None,
+        })
+    }
+}
+
+fn matches_type(kind: SchemaType, value: &Value) -> bool {
+    match kind {
+        SchemaType::String => value.is_string(),
+        SchemaType::Number => value.is_number(),
+        SchemaType::Integer => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        SchemaType::Boolean => value.is_boolean(),
+        SchemaType::Object => value.is_object(),
+        SchemaType::Array => value.is_array(),
+    }
+}
+
+fn type_name(kind: SchemaType) -> &'static str {
+    match kind {
+        SchemaType::String => "a string",
+        SchemaType::Number => "a number",
+        SchemaType::Integer => "an integer",
+        SchemaType::Boolean => "a boolean",
+        SchemaType::Object => "a table",
+        SchemaType::Array => "a list",
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "nothing".to_string(),
+        Value::Object(_) => "a table".to_string(),
+        Value::Array(_) => "a list".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Strings without quotes, everything else as JSON.
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    if path == ["config"] && !value.is_object() {
+        return Some(value);
+    }
+    path.iter().try_fold(value, |value, key| value.get(key))
+}
+
+fn insert(config: &mut Value, path: &[String], value: Value) {
+    if path == ["config"] && !config.is_object() {
+        *config = value;
+        return;
+    }
+    let mut target = config;
+    for key in path {
+        if !target.is_object() {
+            *target = Value::Object(Map::new());
+        }
+        target = target
+            .as_object_mut()
+            .expect("just made an object")
+            .entry(key.clone())
+            .or_insert(Value::Null);
+    }
+    *target = value;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> ConfigSchema {
+        serde_json::from_value(json!({
+            "type": "object",
+            "required": ["token"],
+            "properties": {
+                "token": { "type": "string" },
+                "server": {
+                    "type": "object",
+                    "properties": {
+                        "url": { "type": "string", "default": "https://jira.example.com" },
+                        "port": { "type": "integer", "minimum": 1, "maximum": 65535 },
+                    },
+                },
+                "mode": { "enum": ["fast", "safe"], "default": "safe" },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn fills_defaults_and_reports_paths() {
+        let config = schema().apply(Some(json!({ "token": "t" }))).unwrap();
+        assert_eq!(
+            config,
+            json!({
+                "token": "t",
+                "server": { "url": "https://jira.example.com" },
+                "mode": "safe",
+            })
+        );
+
+        let errors = schema()
+            .apply(Some(json!({ "server": { "port": "80" }, "mode": "slow" })))
+            .unwrap_err();
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "config.mode: must be one of \"fast\", \"safe\"",
+                "config.server.port: expected an integer, got \"80\"",
+                "config.token: is required",
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_through_the_form() {
+        let current = schema().apply(Some(json!({ "token": "t" }))).unwrap();
+        let form = schema().form("jira", &current);
+        let names: Vec<_> = form
+            .fields
+            .iter()
+            .map(|f| match f {
+                Field::Text(f) => f.name.as_str(),
+                Field::Select(f) => f.name.as_str(),
+                Field::Slider(f) => f.name.as_str(),
+            })
+            .collect();
+        assert_eq!(names, vec!["mode", "server.port", "server.url", "token"]);
+
+        let submitted = json!({
+            "mode": "fast",
+            "server.port": 8080.0,
+            "server.url": "",
+            "token": "",
+        });
+        assert_eq!(
+            schema().apply_form(&current, &submitted).unwrap(),
+            json!({
+                "token": "t",
+                "server": { "url": "https://jira.example.com", "port": 8080 },
+                "mode": "fast",
+            })
+        );
+    }
+}
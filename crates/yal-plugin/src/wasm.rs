@@ -0,0 +1,492 @@
+//! Plugins compiled to WebAssembly components implementing `wit/plugin.wit`.
+//!
+//! Each plugin gets its own store, so it can only touch its own memory. Host
+//! functions other than logging fail unless the plugin's `capabilities`
+//! grant them, and there is no WASI: no files, sockets or environment. Every
+//! call runs with a fixed fuel budget and memory cap, so a runaway plugin
+//! traps instead of hanging the app.
+
+use std::{
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use anyhow::{Context, Result, anyhow};
+use futures::future::BoxFuture;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use wasmtime::{
+    Config, Engine, Store, StoreLimits, StoreLimitsBuilder,
+    component::{Component, HasSelf, Linker},
+};
+use yal_core::{Prompt, PromptResponse};
+
+use crate::{
+    api::API_VERSION,
+    backend::{Backend, RequestId},
+    deps::{
+        db,
+        http::{HttpEnv, HttpLimits},
+    },
+    manager::config::Capability,
+    plugin::PluginRef,
+    protocol::{
+        PluginExecuteContext, PluginExecuteRequest, PluginExecuteResponse, PluginInitResponse,
+    },
+};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        world: "plugin",
+        path: "wit",
+        imports: { default: async },
+        exports: { default: async },
+    });
+}
+
+use bindings::yal::plugin::{
+    db as wit_db, http as wit_http, log as wit_log, types, ui as wit_ui, version as wit_version,
+};
+
+/// File a WASM plugin's directory must contain.
+pub const COMPONENT_FILE: &str = "plugin.wasm";
+
+/// Instructions a single `init` or `execute` call may run.
+const FUEL_PER_CALL: u64 = 5_000_000_000;
+/// How often (in fuel) a running call yields to the async runtime.
+const FUEL_YIELD_INTERVAL: u64 = 10_000_000;
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config
+            .async_support(true)
+            .wasm_component_model(true)
+            .consume_fuel(true);
+        Engine::new(&config).expect("WASM engine configuration is valid")
+    })
+}
+
+/// Object-safe view of a `Backend`, so the store's state does not need to
+/// be generic over it.
+trait UiHost: Send + Sync {
+    fn prompt(&self, prompt: Prompt) -> BoxFuture<'_, Result<RequestId>>;
+    fn prompt_state(&self, id: RequestId) -> BoxFuture<'_, Result<PromptResponse>>;
+    fn prompt_submission(&self, id: RequestId) -> BoxFuture<'_, Result<PromptResponse>>;
+    fn prompt_cancel(&self, id: RequestId) -> BoxFuture<'_, Result<()>>;
+    fn set_visibility(&self, visible: bool) -> BoxFuture<'_, Result<()>>;
+}
+
+impl<T: Backend> UiHost for T {
+    fn prompt(&self, prompt: Prompt) -> BoxFuture<'_, Result<RequestId>> {
+        Box::pin(Backend::prompt(self, prompt))
+    }
+
+    fn prompt_state(&self, id: RequestId) -> BoxFuture<'_, Result<PromptResponse>> {
+        Box::pin(Backend::prompt_state(self, id))
+    }
+
+    fn prompt_submission(&self, id: RequestId) -> BoxFuture<'_, Result<PromptResponse>> {
+        Box::pin(Backend::prompt_submission(self, id))
+    }
+
+    fn prompt_cancel(&self, id: RequestId) -> BoxFuture<'_, Result<()>> {
+        Box::pin(Backend::prompt_cancel(self, id))
+    }
+
+    fn set_visibility(&self, visible: bool) -> BoxFuture<'_, Result<()>> {
+        Box::pin(Backend::set_visibility(self, visible))
+    }
+}
+
+struct HostState {
+    plugin: String,
+    capabilities: Vec<Capability>,
+    backend: Arc<dyn UiHost>,
+    http: HttpEnv,
+    /// The plugin's `yal.db` namespace, loaded on first use.
+    db: Option<Map<String, Value>>,
+    limits: StoreLimits,
+}
+
+impl HostState {
+    fn require(&self, capability: Capability) -> Result<(), String> {
+        if self.capabilities.contains(&capability) {
+            Ok(())
+        } else {
+            Err(format!(
+                "plugin '{}' was not granted the '{}' capability",
+                self.plugin,
+                capability.as_str()
+            ))
+        }
+    }
+
+    fn db(&mut self) -> Result<&mut Map<String, Value>, String> {
+        self.require(Capability::Db)?;
+        let path = db::db_path_for(&self.plugin);
+        Ok(self.db.get_or_insert_with(|| db::load_map_from(&path)))
+    }
+
+    fn save_db(&self) -> Result<(), String> {
+        let map = self.db.as_ref().ok_or("db is not open")?;
+        db::atomic_write_json(&db::db_path_for(&self.plugin), map).map_err(|e| e.to_string())
+    }
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+impl types::Host for HostState {}
+
+impl wit_log::Host for HostState {
+    async fn debug(&mut self, message: String) {
+        log::debug!("[{}] {}", self.plugin, message);
+    }
+
+    async fn info(&mut self, message: String) {
+        log::info!("[{}] {}", self.plugin, message);
+    }
+
+    async fn warn(&mut self, message: String) {
+        log::warn!("[{}] {}", self.plugin, message);
+    }
+
+    async fn error(&mut self, message: String) {
+        log::error!("[{}] {}", self.plugin, message);
+    }
+}
+
+impl wit_version::Host for HostState {
+    async fn api(&mut self) -> String {
+        API_VERSION.to_string()
+    }
+}
+
+impl wit_http::Host for HostState {
+    async fn fetch(&mut self, request: wit_http::Request) -> Result<wit_http::Response, String> {
+        self.require(Capability::Http)?;
+        let method = Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+        let mut opts = self.http.request_opts(method, request.url);
+        opts.headers = request.headers.into_iter().collect();
+        opts.query = request.query.into_iter().collect();
+        opts.body_bytes = request.body;
+        if let Some(timeout_ms) = request.timeout_ms {
+            opts.timeout_ms = timeout_ms;
+        }
+
+        let (status, headers, body) = self
+            .http
+            .execute(opts)
+            .await
+            .map_err(|e| format!("{:#}", e))?;
+        Ok(wit_http::Response {
+            status: status.as_u16(),
+            headers: headers.into_iter().collect(),
+            body,
+        })
+    }
+
+    async fn set_default_header(&mut self, name: String, value: String) -> Result<(), String> {
+        self.require(Capability::Http)?;
+        self.http.set_default_header(name, value);
+        Ok(())
+    }
+}
+
+impl wit_db::Host for HostState {
+    async fn get(&mut self, key: String) -> Result<Option<String>, String> {
+        self.db()?.get(&key).map(to_json).transpose()
+    }
+
+    async fn set(&mut self, key: String, value: String) -> Result<(), String> {
+        let value: Value = from_json(&value)?;
+        self.db()?.insert(key, value);
+        self.save_db()
+    }
+
+    async fn delete(&mut self, key: String) -> Result<(), String> {
+        self.db()?.remove(&key);
+        self.save_db()
+    }
+
+    async fn keys(&mut self) -> Result<Vec<String>, String> {
+        Ok(self.db()?.keys().cloned().collect())
+    }
+}
+
+impl wit_ui::Host for HostState {
+    async fn prompt(&mut self, prompt: String) -> Result<String, String> {
+        self.require(Capability::Ui)?;
+        let prompt: Prompt = from_json(&prompt)?;
+        self.backend
+            .prompt(prompt)
+            .await
+            .map_err(|e| format!("{:#}", e))
+    }
+
+    async fn prompt_state(&mut self, id: String) -> Result<String, String> {
+        self.require(Capability::Ui)?;
+        let response = self
+            .backend
+            .prompt_state(id)
+            .await
+            .map_err(|e| format!("{:#}", e))?;
+        to_json(&response)
+    }
+
+    async fn prompt_submission(&mut self, id: String) -> Result<String, String> {
+        self.require(Capability::Ui)?;
+        let response = self
+            .backend
+            .prompt_submission(id)
+            .await
+            .map_err(|e| format!("{:#}", e))?;
+        to_json(&response)
+    }
+
+    async fn prompt_cancel(&mut self, id: String) -> Result<(), String> {
+        self.require(Capability::Ui)?;
+        self.backend
+            .prompt_cancel(id)
+            .await
+            .map_err(|e| format!("{:#}", e))
+    }
+
+    async fn set_visibility(&mut self, visible: bool) -> Result<(), String> {
+        self.require(Capability::Ui)?;
+        self.backend
+            .set_visibility(visible)
+            .await
+            .map_err(|e| format!("{:#}", e))
+    }
+}
+
+pub struct WasmPlugin {
+    config: Option<Value>,
+    store: tokio::sync::Mutex<Store<HostState>>,
+    instance: bindings::Plugin,
+    /// Fuel each `init` and `execute` call starts with
+    fuel_per_call: u64,
+}
+
+impl WasmPlugin {
+    pub async fn new<T: Backend>(
+        plugin_ref: PluginRef,
+        capabilities: Vec<Capability>,
+        backend: Arc<T>,
+    ) -> Result<Self> {
+        let path = plugin_ref.path.join(COMPONENT_FILE);
+        let component = Component::from_file(engine(), &path)
+            .with_context(|| format!("Failed compiling {}", path.display()))?;
+
+        let mut linker = Linker::new(engine());
+        bindings::Plugin::add_to_linker::<HostState, HasSelf<HostState>>(&mut linker, |s| s)?;
+
+        let state = HostState {
+            plugin: plugin_ref.name,
+            capabilities,
+            backend,
+            http: HttpEnv::with_transport(HttpLimits::default(), plugin_ref.http)?,
+            db: None,
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .build(),
+        };
+        let mut store = Store::new(engine(), state);
+        store.limiter(|s| &mut s.limits);
+        store.fuel_async_yield_interval(Some(FUEL_YIELD_INTERVAL))?;
+        store.set_fuel(FUEL_PER_CALL)?;
+
+        let instance = bindings::Plugin::instantiate_async(&mut store, &component, &linker)
+            .await
+            .context("Failed instantiating plugin")?;
+        Ok(Self {
+            config: plugin_ref.config,
+            store: tokio::sync::Mutex::new(store),
+            instance,
+            fuel_per_call: FUEL_PER_CALL,
+        })
+    }
+
+    pub async fn initialize(&self) -> Result<PluginInitResponse> {
+        let config = self
+            .config
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let mut store = self.store.lock().await;
+        store.set_fuel(self.fuel_per_call)?;
+        let response = self
+            .instance
+            .call_init(&mut *store, config.as_ref())
+            .await?
+            .map_err(|e| anyhow!(e))?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    pub async fn run(
+        &self,
+        command: String,
+        context: &PluginExecuteContext,
+        args: Option<Value>,
+    ) -> Result<PluginExecuteResponse> {
+        let request = serde_json::to_string(&PluginExecuteRequest {
+            command,
+            context,
+            args,
+        })?;
+        let mut store = self.store.lock().await;
+        store.set_fuel(self.fuel_per_call)?;
+        let response = self
+            .instance
+            .call_execute(&mut *store, &request)
+            .await?
+            .map_err(|e| anyhow!(e))?;
+        Ok(serde_json::from_str(&response)?)
+    }
+}
+
+/// Whether `dir` holds a WASM plugin rather than a Lua one.
+pub fn is_wasm_plugin(dir: &Path) -> bool {
+    dir.join(COMPONENT_FILE).is_file() && !dir.join("init.lua").is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Display;
+    use anyhow::bail;
+    use wasmtime::Trap;
+
+    /// Answers prompts and nothing else.
+    #[derive(Clone)]
+    struct PromptBackend;
+
+    impl Backend for PromptBackend {
+        async fn prompt(&self, _prompt: Prompt) -> Result<String> {
+            Ok("p1".to_string())
+        }
+
+        async fn prompt_state(&self, _id: String) -> Result<PromptResponse> {
+            bail!("not scripted")
+        }
+
+        async fn prompt_submission(&self, _id: String) -> Result<PromptResponse> {
+            bail!("not scripted")
+        }
+
+        async fn prompt_cancel(&self, _id: String) -> Result<()> {
+            bail!("not scripted")
+        }
+
+        async fn set_visibility(&self, _visible: bool) -> Result<()> {
+            bail!("not scripted")
+        }
+    }
+
+    fn context() -> PluginExecuteContext {
+        let display = || Display {
+            display_id: "1".to_string(),
+            current_space_id: 1,
+        };
+        PluginExecuteContext {
+            windows: Vec::new(),
+            displays: vec![display()],
+            current_display: display(),
+        }
+    }
+
+    /// Loads `tests/fixtures/wasm-plugin`; see its `plugin.wat`.
+    async fn load(capabilities: Vec<Capability>) -> WasmPlugin {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/wasm-plugin");
+        let plugin_ref = PluginRef {
+            name: "wasm-fixture".to_string(),
+            path: dir,
+            config: None,
+            http: Default::default(),
+            dependencies: Default::default(),
+            package_roots: Vec::new(),
+        };
+        WasmPlugin::new(plugin_ref, capabilities, Arc::new(PromptBackend))
+            .await
+            .unwrap()
+    }
+
+    async fn run(plugin: &WasmPlugin, command: &str) -> Result<PluginExecuteResponse> {
+        plugin.run(command.to_string(), &context(), None).await
+    }
+
+    #[test]
+    fn detects_wasm_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        assert!(!is_wasm_plugin(dir));
+
+        std::fs::write(dir.join(COMPONENT_FILE), b"\0asm").unwrap();
+        assert!(is_wasm_plugin(dir));
+
+        // A Lua entry point wins, e.g. for a Lua plugin that ships a component.
+        std::fs::write(dir.join("init.lua"), "return {}").unwrap();
+        assert!(!is_wasm_plugin(dir));
+    }
+
+    #[tokio::test]
+    async fn loads_a_component_and_runs_its_commands() {
+        let plugin = load(Vec::new()).await;
+
+        let info = plugin.initialize().await.unwrap();
+        assert_eq!(info.name, "fixture");
+        assert_eq!(info.api_version.as_deref(), Some("1.1"));
+        assert_eq!(info.commands.len(), 4);
+
+        assert!(run(&plugin, "ok").await.unwrap().hide);
+        let err = run(&plugin, "nope").await.unwrap_err();
+        assert!(err.to_string().contains("unknown command"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn traps_calls_that_run_out_of_fuel() {
+        let mut plugin = load(Vec::new()).await;
+        plugin.fuel_per_call = 1_000_000;
+
+        let err = run(&plugin, "spin").await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Trap>(),
+            Some(&Trap::OutOfFuel),
+            "{:#}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn caps_memory() {
+        let plugin = load(Vec::new()).await;
+
+        let err = run(&plugin, "grow").await.unwrap_err();
+        assert!(err.to_string().contains("out of memory"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn denies_capabilities_not_granted() {
+        let plugin = load(Vec::new()).await;
+        let err = run(&plugin, "ask").await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("plugin 'wasm-fixture' was not granted the 'ui' capability"),
+            "{}",
+            err
+        );
+
+        let plugin = load(vec![Capability::Ui]).await;
+        assert!(run(&plugin, "ask").await.unwrap().hide);
+    }
+}
@@ -0,0 +1,87 @@
+#!/usr/bin/env python3
+"""Process plugin used by the yal-plugin tests.
+
+Commands: `greet` prompts through the host (titled with the configured
+greeting and the last query from `on_query_change`) and hides the palette;
+`crash` exits without answering. `init` reports how many times it ran in
+this process as the description.
+"""
+
+import json
+import sys
+
+config = None
+query = None
+next_id = 0
+inits = 0
+
+
+def send(message):
+    message["jsonrpc"] = "2.0"
+    sys.stdout.write(json.dumps(message) + "\n")
+    sys.stdout.flush()
+
+
+def call(method, params):
+    """Calls the host and waits for its answer."""
+    global next_id
+    next_id += 1
+    send({"id": next_id, "method": method, "params": params})
+    while True:
+        message = json.loads(sys.stdin.readline())
+        if message.get("id") == next_id and "method" not in message:
+            if "error" in message:
+                raise RuntimeError(message["error"]["message"])
+            return message.get("result")
+
+
+def handle(method, params):
+    global config, query, inits
+    if method == "init":
+        config = params.get("config") or {}
+        inits += 1
+        return {
+            "name": "echo",
+            "description": "init #%d" % inits,
+            "version": "0.1.0",
+            "api_version": "1.1",
+            "commands": [{"name": "greet"}, {"name": "crash"}],
+            "hooks": ["on_query_change"],
+        }
+    if method == "on_query_change":
+        query = params
+        return None
+    if method != "execute":
+        raise ValueError("unknown method " + method)
+
+    command = params["command"]
+    if command == "greet":
+        prompt = call(
+            "prompt",
+            {
+                "title": " ".join(filter(None, [config.get("greeting", "Hello"), query])),
+                "content": [{"type": "text", "text": "Who should be greeted?"}],
+            },
+        )
+        response = call("prompt_submission", {"id": prompt["id"]})
+        if "Submit" not in response:
+            return {"hide": False}
+        call("set_visibility", {"visible": False})
+        return {"hide": True}
+    if command == "crash":
+        sys.exit(3)
+    raise ValueError("unknown command " + command)
+
+
+while True:
+    line = sys.stdin.readline()
+    if not line:
+        break
+    message = json.loads(line)
+    if message.get("method") == "shutdown":
+        break
+    try:
+        result = handle(message["method"], message.get("params") or {})
+        send({"id": message["id"], "result": result})
+    except Exception as e:
+        send({"id": message["id"], "error": {"code": -32000, "message": str(e)}})
@@ -0,0 +1,85 @@
+;; Test component for the WASM runtime (see `wasm::tests`). `execute` picks
+;; what to do by the first letter of the command:
+;;
+;;   ok    returns {"hide":true}
+;;   spin  loops forever, until it runs out of fuel
+;;   grow  grows its memory by 128 MiB, past the host's cap
+;;   ask   shows a prompt through the `ui` import
+;;
+;; Rebuild plugin.wasm after changing this file or wit/plugin.wit:
+;;
+;;   wasm-tools component embed ../../../wit plugin.wat -o core.wasm
+;;   wasm-tools component new core.wasm -o plugin.wasm
+;;   rm core.wasm
+(module
+  (import "yal:plugin/ui@0.1.0" "prompt" (func $prompt (param i32 i32 i32)))
+
+  (memory (export "memory") 1)
+  ;; Bump allocator for `cabi_realloc`; nothing is ever freed.
+  (global $heap (mut i32) (i32.const 4096))
+
+  (data (i32.const 256) "{\"name\":\"fixture\",\"version\":\"0.1.0\",\"api_version\":\"1.1\",\"commands\":[{\"name\":\"ok\"},{\"name\":\"spin\"},{\"name\":\"grow\"},{\"name\":\"ask\"}]}")
+  (data (i32.const 1024) "{\"hide\":true}")
+  (data (i32.const 1056) "out of memory")
+  (data (i32.const 1088) "unknown command")
+  (data (i32.const 1120) "{\"title\":\"Hi\",\"content\":[]}")
+
+  ;; Return areas: 2048 for the exports' results, 2064 for `prompt`'s.
+  (func $result (param $tag i32) (param $ptr i32) (param $len i32) (result i32)
+    (i32.store8 (i32.const 2048) (local.get $tag))
+    (i32.store (i32.const 2052) (local.get $ptr))
+    (i32.store (i32.const 2056) (local.get $len))
+    (i32.const 2048))
+
+  (func (export "init") (param i32 i32 i32) (result i32)
+    (call $result (i32.const 0) (i32.const 256) (i32.const 130)))
+
+  (func (export "execute") (param $ptr i32) (param $len i32) (result i32)
+    (local $command i32)
+    ;; The request starts with {"command":"
+    (local.set $command (i32.load8_u offset=12 (local.get $ptr)))
+    (if (i32.eq (local.get $command) (i32.const 111)) ;; o
+      (then (return (call $result (i32.const 0) (i32.const 1024) (i32.const 13)))))
+    (if (i32.eq (local.get $command) (i32.const 115)) ;; s
+      (then (loop $spin (br $spin))))
+    (if (i32.eq (local.get $command) (i32.const 103)) ;; g
+      (then
+        (if (i32.eq (memory.grow (i32.const 2048)) (i32.const -1))
+          (then (return (call $result (i32.const 1) (i32.const 1056) (i32.const 13)))))
+        (return (call $result (i32.const 0) (i32.const 1024) (i32.const 13)))))
+    (if (i32.eq (local.get $command) (i32.const 97)) ;; a
+      (then
+        (call $prompt (i32.const 1120) (i32.const 27) (i32.const 2064))
+        (if (i32.load8_u (i32.const 2064))
+          (then
+            (return (call $result
+              (i32.const 1)
+              (i32.load (i32.const 2068))
+              (i32.load (i32.const 2072))))))
+        (return (call $result (i32.const 0) (i32.const 1024) (i32.const 13)))))
+    (call $result (i32.const 1) (i32.const 1088) (i32.const 15)))
+
+  (func (export "cabi_realloc") (param $old i32) (param $old_size i32) (param $align i32) (param $size i32) (result i32)
+    (local $ptr i32)
+    (local $end i32)
+    (local.set $ptr
+      (i32.and
+        (i32.add (global.get $heap) (i32.sub (local.get $align) (i32.const 1)))
+        (i32.sub (i32.const 0) (local.get $align))))
+    (local.set $end (i32.add (local.get $ptr) (local.get $size)))
+    (if (i32.gt_u (local.get $end) (i32.shl (memory.size) (i32.const 16)))
+      (then
+        (if (i32.eq
+              (memory.grow
+                (i32.shr_u
+                  (i32.add
+                    (i32.sub (local.get $end) (i32.shl (memory.size) (i32.const 16)))
+                    (i32.const 65535))
+                  (i32.const 16)))
+              (i32.const -1))
+          (then unreachable))))
+    (global.set $heap (local.get $end))
+    (if (local.get $old)
+      (then (memory.copy (local.get $ptr) (local.get $old) (local.get $old_size))))
+    (local.get $ptr))
+)
@@ -0,0 +1,350 @@
+use std::{collections::HashMap, io, path::PathBuf, process::Stdio, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    sync::{Notify, mpsc},
+};
+
+const KILL_GRACE: Duration = Duration::from_millis(200);
+
+/// The shell used to interpret a command line, e.g. `sh -c` or `cmd /C`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shell {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        if cfg!(windows) {
+            Self::new("cmd")
+        } else {
+            Self::new("sh")
+        }
+    }
+}
+
+impl Shell {
+    /// Builds a shell from a program name or path, picking the flag that makes it
+    /// run a single command string.
+    pub fn new(program: impl Into<String>) -> Self {
+        let program = program.into();
+        let stem = std::path::Path::new(&program)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let flag = match stem.as_str() {
+            "cmd" => "/C",
+            "powershell" | "pwsh" => "-Command",
+            _ => "-c",
+        };
+        Self {
+            program,
+            args: vec![flag.to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSpec {
+    pub command: String,
+    pub shell: Shell,
+    pub cwd: Option<PathBuf>,
+    pub env: HashMap<String, String>,
+    pub timeout: Option<Duration>,
+}
+
+impl ProcessSpec {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitInfo {
+    /// Exit code, `None` when the process was killed by a signal.
+    pub code: Option<i32>,
+    pub timed_out: bool,
+    pub cancelled: bool,
+}
+
+impl ExitInfo {
+    pub fn success(&self) -> bool {
+        self.code == Some(0) && !self.timed_out && !self.cancelled
+    }
+}
+
+impl std::fmt::Display for ExitInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.cancelled {
+            write!(f, "cancelled")
+        } else if self.timed_out {
+            write!(f, "timed out")
+        } else if let Some(code) = self.code {
+            write!(f, "exit status: {}", code)
+        } else {
+            write!(f, "terminated by signal")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessEvent {
+    Stdout(String),
+    Stderr(String),
+    Exited(ExitInfo),
+}
+
+/// Cancels a running process. Cloneable so it can be handed to a UI.
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<Notify>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.notify_one();
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit: Option<ExitInfo>,
+}
+
+pub struct RunningProcess {
+    events: mpsc::UnboundedReceiver<ProcessEvent>,
+    cancel: CancelHandle,
+}
+
+impl RunningProcess {
+    /// Next line of output, or the exit event. Returns `None` once the process
+    /// has exited and every event was delivered.
+    pub async fn next(&mut self) -> Option<ProcessEvent> {
+        self.events.recv().await
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Drains the process, collecting stdout/stderr line by line.
+    pub async fn wait_with_output(mut self) -> Output {
+        let mut output = Output::default();
+        while let Some(event) = self.next().await {
+            match event {
+                ProcessEvent::Stdout(line) => {
+                    output.stdout.push_str(&line);
+                    output.stdout.push('\n');
+                }
+                ProcessEvent::Stderr(line) => {
+                    output.stderr.push_str(&line);
+                    output.stderr.push('\n');
+                }
+                ProcessEvent::Exited(exit) => output.exit = Some(exit),
+            }
+        }
+        output
+    }
+}
+
+/// Spawns `spec.command` through `spec.shell` and streams its output.
+///
+/// Must be called from within a tokio runtime.
+pub fn spawn(spec: ProcessSpec) -> io::Result<RunningProcess> {
+    let mut command = Command::new(&spec.shell.program);
+    command
+        .args(&spec.shell.args)
+        .arg(&spec.command)
+        .envs(&spec.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(cwd) = &spec.cwd {
+        command.current_dir(cwd);
+    }
+
+    let mut child = command.spawn()?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    let cancel = CancelHandle(Arc::new(Notify::new()));
+
+    let stdout = child
+        .stdout
+        .take()
+        .map(|s| tokio::spawn(forward_lines(s, tx.clone(), ProcessEvent::Stdout)));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|s| tokio::spawn(forward_lines(s, tx.clone(), ProcessEvent::Stderr)));
+
+    let notify = cancel.0.clone();
+    let timeout = spec.timeout;
+    tokio::spawn(async move {
+        let deadline = async {
+            match timeout {
+                Some(t) => tokio::time::sleep(t).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let mut exit = ExitInfo {
+            code: None,
+            timed_out: false,
+            cancelled: false,
+        };
+        tokio::select! {
+            status = child.wait() => {
+                exit.code = status.ok().and_then(|s| s.code());
+            }
+            _ = notify.notified() => {
+                exit.cancelled = true;
+                let _ = child.kill().await;
+            }
+            _ = deadline => {
+                exit.timed_out = true;
+                let _ = child.kill().await;
+            }
+        }
+
+        // Deliver remaining output before the exit event. A killed shell can leave
+        // children holding the pipes open, so only wait briefly in that case.
+        let killed = exit.cancelled || exit.timed_out;
+        for mut reader in [stdout, stderr].into_iter().flatten() {
+            if !killed {
+                let _ = reader.await;
+            } else if tokio::time::timeout(KILL_GRACE, &mut reader).await.is_err() {
+                reader.abort();
+            }
+        }
+        let _ = tx.send(ProcessEvent::Exited(exit));
+    });
+
+    Ok(RunningProcess { events: rx, cancel })
+}
+
+async fn forward_lines<R: AsyncRead + Unpin>(
+    reader: R,
+    tx: mpsc::UnboundedSender<ProcessEvent>,
+    wrap: fn(String) -> ProcessEvent,
+) {
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                if tx.send(wrap(line)).is_err() {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                log::warn!("Failed reading process output: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sleep_cmd(secs: u32) -> String {
+        if cfg!(windows) {
+            format!("ping -n {} 127.0.0.1 > nul", secs + 1)
+        } else {
+            format!("sleep {}", secs)
+        }
+    }
+
+    #[test]
+    fn shell_flags() {
+        assert_eq!(Shell::new("/bin/zsh").args, vec!["-c"]);
+        assert_eq!(Shell::new("cmd.exe").args, vec!["/C"]);
+        assert_eq!(Shell::new("pwsh").args, vec!["-Command"]);
+    }
+
+    #[tokio::test]
+    async fn captures_stdout_and_stderr() {
+        let out = spawn(ProcessSpec::new("echo hello && echo oops 1>&2"))
+            .unwrap()
+            .wait_with_output()
+            .await;
+        assert_eq!(out.stdout.trim(), "hello");
+        assert_eq!(out.stderr.trim(), "oops");
+        assert!(out.exit.unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn reports_exit_code() {
+        let out = spawn(ProcessSpec::new("exit 3"))
+            .unwrap()
+            .wait_with_output()
+            .await;
+        let exit = out.exit.unwrap();
+        assert_eq!(exit.code, Some(3));
+        assert!(!exit.success());
+        assert_eq!(exit.to_string(), "exit status: 3");
+    }
+
+    #[tokio::test]
+    async fn exit_is_the_last_event() {
+        let mut proc = spawn(ProcessSpec::new("echo one && echo two")).unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = proc.next().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events.last(), Some(ProcessEvent::Exited(_))));
+    }
+
+    #[tokio::test]
+    async fn applies_env_and_cwd() {
+        let dir = std::env::temp_dir();
+        let mut spec = ProcessSpec::new(if cfg!(windows) {
+            "echo %YAL_TEST_VAR% && cd"
+        } else {
+            "echo $YAL_TEST_VAR && pwd"
+        });
+        spec.env.insert("YAL_TEST_VAR".into(), "from-env".into());
+        spec.cwd = Some(dir.clone());
+
+        let out = spawn(spec).unwrap().wait_with_output().await;
+        let mut lines = out.stdout.lines();
+        assert_eq!(lines.next().map(str::trim), Some("from-env"));
+        let cwd = PathBuf::from(lines.next().unwrap().trim());
+        assert_eq!(cwd.canonicalize().unwrap(), dir.canonicalize().unwrap());
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let mut spec = ProcessSpec::new(sleep_cmd(5));
+        spec.timeout = Some(Duration::from_millis(100));
+        let started = std::time::Instant::now();
+        let out = spawn(spec).unwrap().wait_with_output().await;
+        let exit = out.exit.unwrap();
+        assert!(exit.timed_out);
+        assert!(!exit.success());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn can_be_cancelled() {
+        let proc = spawn(ProcessSpec::new(sleep_cmd(5))).unwrap();
+        let handle = proc.cancel_handle();
+        let started = std::time::Instant::now();
+        handle.cancel();
+        let out = proc.wait_with_output().await;
+        assert!(out.exit.unwrap().cancelled);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,273 @@
+//! Text templates for snippets.
+//!
+//! Placeholders are written in braces:
+//!
+//! - `{date}` / `{date:%d.%m.%Y}` — current date, `strftime` format (default `%Y-%m-%d`)
+//! - `{time}` / `{time:%H:%M:%S}` — current time (default `%H:%M`)
+//! - `{clipboard}` — current clipboard text
+//! - `{cursor}` — removed from the output; its position is reported in [`Rendered::cursor`]
+//! - `{name}` — any other identifier is a named field, filled in by the user
+//!
+//! `{{` and `}}` produce literal braces.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::NaiveDateTime;
+use chrono::format::{Item, StrftimeItems};
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    DateTime(String),
+    Clipboard,
+    Cursor,
+    Field(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the template source.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Values available while rendering.
+#[derive(Debug, Clone)]
+pub struct Context {
+    pub now: NaiveDateTime,
+    pub clipboard: Option<String>,
+    pub fields: HashMap<String, String>,
+}
+
+impl Context {
+    pub fn new(now: NaiveDateTime) -> Self {
+        Self {
+            now,
+            clipboard: None,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Context for the local time right now.
+    pub fn now() -> Self {
+        Self::new(chrono::Local::now().naive_local())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendered {
+    pub text: String,
+    /// Character offset of the first `{cursor}`, if any.
+    pub cursor: Option<usize>,
+}
+
+impl Template {
+    pub fn parse(src: &str) -> Result<Self, ParseError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = src.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|(_, c)| *c) == Some('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek().map(|(_, c)| *c) == Some('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => {
+                    return Err(ParseError {
+                        offset: i,
+                        message: "unmatched '}' (use '}}' for a literal brace)".to_string(),
+                    });
+                }
+                '{' => {
+                    let end = src[i..].find('}').map(|e| i + e).ok_or(ParseError {
+                        offset: i,
+                        message: "unclosed '{' (use '{{' for a literal brace)".to_string(),
+                    })?;
+                    while chars.peek().is_some_and(|(j, _)| *j <= end) {
+                        chars.next();
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&src[i + 1..end], i)?);
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Named fields in order of first appearance.
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields: Vec<&str> = Vec::new();
+        for part in &self.parts {
+            if let Part::Field(name) = part
+                && !fields.contains(&name.as_str())
+            {
+                fields.push(name);
+            }
+        }
+        fields
+    }
+
+    pub fn uses_clipboard(&self) -> bool {
+        self.parts.contains(&Part::Clipboard)
+    }
+
+    /// Renders the template. Missing fields and clipboard render as empty text.
+    pub fn render(&self, ctx: &Context) -> Rendered {
+        let mut text = String::new();
+        let mut cursor = None;
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => text.push_str(s),
+                Part::DateTime(fmt) => text.push_str(&ctx.now.format(fmt).to_string()),
+                Part::Clipboard => text.push_str(ctx.clipboard.as_deref().unwrap_or_default()),
+                Part::Cursor => {
+                    cursor.get_or_insert(text.chars().count());
+                }
+                Part::Field(name) => {
+                    text.push_str(ctx.fields.get(name).map(String::as_str).unwrap_or_default())
+                }
+            }
+        }
+        Rendered { text, cursor }
+    }
+}
+
+impl std::str::FromStr for Template {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn parse_placeholder(inner: &str, offset: usize) -> Result<Part, ParseError> {
+    let error = |message: String| ParseError { offset, message };
+    let (name, arg) = match inner.split_once(':') {
+        Some((name, arg)) => (name.trim(), Some(arg)),
+        None => (inner.trim(), None),
+    };
+
+    match (name, arg) {
+        ("date", fmt) => datetime(fmt.unwrap_or(DEFAULT_DATE_FORMAT)).map_err(error),
+        ("time", fmt) => datetime(fmt.unwrap_or(DEFAULT_TIME_FORMAT)).map_err(error),
+        ("clipboard", None) => Ok(Part::Clipboard),
+        ("cursor", None) => Ok(Part::Cursor),
+        ("", _) => Err(error("empty placeholder".to_string())),
+        (name, None) if is_identifier(name) => Ok(Part::Field(name.to_string())),
+        (name, _) => Err(error(format!("invalid placeholder '{{{}}}'", name))),
+    }
+}
+
+fn datetime(fmt: &str) -> Result<Part, String> {
+    if StrftimeItems::new(fmt).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid date format '{}'", fmt));
+    }
+    Ok(Part::DateTime(fmt.to_string()))
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn ctx() -> Context {
+        let now = NaiveDate::from_ymd_opt(2025, 3, 7)
+            .unwrap()
+            .and_hms_opt(9, 5, 30)
+            .unwrap();
+        Context::new(now)
+    }
+
+    fn render(src: &str, ctx: &Context) -> Rendered {
+        Template::parse(src).unwrap().render(ctx)
+    }
+
+    #[test]
+    fn renders_literals_and_escapes() {
+        assert_eq!(render("plain text", &ctx()).text, "plain text");
+        assert_eq!(render("{{date}}", &ctx()).text, "{date}");
+        assert_eq!(render("fn main() {{ }}", &ctx()).text, "fn main() { }");
+        assert_eq!(render("{{{date}}}", &ctx()).text, "{2025-03-07}");
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(render("{date}", &ctx()).text, "2025-03-07");
+        assert_eq!(render("{time}", &ctx()).text, "09:05");
+        assert_eq!(render("{date:%d/%m/%Y}", &ctx()).text, "07/03/2025");
+        assert_eq!(render("{date:%A, %B %-d}", &ctx()).text, "Friday, March 7");
+        assert_eq!(render("{time:%H:%M:%S}", &ctx()).text, "09:05:30");
+        // Colons inside the format are kept.
+        assert_eq!(
+            render("{date:%Y-%m-%dT%H:%M}", &ctx()).text,
+            "2025-03-07T09:05"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        let err = Template::parse("{date:%Q}").unwrap_err();
+        assert!(err.message.contains("invalid date format"), "{}", err);
+        assert_eq!(Template::parse("hi {name").unwrap_err().offset, 3);
+        assert_eq!(Template::parse("a } b").unwrap_err().offset, 2);
+        assert!(Template::parse("{}").is_err());
+        assert!(Template::parse("{first name}").is_err());
+        assert!(Template::parse("{clipboard:x}").is_err());
+    }
+
+    #[test]
+    fn fills_fields_and_clipboard() {
+        let tpl = Template::parse("Hi {name}, re: {topic}. Thanks {name}! {clipboard}").unwrap();
+        assert_eq!(tpl.fields(), vec!["name", "topic"]);
+        assert!(tpl.uses_clipboard());
+
+        let mut ctx = ctx();
+        ctx.fields.insert("name".into(), "Ada".into());
+        ctx.clipboard = Some("https://example.com".into());
+        assert_eq!(
+            tpl.render(&ctx).text,
+            "Hi Ada, re: . Thanks Ada! https://example.com"
+        );
+    }
+
+    #[test]
+    fn reports_cursor_in_chars() {
+        let out = render("¡hola {cursor}mundo{cursor}!", &ctx());
+        assert_eq!(out.text, "¡hola mundo!");
+        assert_eq!(out.cursor, Some(6));
+        assert_eq!(render("no cursor", &ctx()).cursor, None);
+    }
+}
@@ -3,10 +3,10 @@ pub mod list;
 
 use crate::app::filtering::filter_memoized_commands;
 use crate::bridge::events::{
-    init_api_listener, init_cmd_list_listener, init_config_listener, init_theme_listener,
-    prime_config, prime_theme,
+    init_api_listener, init_cmd_list_listener, init_config_listener, init_palette_listener,
+    init_theme_listener, prime_config, prime_theme,
 };
-use crate::bridge::invoke::{hide_window, run_cmd};
+use crate::bridge::invoke::{hide_window, query_changed, run_cmd};
 use crate::prompt::PromptView;
 use crate::utils::focus::raf_focus_search;
 use crate::utils::keys::normalize_combo_string;
@@ -14,6 +14,7 @@ use leptos::ev::KeyboardEvent;
 use leptos::prelude::*;
 use std::collections::HashMap;
 use yal_core::{Command, CommandKind, PromptRequest, Shortcut, ShortcutCommand};
+use yal_emoji::SkinTone;
 
 #[component]
 pub fn App() -> impl IntoView {
@@ -22,6 +23,7 @@ pub fn App() -> impl IntoView {
     let (selected, set_selected) = signal(0usize);
     let (filter, set_filter) = signal(Option::<CommandKind>::None);
     let (shortcuts, set_shortcuts) = signal(Vec::<Shortcut>::new());
+    let (skin_tone, set_skin_tone) = signal(SkinTone::default());
     let (form_values, set_form_values) =
         signal(std::collections::HashMap::<String, serde_json::Value>::new());
 
@@ -33,20 +35,28 @@ pub fn App() -> impl IntoView {
     let (prompt, set_prompt) = signal::<Option<PromptRequest>>(None);
 
     // Prime state from backend
-    prime_config(set_shortcuts);
+    prime_config(set_shortcuts, set_skin_tone);
     prime_theme();
 
     // Event listeners
-    init_config_listener(set_shortcuts);
+    init_config_listener(set_shortcuts, set_skin_tone);
     init_theme_listener();
     init_cmd_list_listener(set_cmd_list, reset);
+    init_palette_listener(set_query, set_filter);
     init_api_listener(set_prompt, prompt, form_values, set_form_values);
 
     let filtered = Memo::new(move |_| {
         let q = query.get();
         let list = cmds.get();
         let filter = filter.get();
-        filter_memoized_commands(&list, &q, selected.get(), &set_selected, filter)
+        filter_memoized_commands(
+            &list,
+            &q,
+            selected.get(),
+            &set_selected,
+            filter,
+            skin_tone.get(),
+        )
     });
 
     let prefix_text = Memo::new(move |_| match filter.get() {
@@ -54,6 +64,11 @@ pub fn App() -> impl IntoView {
         Some(CommandKind::Switch) => "switch".to_string(),
         Some(CommandKind::Theme) => "theme".to_string(),
         Some(CommandKind::Plugin) => "plugin".to_string(),
+        Some(CommandKind::Custom) => "run".to_string(),
+        Some(CommandKind::Shell) => "shell".to_string(),
+        Some(CommandKind::Emoji) => "emoji".to_string(),
+        Some(CommandKind::Bookmark) => "bookmark".to_string(),
+        Some(CommandKind::Snippet) => "snippet".to_string(),
         None => String::new(),
     });
 
@@ -148,6 +163,46 @@ pub fn App() -> impl IntoView {
                     }
                 });
             }
+            "r" if ev.ctrl_key() => {
+                ev.prevent_default();
+                set_filter.update(|f| {
+                    *f = match f {
+                        Some(CommandKind::Custom) => None,
+                        _ => Some(CommandKind::Custom),
+                    }
+                });
+            }
+            "u" if ev.ctrl_key() => {
+                ev.prevent_default();
+                set_filter.update(|f| {
+                    *f = match f {
+                        Some(CommandKind::Emoji) => None,
+                        _ => Some(CommandKind::Emoji),
+                    }
+                });
+            }
+            "b" if ev.ctrl_key() => {
+                ev.prevent_default();
+                set_filter.update(|f| {
+                    *f = match f {
+                        Some(CommandKind::Bookmark) => None,
+                        _ => Some(CommandKind::Bookmark),
+                    }
+                });
+            }
+            "s" if ev.ctrl_key() => {
+                ev.prevent_default();
+                set_filter.update(|f| {
+                    *f = match f {
+                        Some(CommandKind::Snippet) => None,
+                        _ => Some(CommandKind::Snippet),
+                    }
+                });
+            }
+            "Tab" if filter.get() == Some(CommandKind::Emoji) => {
+                ev.prevent_default();
+                set_skin_tone.update(|t| *t = t.next());
+            }
             "Escape" => {
                 leptos::task::spawn_local(async move {
                     hide_window().await;
@@ -177,7 +232,11 @@ pub fn App() -> impl IntoView {
         }
     };
 
-    let on_input = move |ev| set_query.set(event_target_value(&ev));
+    let on_input = move |ev| {
+        let value = event_target_value(&ev);
+        set_query.set(value.clone());
+        leptos::task::spawn_local(query_changed(value));
+    };
 
     Effect::new(move |_| {
         if prompt.get().is_none() {
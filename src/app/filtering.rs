@@ -2,14 +2,19 @@ use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 use leptos::prelude::*;
 use yal_core::{Command, CommandKind};
+use yal_emoji::SkinTone;
+
+const EMOJI_LIMIT: usize = 100;
 
 fn fuzzy_filter_commands(cmds: &[Command], query: &str) -> Vec<Command> {
     let matcher = SkimMatcherV2::default();
     let mut scored: Vec<(Command, i64)> = cmds
         .iter()
         .filter_map(|cmd| {
-            matcher
-                .fuzzy_match(&cmd.name(), query)
+            std::iter::once(cmd.name())
+                .chain(cmd.keywords().iter().cloned())
+                .filter_map(|text| matcher.fuzzy_match(&text, query))
+                .max()
                 .map(|score| (cmd.clone(), score))
         })
         .collect();
@@ -22,20 +27,81 @@ fn fuzzy_filter_commands(cmds: &[Command], query: &str) -> Vec<Command> {
     scored.into_iter().map(|(cmd, _)| cmd).collect()
 }
 
+/// Commands that are only listed in their own mode, never in the main palette.
+fn is_mode_only(cmd: &Command) -> bool {
+    matches!(
+        cmd,
+        Command::Shell(_) | Command::Emoji { .. } | Command::Bookmark { .. }
+    )
+}
+
+/// `>` mode: the typed line first, then matching commands from the history.
+fn shell_commands(cmds: &[Command], line: &str) -> Vec<Command> {
+    let history = cmds
+        .iter()
+        .filter(|c| CommandKind::Shell.is_kind(c) && c.name() != line)
+        .cloned()
+        .collect::<Vec<_>>();
+    let history = if line.is_empty() {
+        history
+    } else {
+        fuzzy_filter_commands(&history, line)
+    };
+
+    let typed = (!line.is_empty()).then(|| Command::Shell(line.to_string()));
+    typed.into_iter().chain(history).collect()
+}
+
+/// Emoji mode: searched locally against the embedded dataset. The backend only
+/// publishes recently used emoji, which are ranked first.
+fn emoji_commands(cmds: &[Command], query: &str, skin_tone: SkinTone) -> Vec<Command> {
+    let recent = cmds
+        .iter()
+        .filter_map(|c| match c {
+            Command::Emoji { symbol, .. } => Some(symbol.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    yal_emoji::search(query, &recent, EMOJI_LIMIT)
+        .into_iter()
+        .map(|e| Command::Emoji {
+            symbol: e.with_skin_tone(skin_tone).to_string(),
+            name: e.name.to_string(),
+        })
+        .collect()
+}
+
 pub fn filter_memoized_commands(
     cmds: &[Command],
     query: &str,
     selected: usize,
     set_selected: &WriteSignal<usize>,
     filter: Option<CommandKind>,
+    skin_tone: SkinTone,
 ) -> Vec<Command> {
+    let mode = match (query.strip_prefix('>'), &filter) {
+        (Some(line), _) => Some(shell_commands(cmds, line.trim())),
+        (None, Some(CommandKind::Emoji)) => Some(emoji_commands(cmds, query, skin_tone)),
+        _ => None,
+    };
+    if let Some(v) = mode {
+        if !v.is_empty() && selected >= v.len() {
+            set_selected.set(v.len() - 1);
+        }
+        return v;
+    }
+
     let commands = if let Some(kind) = filter {
         cmds.iter()
             .filter(|c| kind.is_kind(c))
             .cloned()
             .collect::<Vec<_>>()
     } else {
-        cmds.to_vec()
+        cmds.iter()
+            .filter(|c| !is_mode_only(c))
+            .cloned()
+            .collect::<Vec<_>>()
     };
 
     let v: Vec<Command> = if query.trim().is_empty() {
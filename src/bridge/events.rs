@@ -4,8 +4,10 @@ use leptos::prelude::*;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::{closure::Closure, JsCast};
 use yal_core::{
-    AppConfig, FrontendRequest, Prompt, PromptRequest, PromptResponse, Shortcut, Theme,
+    AppConfig, CommandKind, FrontendRequest, PaletteRequest, Prompt, PromptRequest, PromptResponse,
+    Shortcut, Theme,
 };
+use yal_emoji::SkinTone;
 
 #[wasm_bindgen]
 extern "C" {
@@ -13,7 +15,18 @@ extern "C" {
     async fn tauri_listen(event: &str, callback: &js_sys::Function);
 }
 
-pub fn prime_config(set_shortcuts: WriteSignal<Vec<Shortcut>>) {
+fn apply_emoji_cfg(cfg: &AppConfig, set_skin_tone: WriteSignal<SkinTone>) {
+    let tone = cfg.emoji.as_ref().and_then(|e| e.skin_tone.as_deref());
+    match tone.map(str::parse::<SkinTone>).transpose() {
+        Ok(tone) => set_skin_tone.set(tone.unwrap_or_default()),
+        Err(e) => log::warn!("{}", e),
+    }
+}
+
+pub fn prime_config(
+    set_shortcuts: WriteSignal<Vec<Shortcut>>,
+    set_skin_tone: WriteSignal<SkinTone>,
+) {
     leptos::task::spawn_local(async move {
         if let Some(cfg) = get_config().await {
             if let Some(w) = &cfg.window {
@@ -27,6 +40,7 @@ pub fn prime_config(set_shortcuts: WriteSignal<Vec<Shortcut>>) {
                     set_shortcuts.set(shortcuts.clone());
                 }
             }
+            apply_emoji_cfg(&cfg, set_skin_tone);
         }
     });
 }
@@ -53,7 +67,10 @@ pub fn init_theme_listener() {
     });
 }
 
-pub fn init_config_listener(set_shortcuts: WriteSignal<Vec<Shortcut>>) {
+pub fn init_config_listener(
+    set_shortcuts: WriteSignal<Vec<Shortcut>>,
+    set_skin_tone: WriteSignal<SkinTone>,
+) {
     leptos::task::spawn_local(async move {
         let cb = Closure::<dyn FnMut(js_sys::Object)>::new(move |evt_obj: js_sys::Object| {
             if let Ok(payload) = js_sys::Reflect::get(&evt_obj, &JsValue::from_str("payload")) {
@@ -69,6 +86,7 @@ pub fn init_config_listener(set_shortcuts: WriteSignal<Vec<Shortcut>>) {
                             set_shortcuts.set(shortcuts.clone());
                         }
                     }
+                    apply_emoji_cfg(&cfg, set_skin_tone);
                 }
             }
         });
@@ -77,6 +95,33 @@ pub fn init_config_listener(set_shortcuts: WriteSignal<Vec<Shortcut>>) {
     });
 }
 
+/// `palette://show` is sent when the palette is opened from outside (e.g. the
+/// control socket) with a query or mode to start from.
+pub fn init_palette_listener(
+    set_query: WriteSignal<String>,
+    set_filter: WriteSignal<Option<CommandKind>>,
+) {
+    leptos::task::spawn_local(async move {
+        let cb = Closure::<dyn FnMut(js_sys::Object)>::new(move |evt_obj: js_sys::Object| {
+            if let Ok(payload) = js_sys::Reflect::get(&evt_obj, &JsValue::from_str("payload")) {
+                if let Ok(req) = serde_wasm_bindgen::from_value::<PaletteRequest>(payload) {
+                    let query = req.query.unwrap_or_default();
+                    // Shell mode is driven by the `>` prefix rather than a filter.
+                    if req.mode == Some(CommandKind::Shell) {
+                        set_filter.set(None);
+                        set_query.set(format!(">{}", query));
+                    } else {
+                        set_filter.set(req.mode);
+                        set_query.set(query);
+                    }
+                }
+            }
+        });
+        let _unlisten = tauri_listen("palette://show", cb.as_ref().unchecked_ref()).await;
+        cb.forget();
+    });
+}
+
 pub fn init_cmd_list_listener(
     set_cmd_list: WriteSignal<Vec<yal_core::Command>>,
     reset: impl Fn() + 'static,
@@ -119,6 +164,27 @@ pub fn init_api_listener(
         let _u_show = tauri_listen("api://prompt:show", cb_show.as_ref().unchecked_ref()).await;
         cb_show.forget();
 
+        // prompt:update (replaces the content of the prompt that is already showing)
+        let set_prompt_update = set_prompt;
+        let cb_update =
+            Closure::<dyn FnMut(js_sys::Object)>::new(move |evt_obj: js_sys::Object| {
+                if let Ok(payload) = js_sys::Reflect::get(&evt_obj, &JsValue::from_str("payload")) {
+                    if let Ok(req) =
+                        serde_wasm_bindgen::from_value::<FrontendRequest<Prompt>>(payload)
+                    {
+                        if prompt.get_untracked().is_some_and(|p| p.id == req.id) {
+                            set_prompt_update.set(Some(PromptRequest {
+                                id: req.id,
+                                prompt: req.data,
+                            }));
+                        }
+                    }
+                }
+            });
+        let _u_update =
+            tauri_listen("api://prompt:update", cb_update.as_ref().unchecked_ref()).await;
+        cb_update.forget();
+
         // prompt:state
         let prompt_state = prompt;
         let cb_state =
@@ -31,6 +31,11 @@ pub async fn hide_window() {
     .await;
 }
 
+pub async fn query_changed(query: String) {
+    let args = serde_wasm_bindgen::to_value(&json!({ "query": query })).unwrap();
+    let _ = invoke("query_changed", args).await;
+}
+
 pub async fn get_config() -> Option<AppConfig> {
     let v = invoke(
         "get_config",
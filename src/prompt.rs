@@ -93,12 +93,12 @@ pub fn PromptView(
           }
         >
           <div class="yal-popup-header">
-            { p().prompt.title.clone().unwrap_or_default() }
+            { move || p().prompt.title.clone().unwrap_or_default() }
           </div>
 
           <div class="yal-popup-body">
             {
-              p().prompt.content.iter().cloned()
+              move || p().prompt.content.iter().cloned()
                 .map(|n| view!{ <RenderNode node=n set_form_values=set_form_values /> })
                 .collect_view()
             }
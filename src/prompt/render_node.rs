@@ -1,5 +1,5 @@
 use leptos::prelude::*;
-use yal_core::Node;
+use yal_core::{Node, TextVariant};
 
 #[component]
 pub fn RenderNode(
@@ -40,7 +40,17 @@ pub fn RenderNode(
 
         Node::Markdown { md } => view! { <super::RenderMarkdown md=md /> }.into_any(),
 
-        Node::Text { text, .. } => view! { <div class="yal-text">{ text }</div> }.into_any(),
+        Node::Text { text, variant } => {
+            let class = match variant {
+                None => "yal-text",
+                Some(TextVariant::Muted) => "yal-text muted",
+                Some(TextVariant::Caption) => "yal-text caption",
+                Some(TextVariant::Code) => "yal-text code",
+                Some(TextVariant::Emphasis) => "yal-text emphasis",
+                Some(TextVariant::Heading) => "yal-text heading",
+            };
+            view! { <div class=class>{ text }</div> }.into_any()
+        }
 
         Node::Form(form) => view! { <super::RenderForm form=form set_form_values=set_form_values /> }.into_any(),
 
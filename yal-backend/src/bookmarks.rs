@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+use kameo::prelude::Message;
+use kameo::Actor;
+use yal_bookmarks::{Browser, Index, Profile};
+use yal_core::{BookmarksConfig, BrowserKind, Command};
+
+const DEFAULT_HISTORY_LIMIT: usize = 500;
+/// Browsers rewrite their history database constantly, so don't re-read it on
+/// every palette open.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Actor, Default)]
+pub struct BookmarksActor {
+    index: Option<Index>,
+    refreshed_at: Option<Instant>,
+}
+
+impl BookmarksActor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Returns bookmark commands for the given config, rebuilding the index if the
+/// configured profiles changed.
+pub struct GetBookmarks(pub BookmarksConfig);
+
+impl Message<GetBookmarks> for BookmarksActor {
+    type Reply = Vec<Command>;
+
+    async fn handle(
+        &mut self,
+        GetBookmarks(cfg): GetBookmarks,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        let profiles = match cfg.profiles {
+            Some(profiles) => profiles
+                .into_iter()
+                .map(|p| Profile {
+                    browser: match p.browser {
+                        BrowserKind::Chromium => Browser::Chromium,
+                        BrowserKind::Firefox => Browser::Firefox,
+                    },
+                    path: crate::cmd::expand_home(&p.path),
+                })
+                .collect(),
+            None => yal_bookmarks::default_profiles(),
+        };
+        let history_limit = cfg.history_limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+        let stale = self
+            .index
+            .as_ref()
+            .is_none_or(|i| i.profiles() != profiles || i.history_limit() != history_limit);
+        if stale {
+            self.index = Some(Index::new(profiles, history_limit));
+            self.refreshed_at = None;
+        }
+
+        let index = self.index.as_mut().expect("index was just built");
+        if self
+            .refreshed_at
+            .is_none_or(|at| at.elapsed() >= REFRESH_INTERVAL)
+        {
+            if index.refresh() {
+                log::info!("Indexed {} bookmarks", index.entries().len());
+            }
+            self.refreshed_at = Some(Instant::now());
+        }
+
+        index
+            .entries()
+            .into_iter()
+            .map(|e| Command::Bookmark {
+                title: e.title.clone(),
+                url: e.url.clone(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,56 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Replaces the system clipboard contents with `text`.
+pub fn copy(text: &str) -> Result<(), String> {
+    let mut child = Command::new("pbcopy")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run pbcopy: {}", e))?;
+    if let Some(stdin) = child.stdin.as_mut() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("pbcopy exited with {}", status))
+    }
+}
+
+/// Current clipboard contents as text, if any.
+pub fn paste() -> Option<String> {
+    let output = Command::new("pbpaste").output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Sends ⌘V to the focused app, then moves the caret `left` characters back.
+pub fn paste_into_focused_app(left: usize) -> Result<(), String> {
+    let mut script =
+        String::from("tell application \"System Events\"\nkeystroke \"v\" using command down\n");
+    if left > 0 {
+        script.push_str(&format!(
+            "repeat {} times\nkey code 123\nend repeat\n",
+            left
+        ));
+    }
+    script.push_str("end tell");
+
+    let status = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .status()
+        .map_err(|e| format!("Failed to run osascript: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("osascript exited with {}", status))
+    }
+}
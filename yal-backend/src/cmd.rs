@@ -1,4 +1,4 @@
-use std::thread;
+use std::{path::PathBuf, thread};
 
 use kameo::{
     actor::ActorRef,
@@ -9,14 +9,22 @@ use lightsky::WindowId;
 use tauri::{Emitter, Manager};
 use tauri_plugin_opener::OpenerExt;
 use yal_core::{AppInfo, Command, WindowTarget};
+use yal_plugin::manager::settings;
 
 use crate::{
     application_tree,
     ax::{self, AXActor},
+    bookmarks,
     cmd::app::get_app_info,
+    config,
 };
 
 mod app;
+mod custom;
+mod emoji;
+mod plugins;
+mod shell;
+mod snippet;
 pub mod theme;
 
 #[derive(Actor)]
@@ -38,6 +46,21 @@ impl Message<Command> for CommandActor {
                 args,
                 ..
             } => self.run_plugin_cmd(plugin_name, command_name, args).await,
+            Command::PluginSettings { plugin_name } => {
+                self.run_plugin_settings_cmd(plugin_name).await
+            }
+            Command::PluginToggle {
+                plugin_name,
+                enable,
+            } => plugins::run_toggle_cmd(&self.app_handle, &plugin_name, enable).await,
+            Command::PrunePlugins => plugins::run_prune_cmd(&self.app_handle).await,
+            Command::Custom { name, .. } => custom::run_custom_cmd(&self.app_handle, &name).await,
+            Command::Shell(line) => shell::run_shell_cmd(&self.app_handle, line).await,
+            Command::Emoji { symbol, .. } => emoji::run_emoji_cmd(&self.app_handle, &symbol).await,
+            Command::Bookmark { url, .. } => self.run_bookmark_cmd(url).await,
+            Command::Snippet { name, .. } => {
+                snippet::run_snippet_cmd(&self.app_handle, &name).await
+            }
         }
     }
 }
@@ -101,6 +124,14 @@ impl CommandActor {
         Ok(())
     }
 
+    async fn run_bookmark_cmd(&self, url: String) -> Result<(), String> {
+        self.app_handle
+            .opener()
+            .open_url(url, None::<&str>)
+            .map_err(|e| e.to_string())?;
+        self.app_handle.hide().map_err(|e| e.to_string())
+    }
+
     async fn run_plugin_cmd(
         &self,
         plugin_name: String,
@@ -131,6 +162,44 @@ impl CommandActor {
         Ok(())
     }
 
+    async fn run_plugin_settings_cmd(&self, plugin_name: String) -> Result<(), String> {
+        let plugin_ref = self
+            .app_handle
+            .state::<ActorRef<crate::plugin::PluginManagerActor<crate::plugin_backend::PluginBackend>>>();
+
+        let (schema, current) = plugin_ref
+            .ask(crate::plugin::GetPluginSettings {
+                plugin_name: plugin_name.clone(),
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // The form stays open as long as the user likes, so it is shown here
+        // rather than inside the plugin manager.
+        let backend = self
+            .app_handle
+            .state::<crate::plugin_backend::PluginBackend>();
+        let Some(settings) = settings::edit(backend.inner(), &plugin_name, &schema, &current)
+            .await
+            .map_err(|e| format!("Failed to configure plugin '{}': {:#}", plugin_name, e))?
+        else {
+            return Ok(());
+        };
+        plugin_ref
+            .ask(crate::plugin::SavePluginSettings {
+                plugin_name,
+                settings,
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // get_cmds reloads the plugins, which picks up the new settings
+        let cmds: Vec<_> = self.get_cmds().await;
+        let _ = self.app_handle.emit("commands://updated", cmds);
+
+        Ok(())
+    }
+
     async fn run_switch_cmd(&self, target: WindowTarget) -> Result<(), String> {
         let ax_ref = self.app_handle.state::<ActorRef<AXActor>>();
         ax_ref
@@ -195,12 +264,20 @@ impl CommandActor {
             .app_handle
             .state::<ActorRef<crate::plugin::PluginManagerActor<crate::plugin_backend::PluginBackend>>>();
 
+        let overrides = plugins::load_overrides(&self.app_handle).await;
         let plugin_cmds = plugin_ref
-            .ask(crate::plugin::LoadPlugins)
+            .ask(crate::plugin::LoadPlugins { overrides })
             .await
             .unwrap_or_default()
             .iter()
             .flat_map(|p| {
+                let settings = (p.enabled && p.configurable).then(|| Command::PluginSettings {
+                    plugin_name: p.plugin_name.clone(),
+                });
+                let toggle = Command::PluginToggle {
+                    plugin_name: p.plugin_name.clone(),
+                    enable: !p.enabled,
+                };
                 p.commands
                     .iter()
                     .filter(|c| !c.hidden)
@@ -209,13 +286,80 @@ impl CommandActor {
                         command_name: c.name.clone(),
                         args: None,
                     })
+                    .chain(settings)
+                    .chain(std::iter::once(toggle))
+            })
+            .chain(std::iter::once(Command::PrunePlugins))
+            .collect::<Vec<Command>>();
+
+        let custom_cmds = custom::load_custom_commands(&self.app_handle)
+            .await
+            .into_iter()
+            .map(|c| Command::Custom {
+                name: c.name,
+                keywords: c.keywords,
             })
             .collect::<Vec<Command>>();
 
-        [app_infos, switch_targets, themes, plugin_cmds].concat()
+        let shell_history = shell::load_history(&self.app_handle)
+            .await
+            .into_iter()
+            .map(|e| Command::Shell(e.command))
+            .collect::<Vec<Command>>();
+
+        let snippets = snippet::load_snippets()
+            .into_iter()
+            .map(|s| Command::Snippet {
+                name: s.name,
+                keywords: s.keywords,
+            })
+            .collect::<Vec<Command>>();
+
+        let recent_emoji = emoji::recent_commands(&self.app_handle).await;
+
+        let bookmarks_cfg = self
+            .app_handle
+            .state::<ActorRef<config::ConfigActor>>()
+            .ask(config::GetConfig)
+            .await
+            .ok()
+            .and_then(|cfg| cfg.bookmarks)
+            .unwrap_or_default();
+        let bookmarks = self
+            .app_handle
+            .state::<ActorRef<bookmarks::BookmarksActor>>()
+            .ask(bookmarks::GetBookmarks(bookmarks_cfg))
+            .await
+            .unwrap_or_default();
+
+        [
+            app_infos,
+            switch_targets,
+            themes,
+            plugin_cmds,
+            custom_cmds,
+            shell_history,
+            snippets,
+            recent_emoji,
+            bookmarks,
+        ]
+        .concat()
     }
 }
 
+/// `path` with a leading `~` or `~/` replaced by the home directory. Other
+/// users' homes (`~name`) are left alone.
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some("") => "",
+        Some(rest) if rest.starts_with('/') => &rest[1..],
+        _ => return PathBuf::from(path),
+    };
+    dirs::home_dir()
+        .map(|home| home.join(rest))
+        .unwrap_or_else(|| PathBuf::from(path))
+}
+
 #[tauri::command]
 pub async fn run_cmd(app: tauri::AppHandle, cmd: Command) -> Result<(), String> {
     let now = std::time::Instant::now();
@@ -0,0 +1,130 @@
+use std::{sync::Arc, time::Duration};
+
+use kameo::actor::ActorRef;
+use tauri::Manager;
+use tauri_plugin_opener::OpenerExt;
+use yal_core::{
+    CustomAction, CustomCommand, Node, OpenAction, Prompt, ShellAction, TextVariant, UrlAction,
+};
+use yal_process::{ProcessSpec, Shell};
+
+use crate::{config, frontend_middleware::FrontendMiddleware};
+
+const DEFAULT_SHELL_TIMEOUT_MS: u64 = 30_000;
+
+pub async fn load_custom_commands(app_handle: &tauri::AppHandle) -> Vec<CustomCommand> {
+    let cfg_ref = app_handle.state::<ActorRef<config::ConfigActor>>();
+    cfg_ref
+        .ask(config::GetConfig)
+        .await
+        .ok()
+        .and_then(|cfg| cfg.commands)
+        .unwrap_or_default()
+}
+
+pub async fn run_custom_cmd(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+    let cmd = load_custom_commands(app_handle)
+        .await
+        .into_iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| format!("Custom command '{}' not found", name))?;
+
+    match &cmd.action {
+        CustomAction::Shell(action) => run_shell(app_handle, &cmd.name, action).await,
+        CustomAction::Open(action) => {
+            let opened = run_open(app_handle, action);
+            app_handle.hide().map_err(|e| e.to_string())?;
+            opened
+        }
+        CustomAction::Url(UrlAction { url }) => {
+            let opened = app_handle
+                .opener()
+                .open_url(url, None::<&str>)
+                .map_err(|e| e.to_string());
+            app_handle.hide().map_err(|e| e.to_string())?;
+            opened
+        }
+    }
+}
+
+async fn run_shell(
+    app_handle: &tauri::AppHandle,
+    name: &str,
+    action: &ShellAction,
+) -> Result<(), String> {
+    log::info!("Running shell command '{}': {}", name, action.command);
+
+    let shell_cfg = super::shell::shell_config(app_handle).await;
+    let spec = ProcessSpec {
+        command: action.command.clone(),
+        shell: shell_cfg.program.map(Shell::new).unwrap_or_default(),
+        cwd: action.cwd.as_deref().map(super::expand_home),
+        env: action.env.clone().unwrap_or_default(),
+        timeout: Some(Duration::from_millis(
+            action.timeout_ms.unwrap_or(DEFAULT_SHELL_TIMEOUT_MS),
+        )),
+    };
+    // A command that can't be started ends like one that failed: its error
+    // is shown or the window hidden, and then reported.
+    let (text, failed) = match yal_process::spawn(spec) {
+        Ok(process) => {
+            let output = process.wait_with_output().await;
+            let mut text = output.stdout;
+            text.push_str(&output.stderr);
+            if let Some(exit) = output.exit.filter(|e| !e.success()) {
+                log::warn!("Shell command '{}' finished with {}", name, exit);
+                text.push_str(&format!("\n{}", exit));
+            }
+            (text, None)
+        }
+        Err(e) => {
+            let error = format!("Failed to start shell command '{}': {}", name, e);
+            (error.clone(), Some(error))
+        }
+    };
+
+    if action.show_output {
+        show_output(app_handle, name, text).await;
+    } else {
+        app_handle.hide().map_err(|e| e.to_string())?;
+    }
+    failed.map_or(Ok(()), Err)
+}
+
+fn run_open(app_handle: &tauri::AppHandle, action: &OpenAction) -> Result<(), String> {
+    let path = super::expand_home(&action.path);
+    match &action.app {
+        None if action.args.is_empty() => app_handle
+            .opener()
+            .open_path(path.to_string_lossy(), None::<&str>)
+            .map_err(|e| e.to_string()),
+        app => {
+            let mut command = std::process::Command::new("open");
+            if let Some(app) = app {
+                command.arg("-a").arg(app);
+            }
+            command.arg(&path);
+            if !action.args.is_empty() {
+                command.arg("--args").args(&action.args);
+            }
+            command.spawn().map(|_| ()).map_err(|e| e.to_string())
+        }
+    }
+}
+
+async fn show_output(app_handle: &tauri::AppHandle, name: &str, text: String) {
+    let middleware = app_handle.state::<Arc<FrontendMiddleware>>();
+    let prompt = Prompt {
+        title: Some(name.to_string()),
+        width: None,
+        height: None,
+        content: vec![Node::Text {
+            text,
+            variant: Some(TextVariant::Code),
+        }],
+        ui_schema_version: None,
+    };
+    middleware
+        .tell("prompt:show", nanoid::nanoid!(21), prompt)
+        .await;
+}
@@ -0,0 +1,58 @@
+use kameo::actor::ActorRef;
+use tauri::Manager;
+use yal_core::Command;
+
+use crate::{
+    clipboard,
+    state::{GetState, SetState, StateActor},
+};
+
+const RECENT_KEY: &str = "emoji.recent";
+const MAX_RECENT: usize = 24;
+
+async fn load_recent(app_handle: &tauri::AppHandle) -> Vec<String> {
+    let state_ref = app_handle.state::<ActorRef<StateActor>>();
+    state_ref
+        .ask(GetState::<Vec<String>>::new(RECENT_KEY))
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Recently used emoji, most recent first. The full dataset is searched by the
+/// frontend, so these are the only emoji commands that get published.
+pub async fn recent_commands(app_handle: &tauri::AppHandle) -> Vec<Command> {
+    load_recent(app_handle)
+        .await
+        .into_iter()
+        .filter_map(|symbol| {
+            let emoji = yal_emoji::find(&symbol)?;
+            Some(Command::Emoji {
+                symbol,
+                name: emoji.name.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub async fn run_emoji_cmd(app_handle: &tauri::AppHandle, symbol: &str) -> Result<(), String> {
+    clipboard::copy(symbol)?;
+    if let Err(e) = record_recent(app_handle, symbol).await {
+        log::error!("Failed to save recent emoji: {}", e);
+    }
+    app_handle.hide().map_err(|e| e.to_string())
+}
+
+async fn record_recent(app_handle: &tauri::AppHandle, symbol: &str) -> anyhow::Result<()> {
+    let mut recent = load_recent(app_handle).await;
+    recent.retain(|s| s != symbol);
+    recent.insert(0, symbol.to_string());
+    recent.truncate(MAX_RECENT);
+
+    let state_ref = app_handle.state::<ActorRef<StateActor>>();
+    state_ref
+        .ask(SetState::new(RECENT_KEY, &recent)?)
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()))
+}
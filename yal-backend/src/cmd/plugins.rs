@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use kameo::actor::ActorRef;
+use tauri::Manager;
+use yal_plugin::manager::prune;
+
+use crate::{
+    cmd::{CommandActor, PublishCommands},
+    plugin::{FindOrphans, PluginManagerActor, PruneOrphans},
+    plugin_backend::PluginBackend,
+    state::{GetState, SetState, StateActor},
+};
+
+const ENABLED_KEY: &str = "plugins.enabled";
+
+/// Plugins enabled or disabled from the palette, by plugin name. Kept in app
+/// state so toggling never rewrites the user's `plugins.lua`.
+pub async fn load_overrides(app_handle: &tauri::AppHandle) -> HashMap<String, bool> {
+    let state_ref = app_handle.state::<ActorRef<StateActor>>();
+    state_ref
+        .ask(GetState::<HashMap<String, bool>>::new(ENABLED_KEY))
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+pub async fn run_toggle_cmd(
+    app_handle: &tauri::AppHandle,
+    plugin_name: &str,
+    enable: bool,
+) -> Result<(), String> {
+    let mut overrides = load_overrides(app_handle).await;
+    overrides.insert(plugin_name.to_string(), enable);
+
+    let state_ref = app_handle.state::<ActorRef<StateActor>>();
+    state_ref
+        .ask(SetState::new(ENABLED_KEY, &overrides).map_err(|e| e.to_string())?)
+        .await
+        .map_err(|e| e.to_string())?;
+    log::info!(
+        "{} plugin '{}'",
+        if enable { "Enabled" } else { "Disabled" },
+        plugin_name
+    );
+
+    // Publishing reloads the plugins, which drops the VM of a disabled one
+    // and its commands with it.
+    let cmd_ref = app_handle.state::<ActorRef<CommandActor>>();
+    cmd_ref
+        .tell(PublishCommands)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Shows what removed plugins left behind and deletes it once the user
+/// confirms. The prompt runs here rather than in the plugin manager, which
+/// keeps serving commands and palette events while it is open.
+pub async fn run_prune_cmd(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let plugin_ref = app_handle.state::<ActorRef<PluginManagerActor<PluginBackend>>>();
+    let orphans = plugin_ref
+        .ask(FindOrphans)
+        .await
+        .map_err(|e| e.to_string())?;
+    let backend = app_handle.state::<PluginBackend>();
+    if !prune::confirm(backend.inner(), &orphans)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(());
+    }
+    let freed = plugin_ref
+        .ask(PruneOrphans(orphans))
+        .await
+        .map_err(|e| e.to_string())?;
+    log::info!("Pruned plugins, freed {} bytes", freed);
+    Ok(())
+}
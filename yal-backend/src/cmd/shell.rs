@@ -0,0 +1,210 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use kameo::actor::ActorRef;
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+use yal_core::{Node, Prompt, PromptResponse, ShellConfig, TextVariant};
+use yal_process::{ExitInfo, ProcessEvent, ProcessSpec, RunningProcess, Shell};
+
+use crate::{
+    config,
+    frontend_middleware::FrontendMiddleware,
+    state::{GetState, StateActor, UpdateState},
+};
+
+const HISTORY_KEY: &str = "shell.history";
+const DEFAULT_HISTORY_SIZE: usize = 100;
+const MAX_OUTPUT_LINES: usize = 2_000;
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellHistoryEntry {
+    pub command: String,
+    pub runs: u32,
+    pub last_run: u64, // unix seconds
+    pub last_exit: Option<i32>,
+}
+
+pub async fn load_history(app_handle: &tauri::AppHandle) -> Vec<ShellHistoryEntry> {
+    let state_ref = app_handle.state::<ActorRef<StateActor>>();
+    state_ref
+        .ask(GetState::<Vec<ShellHistoryEntry>>::new(HISTORY_KEY))
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+pub async fn shell_config(app_handle: &tauri::AppHandle) -> ShellConfig {
+    let cfg_ref = app_handle.state::<ActorRef<config::ConfigActor>>();
+    cfg_ref
+        .ask(config::GetConfig)
+        .await
+        .ok()
+        .and_then(|cfg| cfg.shell)
+        .unwrap_or_default()
+}
+
+/// Runs `line` in the configured shell and streams its output into a prompt.
+/// Returns as soon as the process has been spawned.
+pub async fn run_shell_cmd(app_handle: &tauri::AppHandle, line: String) -> Result<(), String> {
+    let line = line.trim().to_string();
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    let cfg = shell_config(app_handle).await;
+    let spec = ProcessSpec {
+        command: line.clone(),
+        shell: cfg.program.clone().map(Shell::new).unwrap_or_default(),
+        cwd: Some(super::expand_home(cfg.cwd.as_deref().unwrap_or("~"))),
+        env: Default::default(),
+        timeout: cfg.timeout_ms.map(Duration::from_millis),
+    };
+    log::info!("Running shell command: {}", line);
+    let process = yal_process::spawn(spec).map_err(|e| e.to_string())?;
+
+    let app = app_handle.clone();
+    let history_size = cfg.history_size.unwrap_or(DEFAULT_HISTORY_SIZE);
+    tauri::async_runtime::spawn(async move {
+        let exit = stream_session(&app, &line, process).await;
+        if let Err(e) = record_history(&app, &line, exit, history_size).await {
+            log::error!("Failed to save shell history: {}", e);
+        }
+    });
+    Ok(())
+}
+
+async fn stream_session(
+    app: &tauri::AppHandle,
+    line: &str,
+    mut process: RunningProcess,
+) -> Option<ExitInfo> {
+    let middleware = app.state::<Arc<FrontendMiddleware>>().inner().clone();
+    let id = nanoid::nanoid!(21);
+    let mut output = VecDeque::new();
+    let mut exit = None;
+
+    middleware
+        .tell(
+            "prompt:show",
+            id.clone(),
+            session_prompt(line, &output, exit),
+        )
+        .await;
+
+    // Any answer from the prompt (esc or close) cancels the process.
+    let response = middleware
+        .ask::<_, PromptResponse>("prompt:submit", id.clone(), serde_json::json!({}))
+        .await
+        .recv();
+    tokio::pin!(response);
+
+    let mut closed = false;
+    let mut dirty = false;
+    let mut ticker = tokio::time::interval(UPDATE_INTERVAL);
+    loop {
+        tokio::select! {
+            event = process.next() => match event {
+                Some(ProcessEvent::Stdout(l)) | Some(ProcessEvent::Stderr(l)) => {
+                    output.push_back(l);
+                    if output.len() > MAX_OUTPUT_LINES {
+                        output.pop_front();
+                    }
+                    dirty = true;
+                }
+                Some(ProcessEvent::Exited(e)) => {
+                    exit = Some(e);
+                    dirty = true;
+                }
+                None => break,
+            },
+            _ = &mut response, if !closed => {
+                closed = true;
+                process.cancel();
+            }
+            _ = ticker.tick(), if dirty && !closed => {
+                dirty = false;
+                middleware
+                    .tell("prompt:update", id.clone(), session_prompt(line, &output, exit))
+                    .await;
+            }
+        }
+    }
+
+    if !closed {
+        middleware
+            .tell(
+                "prompt:update",
+                id.clone(),
+                session_prompt(line, &output, exit),
+            )
+            .await;
+        let _ = response.await;
+    }
+    exit
+}
+
+fn session_prompt(line: &str, output: &VecDeque<String>, exit: Option<ExitInfo>) -> Prompt {
+    let status = match exit {
+        Some(exit) => exit.to_string(),
+        None => "running… press esc to cancel".to_string(),
+    };
+    let output = output.iter().cloned().collect::<Vec<_>>().join("\n");
+    Prompt {
+        title: Some("shell".to_string()),
+        width: None,
+        height: None,
+        content: vec![
+            Node::Text {
+                text: format!("$ {}", line),
+                variant: Some(TextVariant::Heading),
+            },
+            Node::Text {
+                text: output,
+                variant: Some(TextVariant::Code),
+            },
+            Node::Text {
+                text: status,
+                variant: Some(TextVariant::Caption),
+            },
+        ],
+        ui_schema_version: None,
+    }
+}
+
+async fn record_history(
+    app: &tauri::AppHandle,
+    line: &str,
+    exit: Option<ExitInfo>,
+    history_size: usize,
+) -> anyhow::Result<()> {
+    let line = line.to_string();
+    let last_exit = exit.and_then(|e| e.code);
+    let last_run = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let update = move |history: &mut Vec<ShellHistoryEntry>| {
+        let mut entry = match history.iter().position(|e| e.command == line) {
+            Some(i) => history.remove(i),
+            None => ShellHistoryEntry {
+                command: line,
+                runs: 0,
+                last_run: 0,
+                last_exit: None,
+            },
+        };
+        entry.runs += 1;
+        entry.last_run = last_run;
+        entry.last_exit = last_exit;
+        history.insert(0, entry);
+        history.truncate(history_size);
+    };
+
+    let state_ref = app.state::<ActorRef<StateActor>>();
+    state_ref
+        .ask(UpdateState::new(HISTORY_KEY, update))
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()))
+}
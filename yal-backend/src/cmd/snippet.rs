@@ -0,0 +1,139 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tauri::Manager;
+use yal_config::load_config;
+use yal_core::{Field, Form, Node, Prompt, PromptResponse, Snippet, TextField};
+use yal_template::{Context, Template};
+
+use crate::{clipboard, config, frontend_middleware::FrontendMiddleware};
+
+/// Time for the previously focused app to regain focus before pasting.
+const PASTE_DELAY: Duration = Duration::from_millis(150);
+
+pub fn load_snippets() -> Vec<Snippet> {
+    load_config::<Vec<Snippet>>(config::snippets_path().as_path())
+}
+
+pub async fn run_snippet_cmd(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+    let snippet = load_snippets()
+        .into_iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| format!("Snippet '{}' not found", name))?;
+    let template =
+        Template::parse(&snippet.text).map_err(|e| format!("Invalid snippet '{}': {}", name, e))?;
+
+    // Read the clipboard now, before the rendered snippet replaces it.
+    let mut ctx = Context::now();
+    if template.uses_clipboard() {
+        ctx.clipboard = clipboard::paste();
+    }
+
+    let fields = template
+        .fields()
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        return finish(app_handle, &snippet, &template, &ctx).await;
+    }
+
+    // Waiting on the form must not block the command actor.
+    let app = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        match ask_fields(&app, &snippet.name, &fields).await {
+            Ok(Some(values)) => {
+                ctx.fields = values;
+                if let Err(e) = finish(&app, &snippet, &template, &ctx).await {
+                    log::error!("Failed to expand snippet '{}': {}", snippet.name, e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("Snippet form for '{}' failed: {}", snippet.name, e),
+        }
+    });
+    Ok(())
+}
+
+async fn finish(
+    app_handle: &tauri::AppHandle,
+    snippet: &Snippet,
+    template: &Template,
+    ctx: &Context,
+) -> Result<(), String> {
+    let rendered = template.render(ctx);
+    clipboard::copy(&rendered.text)?;
+    app_handle.hide().map_err(|e| e.to_string())?;
+
+    if snippet.paste {
+        let left = rendered
+            .cursor
+            .map(|c| rendered.text.chars().count() - c)
+            .unwrap_or_default();
+        tokio::time::sleep(PASTE_DELAY).await;
+        clipboard::paste_into_focused_app(left)?;
+    }
+    Ok(())
+}
+
+/// Shows a form with one text input per field. `None` if the user cancelled.
+async fn ask_fields(
+    app_handle: &tauri::AppHandle,
+    title: &str,
+    fields: &[String],
+) -> anyhow::Result<Option<HashMap<String, String>>> {
+    let middleware = app_handle.state::<Arc<FrontendMiddleware>>();
+    let id = nanoid::nanoid!(21);
+    let prompt = Prompt {
+        title: Some(title.to_string()),
+        width: None,
+        height: None,
+        content: vec![Node::Form(Form {
+            name: Some(title.to_string()),
+            fields: fields
+                .iter()
+                .map(|name| {
+                    Field::Text(TextField {
+                        name: name.clone(),
+                        label: Some(field_label(name)),
+                        placeholder: None,
+                        max_length: None,
+                    })
+                })
+                .collect(),
+        })],
+        ui_schema_version: None,
+    };
+
+    middleware.tell("prompt:show", id.clone(), prompt).await;
+    let response = middleware
+        .ask::<_, PromptResponse>("prompt:submit", id, serde_json::json!({}))
+        .await
+        .recv()
+        .await?;
+
+    let PromptResponse::Submit { values } = response else {
+        return Ok(None);
+    };
+    let values = values
+        .as_object()
+        .map(|map| {
+            map.iter()
+                .map(|(k, v)| match v {
+                    serde_json::Value::String(s) => (k.clone(), s.clone()),
+                    other => (k.clone(), other.to_string()),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Some(values))
+}
+
+/// `first_name` → `First name`
+fn field_label(name: &str) -> String {
+    let words = name.replace(['_', '-'], " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
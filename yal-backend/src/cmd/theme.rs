@@ -2,6 +2,7 @@ use crate::config;
 use kameo::{prelude::Message, Actor};
 use tauri::Emitter;
 use yal_config::load_config;
+use yal_plugin::events::SystemEvent;
 use yal_theme::ALL;
 
 #[derive(Actor)]
@@ -43,6 +44,12 @@ impl ThemeManagerActor {
         {
             log::info!("Applying theme: {}", theme_name);
             let _ = self.app_handle.emit("theme://applied", theme.clone());
+            crate::common::publish(
+                &self.app_handle,
+                SystemEvent::ThemeApplied {
+                    theme: theme_name.to_string(),
+                },
+            );
         }
         self.current = Some(theme_name.to_string());
     }
@@ -1,6 +1,18 @@
+use tauri::Manager;
+use yal_plugin::events::SystemEvent;
+
 #[derive(Debug, Clone)]
 pub enum Events {
     ReloadConfig,
     ReloadPlugins,
     RefreshTree,
+    /// Forwarded to the plugins subscribed to its topic
+    System(SystemEvent),
+}
+
+/// Sends `event` to the plugins through the event router, once it runs.
+pub fn publish(app_handle: &tauri::AppHandle, event: SystemEvent) {
+    if let Some(event_tx) = app_handle.try_state::<kanal::Sender<Events>>() {
+        let _ = event_tx.send(Events::System(event));
+    }
 }
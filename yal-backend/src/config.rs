@@ -1,9 +1,10 @@
 use anyhow::Result;
 use kameo::prelude::Message;
 use kameo::Actor;
-use std::path::PathBuf;
 use yal_core::AppConfig;
 
+pub use yal_config::{config_path, snippets_path, themes_path};
+
 #[derive(Actor)]
 pub struct ConfigActor {
     config: AppConfig,
@@ -44,23 +45,3 @@ impl Message<GetConfig> for ConfigActor {
         Ok(self.config.clone())
     }
 }
-
-pub fn config_base_path() -> PathBuf {
-    let base = std::env::var_os("XDG_CONFIG_HOME")
-        .map(PathBuf::from)
-        .or_else(|| {
-            std::env::var_os("HOME")
-                .map(PathBuf::from)
-                .map(|h| h.join(".config"))
-        })
-        .unwrap_or_else(|| PathBuf::from("."));
-    base.join("yal")
-}
-
-pub fn config_path() -> PathBuf {
-    config_base_path().join("config.lua")
-}
-
-pub fn themes_path() -> PathBuf {
-    config_base_path().join("themes.lua")
-}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use kameo::actor::ActorRef;
+use tauri::Manager;
+use yal_core::{
+    deeplink::{self, DeepLink},
+    Command, Node, Prompt, PromptResponse, TextVariant,
+};
+
+use crate::{cmd, frontend_middleware::FrontendMiddleware};
+
+/// Handles `yal://` links, whether delivered by the OS or found in the argv of
+/// a second instance. Anything else in `urls` is ignored.
+pub fn open_urls<I, S>(app_handle: &tauri::AppHandle, urls: I)
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    for url in urls.into_iter().map(Into::into) {
+        if !deeplink::is_deep_link(&url) {
+            continue;
+        }
+        let app = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = open(&app, &url).await {
+                log::warn!("Ignoring {}: {}", url, e);
+            }
+        });
+    }
+}
+
+async fn open(app_handle: &tauri::AppHandle, url: &str) -> Result<(), String> {
+    let cfg = crate::current_cfg_or_default(app_handle)
+        .await
+        .deeplinks
+        .unwrap_or_default();
+    if !cfg.enabled.unwrap_or(true) {
+        return Err("deep links are disabled".to_string());
+    }
+
+    let link = DeepLink::parse(url).map_err(|e| e.to_string())?;
+    log::info!("Opening deep link: {:?}", link);
+    let confirm = link.runs_plugin() && cfg.confirm_plugin_commands.unwrap_or(true);
+    match link {
+        DeepLink::Show(request) => crate::show_palette(app_handle, request).await,
+        DeepLink::Run(command) => {
+            if confirm && !ask_confirmation(app_handle, &command).await? {
+                return Ok(());
+            }
+            let cmd_ref = app_handle.state::<ActorRef<cmd::CommandActor>>();
+            cmd_ref.ask(command).await.map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Shows the palette with an Enter-to-run prompt. `false` if the user cancelled.
+async fn ask_confirmation(
+    app_handle: &tauri::AppHandle,
+    command: &Command,
+) -> Result<bool, String> {
+    crate::reveal_palette(app_handle).await;
+
+    let Command::Plugin {
+        plugin_name,
+        command_name,
+        args,
+    } = command
+    else {
+        return Ok(true);
+    };
+    let mut content = vec![Node::Text {
+        text: format!(
+            "A link wants to run '{}' from the '{}' plugin.",
+            command_name, plugin_name
+        ),
+        variant: None,
+    }];
+    if let Some(args) = args {
+        content.push(Node::Text {
+            text: serde_json::to_string_pretty(args).unwrap_or_default(),
+            variant: Some(TextVariant::Code),
+        });
+    }
+    content.push(Node::Text {
+        text: "Press Enter to run it or Escape to cancel.".to_string(),
+        variant: Some(TextVariant::Muted),
+    });
+
+    let middleware = app_handle.state::<Arc<FrontendMiddleware>>();
+    let id = nanoid::nanoid!(21);
+    let prompt = Prompt {
+        title: Some("Run plugin command?".to_string()),
+        width: None,
+        height: None,
+        content,
+        ui_schema_version: None,
+    };
+    middleware.tell("prompt:show", id.clone(), prompt).await;
+    let response = middleware
+        .ask::<_, PromptResponse>("prompt:submit", id, serde_json::json!({}))
+        .await
+        .recv()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(matches!(response, PromptResponse::Submit { .. }))
+}
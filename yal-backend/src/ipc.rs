@@ -0,0 +1,103 @@
+use kameo::actor::ActorRef;
+use serde_json::Value;
+use tauri::Manager;
+use yal_core::PaletteRequest;
+use yal_ipc::{Handler, Method, RpcError, Server};
+
+use crate::{
+    cmd::{self, theme},
+    config, plugin,
+    plugin_backend::PluginBackend,
+};
+
+/// Serves the control API (see `yal-ipc`) on the per-user socket.
+pub fn spawn(app_handle: tauri::AppHandle) {
+    let path = yal_ipc::socket_path();
+    let server = match Server::bind(&path) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("Control socket disabled: {}", e);
+            return;
+        }
+    };
+    log::info!("Control socket listening on {}", path.display());
+    tauri::async_runtime::spawn(server.serve(IpcHandler { app_handle }));
+}
+
+struct IpcHandler {
+    app_handle: tauri::AppHandle,
+}
+
+impl Handler for IpcHandler {
+    async fn handle(&self, method: Method) -> Result<Value, RpcError> {
+        let app = &self.app_handle;
+        match method {
+            // Answered by the server.
+            Method::Hello => Ok(Value::Null),
+            Method::Show { query, mode } => {
+                crate::show_palette(app, PaletteRequest { query, mode })
+                    .await
+                    .map_err(internal)?;
+                Ok(Value::Null)
+            }
+            Method::Hide => {
+                crate::hide_palette_window(app);
+                Ok(Value::Null)
+            }
+            Method::ListCommands => {
+                let cmd_ref = app.state::<ActorRef<cmd::CommandActor>>();
+                let cmds = cmd_ref.ask(cmd::GetCommands).await.map_err(internal)?;
+                serde_json::to_value(cmds).map_err(internal)
+            }
+            Method::RunCommand { command } => {
+                let cmd_ref = app.state::<ActorRef<cmd::CommandActor>>();
+                cmd_ref.ask(command).await.map_err(internal)?;
+                Ok(Value::Null)
+            }
+            Method::ReloadConfig => {
+                let cfg = crate::router::reload_config(
+                    app,
+                    &app.state::<ActorRef<config::ConfigActor>>(),
+                    &app.state::<ActorRef<theme::ThemeManagerActor>>(),
+                )
+                .await
+                .ok_or_else(|| RpcError::internal("failed to reload config"))?;
+                serde_json::to_value(cfg).map_err(internal)
+            }
+            Method::ReloadPlugins => {
+                let plugin_ref = app.state::<ActorRef<plugin::PluginManagerActor<PluginBackend>>>();
+                plugin_ref
+                    .ask(plugin::InstallPlugins)
+                    .await
+                    .map_err(internal)?;
+                plugin_ref
+                    .ask(plugin::UnloadPlugins)
+                    .await
+                    .map_err(internal)?;
+                Ok(Value::Null)
+            }
+            Method::ApplyTheme { theme } => {
+                let theme_ref = app.state::<ActorRef<theme::ThemeManagerActor>>();
+                let themes = theme_ref.ask(theme::LoadThemes).await.map_err(internal)?;
+                if !themes
+                    .iter()
+                    .any(|t| t.name.as_deref() == Some(theme.as_str()))
+                {
+                    return Err(RpcError::new(
+                        RpcError::INVALID_PARAMS,
+                        format!("unknown theme '{}'", theme),
+                    ));
+                }
+                theme_ref
+                    .tell(theme::ApplyTheme { theme_name: theme })
+                    .await
+                    .map_err(internal)?;
+                Ok(Value::Null)
+            }
+        }
+    }
+}
+
+fn internal(e: impl std::fmt::Display) -> RpcError {
+    RpcError::internal(e.to_string())
+}
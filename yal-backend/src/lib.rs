@@ -1,20 +1,27 @@
 use kameo::{actor::ActorRef, Actor};
 use std::sync::Arc;
-use tauri::{ActivationPolicy, Manager, WindowEvent};
+use tauri::{ActivationPolicy, Emitter, Manager, RunEvent, WindowEvent};
+use tauri_plugin_deep_link::DeepLinkExt;
 
 mod application_tree;
 mod ax;
+mod bookmarks;
+mod clipboard;
 mod cmd;
 mod common;
 mod config;
 mod config_watcher;
+mod deeplink;
 mod display;
 mod focus;
 mod frontend_middleware;
+mod ipc;
 mod ns_watcher;
 mod plugin;
 mod plugin_backend;
 mod router;
+mod scheduler;
+mod state;
 mod window;
 
 use crate::{
@@ -23,10 +30,12 @@ use crate::{
         run_cmd,
         theme::{self, ThemeManagerActor},
     },
+    plugin::PluginManagerActor,
+    plugin_backend::PluginBackend,
 };
 
 use yal_config::load_config;
-use yal_core::{AppConfig, Theme};
+use yal_core::{deeplink::is_deep_link, AppConfig, PaletteRequest, Theme};
 
 #[tauri::command]
 async fn get_theme(app: tauri::AppHandle) -> Result<Option<Theme>, String> {
@@ -81,6 +90,19 @@ fn hide_window(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// Tells plugins the search query changed.
+#[tauri::command]
+async fn query_changed(app: tauri::AppHandle, query: String) -> Result<(), String> {
+    tell_plugins(&app, plugin::PaletteEvent::QueryChanged(query)).await;
+    Ok(())
+}
+
+/// Runs plugin hooks without waiting for them.
+async fn tell_plugins(app: &tauri::AppHandle, event: plugin::PaletteEvent) {
+    let plugin_ref = app.state::<ActorRef<PluginManagerActor<PluginBackend>>>();
+    let _ = plugin_ref.tell(event).await;
+}
+
 async fn publish_cmd_list(app: &tauri::AppHandle) {
     let cmd_handle = app.state::<ActorRef<cmd::CommandActor>>();
     cmd_handle.tell(cmd::PublishCommands).await.unwrap();
@@ -89,10 +111,31 @@ async fn publish_cmd_list(app: &tauri::AppHandle) {
 async fn reveal_palette(app: &tauri::AppHandle) {
     let cfg = current_cfg_or_default(app).await;
     window::reveal_on_active_space(app, &cfg);
+    tell_plugins(app, plugin::PaletteEvent::Shown).await;
+}
+
+/// Reveals the palette, optionally pre-filled with a query and switched to a mode.
+async fn show_palette(app: &tauri::AppHandle, request: PaletteRequest) -> Result<(), String> {
+    let focus_ref = app.state::<ActorRef<focus::FocusManagerActor>>();
+    let _ = focus_ref.ask(focus::InitFocus).await;
+    publish_cmd_list(app).await;
+    reveal_palette(app).await;
+    app.emit("palette://show", request)
+        .map_err(|e| e.to_string())
 }
 
 fn hide_palette_window(app: &tauri::AppHandle) {
+    // Losing focus hides the palette again after most other ways of hiding it.
+    let visible = app
+        .get_webview_window("main")
+        .is_some_and(|win| win.is_visible().unwrap_or(false));
     app.hide().ok();
+    if visible {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tell_plugins(&app, plugin::PaletteEvent::Hidden).await;
+        });
+    }
 }
 
 async fn current_cfg_or_default(app: &tauri::AppHandle) -> AppConfig {
@@ -114,12 +157,15 @@ pub fn run() {
             tauri_plugin_autostart::MacosLauncher::LaunchAgent,
             None,
         ))
-        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
-            if let Some(win) = app.get_webview_window("main") {
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            if argv.iter().any(|arg| is_deep_link(arg)) {
+                deeplink::open_urls(app, argv);
+            } else if let Some(win) = app.get_webview_window("main") {
                 let _ = win.show();
                 let _ = win.set_focus();
             }
         }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(
             tauri_plugin_global_shortcut::Builder::new()
                 .with_shortcut("cmd+space")
@@ -197,8 +243,9 @@ pub fn run() {
                     frontend_middleware.clone(),
                 );
 
-                let plugin_manager_actor =
-                    plugin::PluginManagerActor::spawn(plugin::PluginManagerActor::new(backend));
+                let plugin_manager_actor = plugin::PluginManagerActor::spawn(
+                    plugin::PluginManagerActor::new(backend.clone()),
+                );
 
                 plugin_manager_actor
                     .ask(plugin::InstallPlugins)
@@ -228,10 +275,19 @@ pub fn run() {
 
                 let config_actor = config::ConfigActor::spawn(config::ConfigActor::new());
 
+                let state_actor = state::StateActor::spawn(state::StateActor::new());
+
+                let bookmarks_actor =
+                    bookmarks::BookmarksActor::spawn(bookmarks::BookmarksActor::new());
+
                 let theme_manager_actor = theme::ThemeManagerActor::spawn(
                     theme::ThemeManagerActor::new(app.handle().clone()),
                 );
 
+                let scheduler_actor = scheduler::SchedulerActor::spawn(
+                    scheduler::SchedulerActor::new(app.handle().clone()),
+                );
+
                 let event_router = router::EventRouter::new(
                     app.handle().clone(),
                     config_actor.clone(),
@@ -261,6 +317,7 @@ pub fn run() {
                 ns_watcher::SystemWatcher::spawn(event_tx.clone());
 
                 app.manage(plugin_manager_actor);
+                app.manage(backend);
                 app.manage(cmd_actor);
                 app.manage(application_tree_actor);
                 app.manage(focus_manager_actor);
@@ -268,7 +325,27 @@ pub fn run() {
                 app.manage(ax_actor);
                 app.manage(theme_manager_actor);
                 app.manage(config_actor);
+                app.manage(state_actor);
+                app.manage(bookmarks_actor);
                 app.manage(frontend_middleware);
+                app.manage(event_tx.clone());
+
+                let _ = scheduler_actor
+                    .tell(scheduler::Reschedule(
+                        cfg.schedules.clone().unwrap_or_default(),
+                    ))
+                    .await;
+                app.manage(scheduler_actor);
+
+                ipc::spawn(app.handle().clone());
+
+                let handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    deeplink::open_urls(&handle, event.urls().iter().map(|u| u.to_string()));
+                });
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    deeplink::open_urls(app.handle(), urls.iter().map(|u| u.to_string()));
+                }
 
                 event_tx.send(common::Events::RefreshTree).unwrap();
             });
@@ -278,12 +355,21 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             run_cmd,
             hide_window,
+            query_changed,
             get_config,
             reload_config,
             get_theme,
             frontend_middleware::api_response,
             frontend_middleware::api_error
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                let plugin_ref = app.state::<ActorRef<PluginManagerActor<PluginBackend>>>();
+                tauri::async_runtime::block_on(async {
+                    let _ = plugin_ref.ask(plugin::UnloadPlugins).await;
+                });
+            }
+        });
 }
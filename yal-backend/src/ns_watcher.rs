@@ -6,7 +6,7 @@ use core_graphics::display::{
 use log::{error, info};
 use objc2::rc::Retained;
 use objc2::runtime::ProtocolObject;
-use objc2_app_kit::NSWorkspace;
+use objc2_app_kit::{NSRunningApplication, NSWorkspace, NSWorkspaceApplicationKey};
 use objc2_core_foundation::{kCFRunLoopDefaultMode, CFRunLoop, CFType};
 use objc2_foundation::{
     ns_string, NSNotification, NSNotificationCenter, NSObjectProtocol, NSOperationQueue, NSString,
@@ -17,6 +17,10 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tauri::async_runtime;
 use tokio::time::sleep;
+use yal_plugin::events::SystemEvent;
+
+/// A notification, with the event plugins see for it, if any.
+type Raw = Option<SystemEvent>;
 
 pub struct SystemWatcher {
     event_tx: kanal::Sender<crate::common::Events>,
@@ -35,7 +39,7 @@ impl SystemWatcher {
     async fn run(self) -> Result<(), String> {
         info!("Starting SystemWatcher (installing on main runloop)");
 
-        let (raw_tx, raw_rx) = kanal::unbounded::<()>();
+        let (raw_tx, raw_rx) = kanal::unbounded::<Raw>();
 
         let (ready_tx, ready_rx) = kanal::unbounded::<Result<(), String>>();
         std::thread::Builder::new()
@@ -76,7 +80,11 @@ impl SystemWatcher {
         let debounce_ms = 200u64;
         let mut last: Instant = Instant::now();
 
-        while let Ok(()) = raw_rx.as_async().recv().await {
+        while let Ok(raw) = raw_rx.as_async().recv().await {
+            // Plugins get every event; only the tree refresh is debounced.
+            if let Some(event) = raw {
+                let _ = self.event_tx.send(crate::common::Events::System(event));
+            }
             if last.elapsed() >= Duration::from_millis(debounce_ms) {
                 last = Instant::now();
                 sleep(Duration::from_millis(1000)).await;
@@ -138,10 +146,10 @@ thread_local! {
     static MAIN_GUARD: RefCell<Option<SystemObserverGuard>> = const { RefCell::new(None) };
 }
 
-static SINK: OnceCell<Arc<Mutex<kanal::Sender<()>>>> = OnceCell::new();
+static SINK: OnceCell<Arc<Mutex<kanal::Sender<Raw>>>> = OnceCell::new();
 
 unsafe fn install_observers_on_main(
-    raw_tx: kanal::Sender<()>,
+    raw_tx: kanal::Sender<Raw>,
 ) -> Result<SystemObserverGuard, String> {
     let _ = SINK.set(Arc::new(Mutex::new(raw_tx)));
 
@@ -153,10 +161,11 @@ unsafe fn install_observers_on_main(
 
     let mut tokens: Vec<Retained<ProtocolObject<dyn NSObjectProtocol>>> = Vec::new();
 
-    let mut add = |name: &NSString| {
+    let mut add = |name: &NSString, to_event: fn(&NSNotification) -> Raw| {
         let sink = SINK.get().expect("SINK initialized").clone();
-        let block = move |_note: NonNull<NSNotification>| {
-            let _ = sink.lock().unwrap().send(());
+        let block = move |note: NonNull<NSNotification>| {
+            let raw = to_event(note.as_ref());
+            let _ = sink.lock().unwrap().send(raw);
         };
         let blk: RcBlock<dyn Fn(NonNull<NSNotification>) + 'static> = StackBlock::new(block).copy();
 
@@ -170,16 +179,34 @@ unsafe fn install_observers_on_main(
         tokens.push(token);
     };
 
-    let names: &[&NSString] = &[
-        ns_string!("NSWorkspaceActiveSpaceDidChangeNotification"),
-        ns_string!("NSWorkspaceDidLaunchApplicationNotification"),
-        ns_string!("NSWorkspaceDidTerminateApplicationNotification"),
-        ns_string!("NSWorkspaceDidActivateApplicationNotification"),
-        ns_string!("NSWorkspaceDidHideApplicationNotification"),
-        ns_string!("NSWorkspaceDidUnhideApplicationNotification"),
+    let names: &[(&NSString, fn(&NSNotification) -> Raw)] = &[
+        (
+            ns_string!("NSWorkspaceActiveSpaceDidChangeNotification"),
+            |_| Some(SystemEvent::SpaceChanged),
+        ),
+        (
+            ns_string!("NSWorkspaceDidLaunchApplicationNotification"),
+            |note| app_of(note).map(|(app_name, pid)| SystemEvent::AppLaunched { app_name, pid }),
+        ),
+        (
+            ns_string!("NSWorkspaceDidTerminateApplicationNotification"),
+            |note| app_of(note).map(|(app_name, pid)| SystemEvent::AppTerminated { app_name, pid }),
+        ),
+        (
+            ns_string!("NSWorkspaceDidActivateApplicationNotification"),
+            |_| None,
+        ),
+        (
+            ns_string!("NSWorkspaceDidHideApplicationNotification"),
+            |_| None,
+        ),
+        (
+            ns_string!("NSWorkspaceDidUnhideApplicationNotification"),
+            |_| None,
+        ),
     ];
-    for &n in names {
-        add(n);
+    for &(n, to_event) in names {
+        add(n, to_event);
     }
 
     let mut cg_registered = false;
@@ -197,8 +224,26 @@ unsafe fn install_observers_on_main(
     })
 }
 
-unsafe extern "C" fn display_cb(_display: u32, _flags: u32, _user: *const std::ffi::c_void) {
+/// Name and pid of the application a workspace notification is about.
+fn app_of(note: &NSNotification) -> Option<(String, i32)> {
+    unsafe {
+        let info = note.userInfo()?;
+        let app = info.objectForKey(NSWorkspaceApplicationKey)?;
+        let app: &NSRunningApplication = app.downcast_ref()?;
+        let name = app
+            .localizedName()
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        Some((name, app.processIdentifier()))
+    }
+}
+
+/// `kCGDisplayBeginConfigurationFlag`: sent before a change is made.
+const BEGIN_CONFIGURATION: u32 = 1 << 0;
+
+unsafe extern "C" fn display_cb(_display: u32, flags: u32, _user: *const std::ffi::c_void) {
     if let Some(sink) = SINK.get() {
-        let _ = sink.lock().unwrap().send(());
+        let raw = (flags & BEGIN_CONFIGURATION == 0).then_some(SystemEvent::DisplayChanged);
+        let _ = sink.lock().unwrap().send(raw);
     }
 }
@@ -1,8 +1,14 @@
+use std::collections::HashMap;
+
 use kameo::{prelude::Message, Actor};
 use yal_plugin::{
     backend::Backend,
+    events::SystemEvent,
+    hooks::Hook,
+    manager::{prune::Orphan, settings},
     plugin::PluginManifest,
     protocol::{PluginExecuteContext, PluginExecuteResponse},
+    schema::ConfigSchema,
     PluginManager,
 };
 
@@ -36,20 +42,26 @@ impl<T: Backend> Message<InstallPlugins> for PluginManagerActor<T> {
     }
 }
 
-pub struct LoadPlugins;
+pub struct LoadPlugins {
+    /// Plugins enabled or disabled from the palette, by plugin name
+    pub overrides: HashMap<String, bool>,
+}
 
 impl<T: Backend> Message<LoadPlugins> for PluginManagerActor<T> {
     type Reply = Vec<PluginManifest>;
 
     async fn handle(
         &mut self,
-        _msg: LoadPlugins,
+        msg: LoadPlugins,
         _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
     ) -> Self::Reply {
         log::debug!("Loading plugins...");
-        self.manager.load_config().await.unwrap();
+        self.manager.overrides = msg.overrides;
+        if let Err(e) = self.manager.load_config().await {
+            log::error!("Failed loading the plugin config: {:#}", e);
+        }
         log::debug!("Plugin config loaded: {:#?}", self.manager.config);
-        self.manager.load_plugins().await.unwrap();
+        self.manager.load_plugins().await;
         log::debug!("Plugins loaded: {}", self.manager.plugins.len());
         self.manager.commands().await
     }
@@ -96,6 +108,142 @@ impl<T: Backend> Message<ExecutePluginCommand> for PluginManagerActor<T> {
     }
 }
 
+/// The config schema of a plugin and its current config, for its settings
+/// form.
+pub struct GetPluginSettings {
+    pub plugin_name: String,
+}
+
+impl<T: Backend> Message<GetPluginSettings> for PluginManagerActor<T> {
+    type Reply = Result<(ConfigSchema, serde_json::Value), String>;
+
+    async fn handle(
+        &mut self,
+        msg: GetPluginSettings,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.manager
+            .current_settings(&msg.plugin_name)
+            .map_err(|e| format!("Failed to configure plugin '{}': {:#}", msg.plugin_name, e))
+    }
+}
+
+/// Saves settings submitted from a plugin's settings form. They apply the
+/// next time plugins are loaded.
+pub struct SavePluginSettings {
+    pub plugin_name: String,
+    pub settings: serde_json::Value,
+}
+
+impl<T: Backend> Message<SavePluginSettings> for PluginManagerActor<T> {
+    type Reply = Result<(), String>;
+
+    async fn handle(
+        &mut self,
+        msg: SavePluginSettings,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        settings::save(&msg.plugin_name, &msg.settings).map_err(|e| {
+            format!(
+                "Failed to save the settings of '{}': {:#}",
+                msg.plugin_name, e
+            )
+        })?;
+        log::info!("Saved settings of plugin '{}'", msg.plugin_name);
+        Ok(())
+    }
+}
+
+/// Leftovers of removed plugins, as of the current `plugins.lua`.
+pub struct FindOrphans;
+
+impl<T: Backend> Message<FindOrphans> for PluginManagerActor<T> {
+    type Reply = Result<Vec<Orphan>, String>;
+
+    async fn handle(
+        &mut self,
+        _msg: FindOrphans,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.manager
+            .load_config()
+            .await
+            .and_then(|_| self.manager.orphans())
+            .map_err(|e| format!("Failed to find removed plugins: {:#}", e))
+    }
+}
+
+/// Deletes leftovers the user agreed to let go of.
+pub struct PruneOrphans(pub Vec<Orphan>);
+
+impl<T: Backend> Message<PruneOrphans> for PluginManagerActor<T> {
+    /// Bytes freed
+    type Reply = Result<u64, String>;
+
+    async fn handle(
+        &mut self,
+        msg: PruneOrphans,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.manager
+            .prune(&msg.0)
+            .map_err(|e| format!("Failed to prune plugins: {:#}", e))
+    }
+}
+
+/// Palette changes plugins can hook into.
+pub enum PaletteEvent {
+    Shown,
+    Hidden,
+    QueryChanged(String),
+}
+
+impl<T: Backend> Message<PaletteEvent> for PluginManagerActor<T> {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        msg: PaletteEvent,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        let (hook, arg) = match msg {
+            PaletteEvent::Shown => (
+                Hook::OnShow,
+                serde_json::to_value(&self.manager.execution_context).ok(),
+            ),
+            PaletteEvent::Hidden => (Hook::OnHide, None),
+            PaletteEvent::QueryChanged(query) => (Hook::OnQueryChange, Some(query.into())),
+        };
+        self.manager.dispatch_hook(hook, arg);
+    }
+}
+
+pub struct UnloadPlugins;
+
+impl<T: Backend> Message<UnloadPlugins> for PluginManagerActor<T> {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _msg: UnloadPlugins,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.manager.shutdown().await;
+    }
+}
+
+impl<T: Backend> Message<SystemEvent> for PluginManagerActor<T> {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        msg: SystemEvent,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.manager.publish(&msg);
+    }
+}
+
 impl<T: Backend> Message<PluginExecuteContext> for PluginManagerActor<T> {
     type Reply = ();
 
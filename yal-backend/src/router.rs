@@ -1,5 +1,5 @@
 use kameo::actor::ActorRef;
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
 
 use crate::{
     application_tree::ApplicationTreeActor,
@@ -7,6 +7,7 @@ use crate::{
     common::Events,
     config::{ConfigActor, GetConfig, ReloadConfig},
     plugin_backend::PluginBackend,
+    scheduler::{Reschedule, SchedulerActor},
 };
 
 pub struct EventRouter {
@@ -45,25 +46,7 @@ impl EventRouter {
                 match event {
                     Events::ReloadConfig => {
                         log::info!("EventRouter: ReloadConfig event received");
-                        let _ = self.config_ref.tell(ReloadConfig).await;
-                        let config = self.config_ref.ask(GetConfig).await;
-                        if let Ok(cfg) = config {
-                            let _ = self
-                                .theme_ref
-                                .tell(crate::cmd::theme::ApplyTheme {
-                                    theme_name: cfg.theme.clone().unwrap_or_default(),
-                                })
-                                .await;
-
-                            crate::window::apply_window_size(&self.app_handle, &cfg);
-
-                            crate::window::position_main_window_on_mouse_display(
-                                &self.app_handle,
-                                &cfg,
-                            );
-
-                            let _ = self.app_handle.emit("config://updated", cfg);
-                        }
+                        reload_config(&self.app_handle, &self.config_ref, &self.theme_ref).await;
                     }
                     Events::RefreshTree => {
                         log::info!("EventRouter: RefreshTree event received");
@@ -113,9 +96,42 @@ impl EventRouter {
                             .ask(crate::plugin::InstallPlugins)
                             .await;
                     }
+                    Events::System(event) => {
+                        log::debug!("EventRouter: {} event received", event.topic());
+                        let _ = self.plugin_manager_ref.tell(event).await;
+                    }
                 }
             }
         });
         event_tx
     }
 }
+
+/// Reloads `config.lua` and applies it: theme, window size/position, and the
+/// `config://updated` event for the frontend.
+pub async fn reload_config(
+    app_handle: &tauri::AppHandle,
+    config_ref: &ActorRef<ConfigActor>,
+    theme_ref: &ActorRef<ThemeManagerActor>,
+) -> Option<yal_core::AppConfig> {
+    let _ = config_ref.tell(ReloadConfig).await;
+    let cfg = config_ref.ask(GetConfig).await.ok()?;
+    let _ = theme_ref
+        .tell(crate::cmd::theme::ApplyTheme {
+            theme_name: cfg.theme.clone().unwrap_or_default(),
+        })
+        .await;
+
+    crate::window::apply_window_size(app_handle, &cfg);
+    crate::window::position_main_window_on_mouse_display(app_handle, &cfg);
+
+    if let Some(scheduler_ref) = app_handle.try_state::<ActorRef<SchedulerActor>>() {
+        let _ = scheduler_ref
+            .tell(Reschedule(cfg.schedules.clone().unwrap_or_default()))
+            .await;
+    }
+
+    let _ = app_handle.emit("config://updated", cfg.clone());
+    crate::common::publish(app_handle, yal_plugin::events::SystemEvent::ConfigReloaded);
+    Some(cfg)
+}
@@ -0,0 +1,359 @@
+//! Runs the commands `schedules` in `config.lua` asks for: every few
+//! seconds, when a cron expression matches, or once at login.
+//!
+//! The time of each schedule's last run is kept in the state file. A run
+//! that came due while YAL was not running happens once as soon as the
+//! schedule starts again, unless its `catch_up` is false. Schedules seen for
+//! the first time count from then.
+
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeDelta};
+use croner::Cron;
+use kameo::{actor::ActorRef, prelude::Message, Actor};
+use tauri::{async_runtime::JoinHandle, Manager};
+use yal_core::{Command, ScheduledCommand};
+
+use crate::{
+    cmd::CommandActor,
+    state::{GetState, SetState, StateActor},
+};
+
+const STATE_KEY: &str = "schedules";
+
+/// Longest the scheduler sleeps before looking at the clock again, so it
+/// notices the clock changing or the machine waking up.
+const MAX_NAP: Duration = Duration::from_secs(60);
+
+/// Time of each timed schedule's last run, by name.
+pub type LastRuns = HashMap<String, DateTime<Local>>;
+
+#[derive(Debug, Clone)]
+pub enum Trigger {
+    Every(TimeDelta),
+    Cron(Box<Cron>),
+    AtLogin,
+}
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub name: String,
+    pub trigger: Trigger,
+    pub command: Command,
+    pub catch_up: bool,
+}
+
+impl Schedule {
+    pub fn parse(config: &ScheduledCommand) -> Result<Self, String> {
+        let trigger = match (config.every, &config.cron, config.at_login) {
+            (Some(seconds), None, false) => i64::try_from(seconds)
+                .ok()
+                .and_then(TimeDelta::try_seconds)
+                .filter(|period| *period > TimeDelta::zero())
+                .map(Trigger::Every)
+                .ok_or_else(|| format!("invalid `every`: {} seconds", seconds))?,
+            (None, Some(expr), false) => Cron::new(expr)
+                .with_seconds_optional()
+                .parse()
+                .map(|cron| Trigger::Cron(Box::new(cron)))
+                .map_err(|e| format!("invalid cron expression '{}': {}", expr, e))?,
+            (None, None, true) => Trigger::AtLogin,
+            _ => return Err("needs exactly one of `every`, `cron` and `at_login`".to_string()),
+        };
+        Ok(Self {
+            name: config.name.clone(),
+            trigger,
+            command: config.command.clone(),
+            catch_up: config.catch_up.unwrap_or(true),
+        })
+    }
+
+    /// When the schedule runs next after running at `last`.
+    fn next_after(&self, last: DateTime<Local>) -> Option<DateTime<Local>> {
+        match &self.trigger {
+            Trigger::Every(period) => Some(last + *period),
+            Trigger::Cron(cron) => cron.find_next_occurrence(&last, false).ok(),
+            Trigger::AtLogin => None,
+        }
+    }
+}
+
+pub trait Clock: Send + 'static {
+    fn now(&self) -> DateTime<Local>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// A scheduled command that came due.
+#[derive(Debug, Clone)]
+pub struct Fired {
+    pub name: String,
+    pub command: Command,
+    pub at: DateTime<Local>,
+}
+
+/// `last_runs` for `schedules`: timed schedules without a last run count
+/// from `now`, and removed schedules are forgotten.
+pub fn with_baselines(
+    schedules: &[Schedule],
+    mut last_runs: LastRuns,
+    now: DateTime<Local>,
+) -> LastRuns {
+    last_runs.retain(|name, _| schedules.iter().any(|s| &s.name == name));
+    for schedule in schedules {
+        if !matches!(schedule.trigger, Trigger::AtLogin) {
+            last_runs.entry(schedule.name.clone()).or_insert(now);
+        }
+    }
+    last_runs
+}
+
+/// Sends every schedule to `fired` when it is due, until nothing is left to
+/// run or the receiver is gone. `at_login` schedules only run if `login`.
+pub async fn run(
+    clock: impl Clock,
+    schedules: Vec<Schedule>,
+    last_runs: LastRuns,
+    login: bool,
+    fired: kanal::AsyncSender<Fired>,
+) {
+    let start = clock.now();
+    let mut next: Vec<Option<DateTime<Local>>> = schedules
+        .iter()
+        .map(|schedule| match schedule.trigger {
+            Trigger::AtLogin => login.then_some(start),
+            _ => {
+                let last = last_runs.get(&schedule.name).copied().unwrap_or(start);
+                match schedule.next_after(last) {
+                    Some(missed) if missed <= start && schedule.catch_up => Some(start),
+                    Some(missed) if missed <= start => schedule.next_after(start),
+                    due => due,
+                }
+            }
+        })
+        .collect();
+
+    while let Some(due) = next.iter().flatten().min().copied() {
+        while let Ok(wait) = (due - clock.now()).to_std() {
+            if wait.is_zero() {
+                break;
+            }
+            tokio::time::sleep(wait.min(MAX_NAP)).await;
+        }
+        let now = clock.now();
+        for (schedule, next) in schedules.iter().zip(&mut next) {
+            let Some(at) = next.filter(|at| *at <= now) else {
+                continue;
+            };
+            // Keep to the schedule unless runs were missed meanwhile.
+            *next = schedule
+                .next_after(at)
+                .filter(|after| *after > now)
+                .or_else(|| schedule.next_after(now));
+            let fired_command = Fired {
+                name: schedule.name.clone(),
+                command: schedule.command.clone(),
+                at: now,
+            };
+            if fired.send(fired_command).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[derive(Actor)]
+pub struct SchedulerActor {
+    app_handle: tauri::AppHandle,
+    tasks: Vec<JoinHandle<()>>,
+    started: bool,
+}
+
+impl SchedulerActor {
+    pub fn new(app_handle: tauri::AppHandle) -> Self {
+        Self {
+            app_handle,
+            tasks: Vec::new(),
+            started: false,
+        }
+    }
+}
+
+/// Replaces the running schedules with these, from `config.lua`.
+pub struct Reschedule(pub Vec<ScheduledCommand>);
+
+impl Message<Reschedule> for SchedulerActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        msg: Reschedule,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        let login = !std::mem::replace(&mut self.started, true);
+
+        let mut schedules: Vec<Schedule> = Vec::new();
+        for config in &msg.0 {
+            if schedules.iter().any(|s| s.name == config.name) {
+                log::warn!("Ignoring schedule '{}': name already used", config.name);
+                continue;
+            }
+            match Schedule::parse(config) {
+                Ok(schedule) => schedules.push(schedule),
+                Err(e) => log::warn!("Ignoring schedule '{}': {}", config.name, e),
+            }
+        }
+
+        let last_runs = with_baselines(&schedules, load(&self.app_handle).await, Local::now());
+        if let Err(e) = save(&self.app_handle, &last_runs).await {
+            log::warn!("Failed saving schedule run times: {}", e);
+        }
+        log::info!("Scheduled {} commands", schedules.len());
+
+        let (tx, rx) = kanal::unbounded_async();
+        self.tasks.push(tauri::async_runtime::spawn(run(
+            SystemClock,
+            schedules,
+            last_runs.clone(),
+            login,
+            tx,
+        )));
+        let app_handle = self.app_handle.clone();
+        self.tasks.push(tauri::async_runtime::spawn(async move {
+            let mut last_runs = last_runs;
+            while let Ok(fired) = rx.recv().await {
+                log::info!("Running scheduled command '{}'", fired.name);
+                let cmd_ref = app_handle.state::<ActorRef<CommandActor>>();
+                if let Err(e) = cmd_ref.ask(fired.command).await {
+                    log::warn!("Scheduled command '{}' failed: {}", fired.name, e);
+                }
+                if last_runs.contains_key(&fired.name) {
+                    last_runs.insert(fired.name, fired.at);
+                    if let Err(e) = save(&app_handle, &last_runs).await {
+                        log::warn!("Failed saving schedule run times: {}", e);
+                    }
+                }
+            }
+        }));
+    }
+}
+
+/// Saved as Unix timestamps.
+async fn load(app_handle: &tauri::AppHandle) -> LastRuns {
+    let state_ref = app_handle.state::<ActorRef<StateActor>>();
+    let saved = state_ref
+        .ask(GetState::<HashMap<String, i64>>::new(STATE_KEY))
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    saved
+        .into_iter()
+        .filter_map(|(name, secs)| {
+            let at = DateTime::from_timestamp(secs, 0)?;
+            Some((name, at.with_timezone(&Local)))
+        })
+        .collect()
+}
+
+async fn save(app_handle: &tauri::AppHandle, last_runs: &LastRuns) -> Result<()> {
+    let saved: HashMap<&str, i64> = last_runs
+        .iter()
+        .map(|(name, at)| (name.as_str(), at.timestamp()))
+        .collect();
+    let state_ref = app_handle.state::<ActorRef<StateActor>>();
+    state_ref
+        .ask(SetState::new(STATE_KEY, &saved)?)
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// Wall time that moves with tokio's (paused) clock.
+    struct PausedClock {
+        start: DateTime<Local>,
+        instant: tokio::time::Instant,
+    }
+
+    impl Clock for PausedClock {
+        fn now(&self) -> DateTime<Local> {
+            self.start + TimeDelta::from_std(self.instant.elapsed()).unwrap()
+        }
+    }
+
+    fn schedule(name: &str, every: Option<u64>, cron: Option<&str>, catch_up: bool) -> Schedule {
+        Schedule::parse(&ScheduledCommand {
+            name: name.to_string(),
+            command: Command::Theme(name.to_string()),
+            every,
+            cron: cron.map(str::to_string),
+            at_login: every.is_none() && cron.is_none(),
+            catch_up: Some(catch_up),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_due_and_missed_schedules() {
+        let at = |day, h, m| Local.with_ymd_and_hms(2026, 3, day, h, m, 0).unwrap();
+        let start = at(2, 9, 0);
+        let schedules = vec![
+            schedule("login", None, None, true),
+            schedule("hourly", Some(3600), None, true),
+            schedule("morning", None, Some("0 8 * * *"), true),
+            schedule("skipped", None, Some("30 8 * * *"), false),
+            schedule("new", Some(1800), None, true),
+        ];
+        let last_runs = LastRuns::from([
+            ("hourly".to_string(), at(2, 7, 30)),
+            ("morning".to_string(), at(1, 8, 0)),
+            ("skipped".to_string(), at(1, 8, 30)),
+            ("removed".to_string(), at(1, 8, 30)),
+        ]);
+        let last_runs = with_baselines(&schedules, last_runs, start);
+        assert!(!last_runs.contains_key("removed"));
+        assert_eq!(last_runs.get("new"), Some(&start));
+
+        let clock = PausedClock {
+            start,
+            instant: tokio::time::Instant::now(),
+        };
+        let (tx, rx) = kanal::unbounded_async();
+        tokio::spawn(run(clock, schedules, last_runs, true, tx));
+
+        let end = tokio::time::Instant::now() + Duration::from_secs(2 * 3600 + 60);
+        let mut fired = Vec::new();
+        while let Ok(Ok(f)) = tokio::time::timeout_at(end, rx.recv()).await {
+            fired.push(format!("{} {}", f.at.format("%H:%M"), f.name));
+        }
+        assert_eq!(
+            fired,
+            vec![
+                "09:00 login",
+                "09:00 hourly",
+                "09:00 morning",
+                "09:30 new",
+                "10:00 hourly",
+                "10:00 new",
+                "10:30 new",
+                "11:00 hourly",
+                "11:00 new",
+            ]
+        );
+    }
+}
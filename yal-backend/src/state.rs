@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use kameo::prelude::Message;
+use kameo::Actor;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+/// Small persistent key/value store for app state that must survive restarts
+/// but doesn't belong in the user's config files.
+#[derive(Actor)]
+pub struct StateActor {
+    path: PathBuf,
+    state: Map<String, Value>,
+}
+
+impl StateActor {
+    pub fn new() -> Self {
+        let path = state_path();
+        let state = load_state(&path);
+        Self { path, state }
+    }
+
+    fn persist(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        {
+            let mut f = fs::File::create(&tmp)?;
+            f.write_all(&serde_json::to_vec_pretty(&self.state)?)?;
+            f.flush()?;
+        }
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+pub struct GetState<T> {
+    pub key: String,
+    _marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> GetState<T> {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static> Message<GetState<T>> for StateActor {
+    type Reply = Option<T>;
+
+    async fn handle(
+        &mut self,
+        msg: GetState<T>,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.state
+            .get(&msg.key)
+            .cloned()
+            .and_then(|v| serde_json::from_value(v).ok())
+    }
+}
+
+pub struct SetState {
+    pub key: String,
+    pub value: Value,
+}
+
+impl SetState {
+    pub fn new<T: Serialize>(key: impl Into<String>, value: &T) -> Result<Self> {
+        Ok(Self {
+            key: key.into(),
+            value: serde_json::to_value(value)?,
+        })
+    }
+}
+
+impl Message<SetState> for StateActor {
+    type Reply = Result<()>;
+
+    async fn handle(
+        &mut self,
+        msg: SetState,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.state.insert(msg.key, msg.value);
+        self.persist()
+    }
+}
+
+/// Changes the value under `key` in place, starting from `T::default()` when
+/// there is none or it no longer parses. Read-modify-write updates go through
+/// this rather than `GetState` and `SetState`, so concurrent ones can't
+/// overwrite each other.
+pub struct UpdateState<T> {
+    pub key: String,
+    update: Box<dyn FnOnce(&mut T) + Send>,
+}
+
+impl<T> UpdateState<T> {
+    pub fn new(key: impl Into<String>, update: impl FnOnce(&mut T) + Send + 'static) -> Self {
+        Self {
+            key: key.into(),
+            update: Box::new(update),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Default + Send + 'static> Message<UpdateState<T>>
+    for StateActor
+{
+    type Reply = Result<()>;
+
+    async fn handle(
+        &mut self,
+        msg: UpdateState<T>,
+        _ctx: &mut kameo::prelude::Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        let mut value: T = self
+            .state
+            .get(&msg.key)
+            .cloned()
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default();
+        (msg.update)(&mut value);
+        self.state.insert(msg.key, serde_json::to_value(&value)?);
+        self.persist()
+    }
+}
+
+fn load_state(path: &Path) -> Map<String, Value> {
+    match fs::read_to_string(path) {
+        Ok(s) => match serde_json::from_str::<Value>(&s) {
+            Ok(Value::Object(map)) => map,
+            _ => {
+                log::warn!("Ignoring malformed state file {}", path.display());
+                Map::new()
+            }
+        },
+        Err(_) => Map::new(),
+    }
+}
+
+pub fn state_path() -> PathBuf {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| !p.as_os_str().is_empty())
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .map(|h| h.join(".local/state"))
+        })
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("yal/state.json")
+}